@@ -1,34 +1,58 @@
+use super::{
+    configuration::ConfigurationArgs,
+    credits::{render_credits_html, render_credits_markdown},
+    discovery::{discover_shaderpacks, is_listed},
+    licenses::{describe_shaderpack_license, resolve_shaderpack_license},
+    lock::{check_lock, generate_lock, update_lock},
+};
+use crate::{
+    source::ShaderPackSource,
+    validation::{find_dependents, has_errors, validate_shaderpack, ProblemType},
+};
 use anyhow::{anyhow, bail, Context, Result};
 use clap::Parser;
+use colored::Colorize;
 use shaded_models::{
+    addon::{AddonBinary, AddonReadErrorKind},
     collection::{
+        collection_archive_filename,
         constants::{
-            ADDON_DIRECTORY_NAME, COLLECTION_ARCHIVE_FILENAME, COLLECTION_MANIFEST_FILENAME,
-            LICENSE_DIRECTORY_NAME, PRESET_DIRECTORY_NAME, SHADER_DIRECTORY_NAME,
-            TEXTURE_DIRECTORY_NAME,
+            ADDON_DIRECTORY_NAME, COLLECTION_MANIFEST_FILENAME, CREDITS_FILENAME,
+            CREDITS_HTML_FILENAME, EFFECT_METADATA_VERSION, LICENSE_DIRECTORY_NAME,
+            PRESET_DIRECTORY_NAME, SHADER_DIRECTORY_NAME, TEXTURE_DIRECTORY_NAME,
         },
-        CollectionManifest, CollectionShaderPack,
+        license_file_name, sanitize_file_name, CollectionEffect, CollectionEffectMetadata,
+        CollectionEffectTechnique, CollectionEffectUniform, CollectionManifest,
+        CollectionShaderPack,
     },
-    collection_configuration::CollectionConfiguration,
-    shaderpack::{constants::SHADERPACK_MANIFEST_FILENAME, ShaderPackManifest},
+    collection_configuration::{CollectionConfiguration, NameClashPolicy},
+    effect::{EffectFile, EFFECT_ENTRYPOINT_EXTENSION},
+    image::ImageInfo,
+    reshade::ReShadeVersionRequirement,
+    shaderpack::{FileCategory, FileDeclaration, ShaderPackManifest},
 };
 use std::{
-    fs::{self, create_dir_all, read_to_string, OpenOptions},
-    io::Write,
-    path::PathBuf,
+    collections::{BTreeMap, BTreeSet},
+    fs::{self, create_dir_all, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    process::ExitCode,
 };
-use walkdir::{DirEntry, WalkDir};
 use zip_extensions::zip_create_from_directory;
 
 const BUILD_FILES_SUBDIRECTORY_NAME: &str = ".build";
 
+/// The exit code used when a collection was built, but some shaderpacks were skipped.
+const EXIT_CODE_BUILT_WITH_SKIPS: u8 = 2;
+
 /// Build a collection using the provided configuration file and output it to the given directory
 /// as a zip archive.
+///
+/// Exits with code 2 if the collection was built but some shaderpacks were skipped.
 #[derive(Debug, Parser)]
 pub struct BuildCommand {
-    /// Path to the collection configuration file.
-    #[arg(short = 'c', long = "configuration")]
-    configuration_file_path: PathBuf,
+    #[command(flatten)]
+    configuration: ConfigurationArgs,
 
     /// Path to a directory that the built collection archive should be placed at.
     #[arg(short = 'o', long = "output")]
@@ -37,18 +61,34 @@ pub struct BuildCommand {
     /// Delete any existing artifacts generated by another build.
     #[arg(short = 'd', long = "delete-existing")]
     delete_existing: bool,
+
+    /// Build a separate archive for every profile in the configuration.
+    #[arg(long = "all-profiles", conflicts_with = "profile")]
+    all_profiles: bool,
+
+    /// Build without the shaderpacks that fail validation instead of failing the build.
+    #[arg(long = "skip-invalid")]
+    skip_invalid: bool,
+
+    /// Fail the build if any shaderpack is skipped instead of exiting with a distinct exit code.
+    #[arg(long = "strict")]
+    strict: bool,
+
+    /// Fail if the collection lock file is missing or does not match the current inputs instead of updating it.
+    #[arg(long = "locked")]
+    locked: bool,
 }
 
 impl BuildCommand {
-    pub fn run(&self) -> Result<()> {
+    pub fn run(&self) -> Result<ExitCode> {
         // Prevent overwriting an existing collection build output unless specified to delete it.
         if self.output_path.exists()
-            && !self
+            && self
                 .output_path
                 .read_dir()
                 .context("Failed to read output path directory")?
                 .next()
-                .is_none()
+                .is_some()
         {
             if !self.delete_existing {
                 return Err(anyhow!("There are already files in the output directory. Pass the --delete-existing flag to delete any existing files."));
@@ -56,140 +96,845 @@ impl BuildCommand {
             fs::remove_dir_all(&self.output_path).context("Failed to existing output directory")?;
         }
 
+        let profiles = self.configuration.selected_profiles(self.all_profiles)?;
+
+        // Validate every shaderpack before anything is packed.
+        let invalid = self.validate_shaderpacks(&profiles)?;
+
+        // Check the inputs of the collection against the lock file before building in locked mode.
+        let invalid_sources: Vec<_> = invalid.iter().map(|(source, _)| source.clone()).collect();
+        let lock = generate_lock(&self.configuration, &profiles, &invalid_sources)?;
+        if self.locked {
+            check_lock(
+                &self.configuration.configuration_file_path,
+                &lock,
+                &profiles,
+            )?;
+        }
+
+        let mut skipped = 0;
+        for profile in &profiles {
+            if let Some(profile) = profile {
+                println!("Building profile {}", profile);
+                println!();
+            }
+            let result = self.build(profile.as_deref(), &invalid);
+            if result.is_err() {
+                self.remove_build_directory(profile.as_deref());
+            }
+            skipped += result?;
+        }
+
+        // Record the inputs of the successful build in the lock file.
+        if !self.locked {
+            for change in update_lock(
+                &self.configuration.configuration_file_path,
+                &lock,
+                &profiles,
+            )? {
+                println!(" * {}", change);
+            }
+        }
+
+        if skipped > 0 {
+            println!(
+                "{}",
+                format!(
+                    "Collection was built, but {} shaderpacks were skipped",
+                    skipped
+                )
+                .yellow()
+            );
+            return Ok(ExitCode::from(EXIT_CODE_BUILT_WITH_SKIPS));
+        }
+        Ok(ExitCode::SUCCESS)
+    }
+
+    /// Validate every shaderpack included by the given profiles, reporting the errors of all of them at once.
+    ///
+    /// A shaderpack is validated again for every profile that applies different overrides to it.
+    ///
+    /// Fails if any shaderpack is invalid, unless invalid shaderpacks should be skipped, in which case
+    /// their sources and manifests with the overrides applied are returned.
+    fn validate_shaderpacks(
+        &self,
+        profiles: &[Option<String>],
+    ) -> Result<Vec<(ShaderPackSource, ShaderPackManifest)>> {
+        println!("Validating shaderpacks");
+        let mut validated: Vec<(ShaderPackSource, ShaderPackManifest)> = vec![];
+        let mut invalid = vec![];
+        for profile in profiles {
+            let configuration = self.configuration.load_profile(profile.as_deref())?;
+            let discovered =
+                discover_shaderpacks(&self.configuration.configuration_file_path, &configuration)?;
+            discovered.report(profile.as_deref().filter(|_| profiles.len() > 1));
+            let shaderpacks = discovered.shaderpacks;
+            let dependents = find_dependents(shaderpacks.iter().map(|(_, manifest)| manifest));
+            for (source, manifest) in shaderpacks {
+                if is_listed(&validated, &source, &manifest) {
+                    continue;
+                }
+                let name = match profile {
+                    Some(profile) if profiles.len() > 1 => {
+                        format!("{} ({})", manifest.name, profile)
+                    }
+                    _ => manifest.name.clone(),
+                };
+
+                let mut warning_count = 0;
+                let sections = validate_shaderpack(
+                    &source,
+                    &manifest,
+                    dependents.get(&manifest.name).map_or(&[], Vec::as_slice),
+                )?;
+                for (key, problem) in sections.iter().flat_map(|section| &section.problems) {
+                    match problem {
+                        ProblemType::Warning(_) => warning_count += 1,
+                        ProblemType::Error(err) => eprintln!(
+                            "{}",
+                            format!(" * [{}] Error with {}: {:#}", name, key, err).red()
+                        ),
+                    }
+                }
+                if warning_count > 0 {
+                    println!(
+                        "{}",
+                        format!(
+                            " * [{}] {} validation warnings, run `shaded shaderpack validate` for details",
+                            name, warning_count
+                        )
+                        .yellow()
+                    );
+                }
+
+                if has_errors(&sections) {
+                    invalid.push((source.clone(), manifest.clone(), name));
+                }
+                validated.push((source, manifest));
+            }
+        }
+        println!();
+
+        if invalid.is_empty() {
+            return Ok(vec![]);
+        }
+        let names = invalid
+            .iter()
+            .map(|(_, _, name)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        if !self.skip_invalid {
+            bail!(
+                "The following shaderpacks are invalid: {}. Fix the errors above, or pass --skip-invalid to build without them.",
+                names
+            );
+        }
+        eprintln!(
+            "{}",
+            format!("Skipping invalid shaderpacks: {}", names).yellow()
+        );
+        println!();
+        Ok(invalid
+            .into_iter()
+            .map(|(source, manifest, _)| (source, manifest))
+            .collect())
+    }
+
+    /// Get the directory the files of a profile are placed in before they are archived.
+    fn build_directory(&self, profile: Option<&str>) -> PathBuf {
+        let mut build_directory = self.output_path.join(BUILD_FILES_SUBDIRECTORY_NAME);
+        if let Some(profile) = profile {
+            build_directory.push(sanitize_file_name(profile));
+        }
+        build_directory
+    }
+
+    /// Remove the files of a failed build of a profile, so they can't be mistaken for a finished build.
+    fn remove_build_directory(&self, profile: Option<&str>) {
+        let build_directory = self.build_directory(profile);
+        if let Err(err) = fs::remove_dir_all(&build_directory) {
+            if err.kind() != io::ErrorKind::NotFound {
+                eprintln!(
+                    "{}",
+                    format!(
+                        "Failed to remove the build directory {:?}: {}",
+                        build_directory, err
+                    )
+                    .yellow()
+                );
+            }
+        }
+        // Only succeeds if no other profile was built into it.
+        let _ = fs::remove_dir(self.output_path.join(BUILD_FILES_SUBDIRECTORY_NAME));
+    }
+
+    /// Build the collection archive for a single profile, or the base configuration if there is none.
+    ///
+    /// Returns the number of shaderpacks that were skipped.
+    ///
+    /// # Arguments
+    /// * `invalid`: The shaderpacks that failed validation, which are skipped.
+    fn build(
+        &self,
+        profile: Option<&str>,
+        invalid: &[(ShaderPackSource, ShaderPackManifest)],
+    ) -> Result<usize> {
         // Create a build directory to temporarily place all files in before they're turned into an archive.
-        let temp_build_directory = self.output_path.join(BUILD_FILES_SUBDIRECTORY_NAME);
+        let temp_build_directory = self.build_directory(profile);
         create_dir_all(&temp_build_directory)
             .context("Failed to create a temporary build directory")?;
 
         // Load the collection config and use its parent directory of it as the search directory base.
-        let configuration: CollectionConfiguration = toml::from_str(
-            &fs::read_to_string(&self.configuration_file_path)
-                .context("Failed to read configuration")?,
-        )
-        .context("Failed to load toml from configuration")?;
-
-        // Recursively find shaderpack manifests.
-        let directories: Vec<DirEntry> = WalkDir::new(
-            self.configuration_file_path
-                .parent()
-                .context("Failed to get the parent directory of the configuration file")?
-                .join(configuration.search_directory.path)
-                .canonicalize()
-                .context("Failed to canconicalize configuration search directory")?,
-        )
-        .max_depth(configuration.search_directory.max_depth)
-        .into_iter()
-        .filter_map(|e| e.ok()) // Only grab DirEntry's that are not errors.
-        .filter(|e| {
-            e.metadata()
-                .map_or(false, |m| m.is_dir()) // DirEntry must be a directory.
-                && e.path().join(SHADERPACK_MANIFEST_FILENAME).exists() // DirEntry must have a shader manifest.
-        })
-        .collect();
+        let configuration = self.configuration.load_profile(profile)?;
 
-        // If no shaderpack manifests are found, return early with an error.
-        if directories.len() == 0 {
-            bail!("No manifests could be found inside of the search directory or {} directories under it", configuration.search_directory.max_depth);
+        // Find every shaderpack included by the configuration.
+        let shaderpacks =
+            discover_shaderpacks(&self.configuration.configuration_file_path, &configuration)?
+                .shaderpacks;
+
+        // If no shaderpacks are found, return early with an error.
+        if shaderpacks.is_empty() {
+            bail!("No shaderpacks were found using the shaderpacks, search directories and filters of the configuration");
         }
 
         // For every directory with a manifest found, read the manifest and pack it into the collection.
         let mut included_shaderpacks = vec![];
-        for directory in directories {
-            let directory = directory.path();
-            let manifest: ShaderPackManifest = serde_json::from_str(&read_to_string(
-                directory.join(SHADERPACK_MANIFEST_FILENAME),
-            )?)?;
+        let mut collection_effects = vec![];
+        let mut collection_presets = vec![];
+        let mut technique_owners: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let mut effect_file_owners: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        let mut license_file_names = BTreeSet::new();
+        let mut outcomes = vec![];
+
+        // Combine the ReShade versions of the shaderpacks that can be included, comparing each of them
+        // against the others so that every shaderpack involved in a conflict is reported.
+        let compatible_reshade_versions: Vec<_> = shaderpacks
+            .iter()
+            .filter(|(source, manifest)| !is_listed(invalid, source, manifest))
+            .filter_map(|(_, manifest)| {
+                let compatible = manifest
+                    .reshade_version
+                    .intersection(&configuration.reshade_version)?;
+                Some((manifest.name.as_str(), compatible))
+            })
+            .collect();
+        let (reshade_version, reshade_version_conflicts) = match combine_reshade_versions(
+            &configuration.reshade_version,
+            &compatible_reshade_versions,
+        ) {
+            Ok(reshade_version) => (reshade_version, BTreeMap::new()),
+            Err(conflicts) => (configuration.reshade_version.clone(), conflicts),
+        };
 
+        for (source, manifest) in shaderpacks {
             println!("Processing {} by {:?}:", manifest.name, manifest.authors);
-            if manifest.reshade_version != configuration.reshade_version {
+            if is_listed(invalid, &source, &manifest) {
+                eprintln!(" * This shaderpack failed validation!");
+                println!();
+                outcomes.push((
+                    manifest.name,
+                    ShaderPackOutcome::Skipped("failed validation".to_owned()),
+                ));
+                continue;
+            }
+
+            // Skip shaderpacks that support none of the ReShade versions the collection is built for.
+            if !manifest
+                .reshade_version
+                .overlaps(&configuration.reshade_version)
+            {
                 eprintln!(
-                    " * This collection is for a different ReShade version! {} != {}",
+                    " * This shaderpack does not support any ReShade version this collection is for! {} does not overlap {}",
                     manifest.reshade_version, configuration.reshade_version
                 );
                 println!();
+                outcomes.push((
+                    manifest.name,
+                    ShaderPackOutcome::Skipped(format!(
+                        "supports ReShade {}, which does not overlap {}",
+                        manifest.reshade_version, configuration.reshade_version
+                    )),
+                ));
+                continue;
+            };
+            if let Some(conflicting) = reshade_version_conflicts.get(&manifest.name) {
+                eprintln!(
+                    " * This shaderpack has no supported ReShade version in common with other shaderpacks! {} does not overlap {}",
+                    manifest.reshade_version,
+                    conflicting.join(", ")
+                );
+                println!();
+                outcomes.push((
+                    manifest.name,
+                    ShaderPackOutcome::Failed(format!(
+                        "supports ReShade {}, which does not overlap {}",
+                        manifest.reshade_version,
+                        conflicting.join(", ")
+                    )),
+                ));
                 continue;
             }
+            println!(
+                "[{}] ReShade version: {}",
+                manifest.name, manifest.reshade_version
+            );
 
-            // Copy shaders to the build directory.
-            let shaders = manifest.shaders.unwrap_or_default();
-            let shader_path = temp_build_directory.join(SHADER_DIRECTORY_NAME);
-            for shader in &shaders {
-                println!("[{}] Packing shader: {:?}", manifest.name, shader.source);
-                shader.copy_to_output_path(&directory, &shader_path)?;
-            }
-
-            // Copy textures to the build directory.
-            let textures = manifest.textures.unwrap_or_default();
-            let texture_path = temp_build_directory.join(TEXTURE_DIRECTORY_NAME);
-            for texture in &textures {
-                println!("[{}] Packing texture: {:?}", manifest.name, texture.source);
-                texture.copy_to_output_path(&directory, &texture_path)?;
-            }
-
-            // Copy presets to the build directory.
-            let presets = manifest.presets.unwrap_or_default();
-            let preset_path = temp_build_directory.join(PRESET_DIRECTORY_NAME);
-            for preset in &presets {
-                println!("[{}] Packing preset: {:?}", manifest.name, preset.source);
-                preset.copy_to_output_path(&directory, &preset_path)?;
+            // Check the license against the collection license policy.
+            let license = resolve_shaderpack_license(&source, &manifest);
+            println!(
+                "[{}] License: {}",
+                manifest.name,
+                describe_shaderpack_license(&license)
+            );
+            let mut notes = vec![];
+            if let Err(violation) = configuration.license_policy.check(&license) {
+                eprintln!(
+                    " * License is not allowed by the collection license policy: {}",
+                    violation
+                );
+                let reason = format!("license is not allowed by the policy: {}", violation);
+                if configuration.license_policy.fail_on_violation {
+                    println!();
+                    outcomes.push((manifest.name, ShaderPackOutcome::Failed(reason)));
+                    continue;
+                }
+                notes.push(reason);
             }
 
-            // Copy addons to build directory.
-            let addons = &manifest.addons.unwrap_or_default();
-            let addon_path = temp_build_directory.join(ADDON_DIRECTORY_NAME);
-            for addon in addons {
-                println!("[{}] Packing addon: {:?}", manifest.name, addon.source);
-                addon.copy_to_output_path(&directory, &addon_path)?;
+            // Parse effects to record their metadata and so presets can be checked against the whole collection.
+            let effects: Vec<_> = manifest
+                .load_effects(|path| source.read(path))
+                .into_iter()
+                .filter(|(shader, _)| shader.has_extension(&[EFFECT_ENTRYPOINT_EXTENSION]))
+                .collect();
+            let effect_metadata = (!effects.is_empty()).then(|| CollectionEffectMetadata {
+                metadata_version: EFFECT_METADATA_VERSION,
+                effects: effects
+                    .iter()
+                    .map(|(shader, effect)| collection_effect(shader, effect))
+                    .collect(),
+            });
+            for (shader, effect) in &effects {
+                effect_file_owners
+                    .entry(shader.output_file_name().to_lowercase())
+                    .or_default()
+                    .insert(manifest.name.clone());
+                for technique in &effect.techniques {
+                    technique_owners
+                        .entry(technique.name.clone())
+                        .or_default()
+                        .insert(manifest.name.clone());
+                }
             }
+            collection_effects.extend(
+                effects
+                    .into_iter()
+                    .map(|(shader, effect)| (shader.output_file_name(), effect)),
+            );
+            collection_presets.extend(
+                manifest
+                    .load_presets(|path| source.read(path))
+                    .into_iter()
+                    .map(|(declaration, preset)| {
+                        (
+                            format!("[{}] {}", manifest.name, declaration.source.display()),
+                            preset,
+                        )
+                    }),
+            );
 
-            // Copy licenses to build directory.
-            if let Some(manifest_license_path) = manifest.license_file.map(|p| directory.join(p)) {
-                let license_path = temp_build_directory.join(LICENSE_DIRECTORY_NAME);
-                println!("[{}] Writing license data", manifest.name);
-                fs::create_dir_all(&license_path)?;
-                fs::copy(
-                    manifest_license_path,
-                    license_path.join(format!("LICENSE-{}", manifest.name)),
-                )
-                .context("Failed to write license")?;
-            }
+            // Copy the files of this shaderpack to the build directory.
+            let packed = match pack_shaderpack_files(
+                &source,
+                &manifest,
+                &configuration,
+                &temp_build_directory,
+                &mut license_file_names,
+                &mut notes,
+            ) {
+                Ok(packed) => packed,
+                Err(err) => {
+                    eprintln!(" * Failed to pack shaderpack: {:?}", err);
+                    println!();
+                    outcomes.push((
+                        manifest.name,
+                        ShaderPackOutcome::Failed(format!("{:#}", err)),
+                    ));
+                    continue;
+                }
+            };
 
             // Add this shaderpack to the list of this collection's includued shaderpacks.
+            outcomes.push((manifest.name.clone(), ShaderPackOutcome::Included(notes)));
             included_shaderpacks.push(CollectionShaderPack {
                 name: manifest.name,
+                version: manifest.version,
                 authors: manifest.authors,
                 description: manifest.description,
                 images: manifest.images,
-                shader_count: shaders.len(),
-                texture_count: textures.len(),
-                preset_count: textures.len(),
-                addon_count: addons.len(),
+                shader_count: packed.shader_count,
+                texture_count: packed.texture_count,
+                preset_count: packed.preset_count,
+                addon_count: packed.addon_count,
+                license: license.expression,
+                files: packed.files,
+                provenance: source.provenance(),
+                effect_metadata,
             });
 
             println!();
         }
 
+        // Stop before writing anything else if a shaderpack failed, or was skipped in strict mode.
+        let failed = shaderpack_names(&outcomes, |outcome| {
+            matches!(outcome, ShaderPackOutcome::Failed(_))
+        });
+        let skipped = shaderpack_names(&outcomes, |outcome| {
+            matches!(outcome, ShaderPackOutcome::Skipped(_))
+        });
+        if !failed.is_empty() {
+            print_build_summary(&outcomes);
+            bail!(
+                "The following shaderpacks failed to build: {}",
+                failed.join(", ")
+            );
+        }
+        if included_shaderpacks.is_empty() {
+            print_build_summary(&outcomes);
+            bail!("No shaderpacks were included in the collection");
+        }
+        if !skipped.is_empty() && self.strict {
+            print_build_summary(&outcomes);
+            bail!(
+                "The following shaderpacks were skipped, which is not allowed with --strict: {}",
+                skipped.join(", ")
+            );
+        }
+
+        // Report preset references that do not resolve to any effect inside of the collection.
+        println!("Validating presets against the collection");
+        for (preset_name, preset) in &collection_presets {
+            for error in preset.unresolved_references(&collection_effects) {
+                eprintln!(" * {}: {}", preset_name, error);
+            }
+        }
+        println!();
+
+        // Report techniques and effect files that are provided by more than one shaderpack, as ReShade
+        // identifies effects by these names.
+        if configuration.name_clash_policy != NameClashPolicy::Allow {
+            println!("Checking for technique and effect name clashes between shaderpacks");
+            let mut clash_found = false;
+            for (kind, owners) in [
+                ("Technique", &technique_owners),
+                ("Effect file", &effect_file_owners),
+            ] {
+                for (name, shaderpacks) in owners.iter().filter(|(_, packs)| packs.len() > 1) {
+                    clash_found = true;
+                    eprintln!(
+                        " * {} '{}' is provided by multiple shaderpacks: {}",
+                        kind,
+                        name,
+                        shaderpacks.iter().cloned().collect::<Vec<_>>().join(", ")
+                    );
+                }
+            }
+            println!();
+
+            if clash_found && configuration.name_clash_policy == NameClashPolicy::Fail {
+                bail!("Technique or effect file names clash between shaderpacks. Set name_clash_policy to \"warn\" or \"allow\" in the configuration to build anyway.");
+            }
+        }
+
+        println!("Collection supports ReShade versions: {}", reshade_version);
+
         // Write a collection.json file to the build directory root.
         println!("Writing collection.json file with collection data");
 
-        let collection = serde_json::to_string_pretty(&CollectionManifest {
+        let collection_manifest = CollectionManifest {
             manifest_version: 1,
             name: configuration.name,
+            version: configuration.version,
             description: configuration.description,
-            reshade_version: configuration.reshade_version,
+            reshade_version,
+            target_architecture: configuration.target_architecture,
+            profile: profile.map(str::to_owned),
             shader_packs: included_shaderpacks,
-        })?;
+        };
+        let collection = serde_json::to_string_pretty(&collection_manifest)?;
         let mut collection_file = OpenOptions::new()
             .write(true)
             .create(true)
-            .open(&temp_build_directory.join(COLLECTION_MANIFEST_FILENAME))?;
+            .truncate(true)
+            .open(temp_build_directory.join(COLLECTION_MANIFEST_FILENAME))?;
         collection_file.write_all(collection.as_bytes())?;
         collection_file.flush()?;
 
+        // Write the credits for every included shaderpack to the build directory root.
+        println!("Writing {} file with shaderpack credits", CREDITS_FILENAME);
+        fs::write(
+            temp_build_directory.join(CREDITS_FILENAME),
+            render_credits_markdown(&collection_manifest),
+        )
+        .context("Failed to write credits")?;
+        if configuration.credits.html {
+            println!(
+                "Writing {} file with shaderpack credits",
+                CREDITS_HTML_FILENAME
+            );
+            fs::write(
+                temp_build_directory.join(CREDITS_HTML_FILENAME),
+                render_credits_html(&collection_manifest),
+            )
+            .context("Failed to write HTML credits")?;
+        }
+
         // Zip
-        let zip_path = self.output_path.join(COLLECTION_ARCHIVE_FILENAME);
+        let zip_path = self.output_path.join(collection_archive_filename(profile));
         zip_create_from_directory(&zip_path, &temp_build_directory)?;
+        println!();
+
+        print_build_summary(&outcomes);
+        Ok(skipped.len())
+    }
+}
+
+/// Get the ReShade versions supported by every shaderpack, independent of the order of the shaderpacks.
+///
+/// Fails with the shaderpacks that conflict, mapping the name of each to the shaderpacks it has no version
+/// in common with, e.g. `Bloom (5.x)`. If every pair of shaderpacks overlaps but no version is supported by
+/// all of them, every shaderpack conflicts with all of the others.
+///
+/// # Arguments
+/// * `base`: The ReShade versions the collection is built for.
+/// * `requirements`: The name of each shaderpack and the ReShade versions it supports.
+fn combine_reshade_versions(
+    base: &ReShadeVersionRequirement,
+    requirements: &[(&str, ReShadeVersionRequirement)],
+) -> Result<ReShadeVersionRequirement, BTreeMap<String, Vec<String>>> {
+    if let Some(combined) = requirements
+        .iter()
+        .try_fold(base.clone(), |combined, (_, requirement)| {
+            combined.intersection(requirement)
+        })
+    {
+        return Ok(combined);
+    }
+
+    let describe = |(name, requirement): &(&str, ReShadeVersionRequirement)| {
+        format!("{} ({})", name, requirement)
+    };
+    let mut conflicts: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (index, a) in requirements.iter().enumerate() {
+        for b in &requirements[index + 1..] {
+            if !a.1.overlaps(&b.1) {
+                conflicts
+                    .entry(a.0.to_owned())
+                    .or_default()
+                    .push(describe(b));
+                conflicts
+                    .entry(b.0.to_owned())
+                    .or_default()
+                    .push(describe(a));
+            }
+        }
+    }
+    if conflicts.is_empty() {
+        for a in requirements {
+            conflicts.insert(
+                a.0.to_owned(),
+                requirements
+                    .iter()
+                    .filter(|b| b.0 != a.0)
+                    .map(describe)
+                    .collect(),
+            );
+        }
+    }
+    Err(conflicts)
+}
+
+/// What happened to a shaderpack during a build.
+enum ShaderPackOutcome {
+    /// Packed into the collection, with notes about anything that was left out of it.
+    Included(Vec<String>),
+    /// Left out of the collection because it is not compatible with it.
+    Skipped(String),
+    /// Could not be packed into the collection.
+    Failed(String),
+}
+
+/// Get the names of the shaderpacks with an outcome matching the predicate.
+fn shaderpack_names(
+    outcomes: &[(String, ShaderPackOutcome)],
+    predicate: impl Fn(&ShaderPackOutcome) -> bool,
+) -> Vec<String> {
+    outcomes
+        .iter()
+        .filter(|(_, outcome)| predicate(outcome))
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+/// Print what happened to every shaderpack during a build.
+fn print_build_summary(outcomes: &[(String, ShaderPackOutcome)]) {
+    let count = |predicate: fn(&ShaderPackOutcome) -> bool| {
+        outcomes
+            .iter()
+            .filter(|(_, outcome)| predicate(outcome))
+            .count()
+    };
+    println!(
+        "Build summary: {} included, {} skipped, {} failed",
+        count(|outcome| matches!(outcome, ShaderPackOutcome::Included(_))),
+        count(|outcome| matches!(outcome, ShaderPackOutcome::Skipped(_))),
+        count(|outcome| matches!(outcome, ShaderPackOutcome::Failed(_))),
+    );
+    for (name, outcome) in outcomes {
+        match outcome {
+            ShaderPackOutcome::Included(notes) => {
+                println!("{}", format!(" * Included {}", name).green());
+                for note in notes {
+                    println!("   - {}", note);
+                }
+            }
+            ShaderPackOutcome::Skipped(reason) => {
+                eprintln!("{}", format!(" * Skipped {}: {}", name, reason).yellow())
+            }
+            ShaderPackOutcome::Failed(reason) => {
+                eprintln!("{}", format!(" * Failed {}: {}", name, reason).red())
+            }
+        }
+    }
+    println!();
+}
 
-        Ok(())
+/// The files of a shaderpack that were copied into the build directory.
+struct PackedShaderPackFiles {
+    /// The archive paths of every copied file.
+    files: Vec<String>,
+    shader_count: usize,
+    texture_count: usize,
+    preset_count: usize,
+    addon_count: usize,
+}
+
+/// Copy the files of a shaderpack into the build directory, leaving out the textures and addons
+/// excluded by the configuration. A note is added for every file that was left out.
+///
+/// # Arguments
+/// * `license_file_names`: The license file names taken by the other shaderpacks, see [`license_file_name`].
+fn pack_shaderpack_files(
+    source: &ShaderPackSource,
+    manifest: &ShaderPackManifest,
+    configuration: &CollectionConfiguration,
+    build_directory: &Path,
+    license_file_names: &mut BTreeSet<String>,
+    notes: &mut Vec<String>,
+) -> Result<PackedShaderPackFiles> {
+    // Copy shaders to the build directory.
+    let mut files = vec![];
+    let shaders = manifest.files(FileCategory::Shaders);
+    let shader_path = build_directory.join(SHADER_DIRECTORY_NAME);
+    for shader in shaders {
+        println!("[{}] Packing shader: {:?}", manifest.name, shader.source);
+        source.copy_to_output_path(shader, &shader_path)?;
+        files.push(shader.archive_path(SHADER_DIRECTORY_NAME));
+    }
+
+    // Copy textures within the maximum texture size to the build directory.
+    let mut textures = vec![];
+    let texture_path = build_directory.join(TEXTURE_DIRECTORY_NAME);
+    for texture in manifest.files(FileCategory::Textures) {
+        if let Some(max_texture_size) = configuration.max_texture_size {
+            if source
+                .read(&texture.source)
+                .ok()
+                .and_then(|contents| ImageInfo::parse(&contents).ok())
+                .is_some_and(|info| info.width > max_texture_size || info.height > max_texture_size)
+            {
+                println!(
+                    "[{}] Skipping texture larger than {}px: {:?}",
+                    manifest.name, max_texture_size, texture.source
+                );
+                notes.push(format!(
+                    "skipped texture larger than {}px: {:?}",
+                    max_texture_size, texture.source
+                ));
+                continue;
+            }
+        }
+        println!("[{}] Packing texture: {:?}", manifest.name, texture.source);
+        source.copy_to_output_path(texture, &texture_path)?;
+        files.push(texture.archive_path(TEXTURE_DIRECTORY_NAME));
+        textures.push(texture);
+    }
+
+    // Copy presets to the build directory.
+    let presets = manifest.files(FileCategory::Presets);
+    let preset_path = build_directory.join(PRESET_DIRECTORY_NAME);
+    for preset in presets {
+        println!("[{}] Packing preset: {:?}", manifest.name, preset.source);
+        source.copy_to_output_path(preset, &preset_path)?;
+        files.push(preset.archive_path(PRESET_DIRECTORY_NAME));
+    }
+
+    // Copy addons matching the target architecture to build directory.
+    let mut addons = vec![];
+    let addon_path = build_directory.join(ADDON_DIRECTORY_NAME);
+    for addon in manifest.files(FileCategory::Addons) {
+        let binary = source
+            .read(&addon.source)
+            .map_err(AddonReadErrorKind::ReadFailure)
+            .and_then(|contents| AddonBinary::parse(&contents))
+            .with_context(|| format!("Failed to read addon {:?}", addon.source))?;
+        match binary.machine.architecture() {
+            Some(architecture) if configuration.target_architecture.includes(architecture) => {
+                println!("[{}] Packing addon: {:?}", manifest.name, addon.source);
+                source.copy_to_output_path(addon, &addon_path)?;
+                files.push(addon.archive_path(ADDON_DIRECTORY_NAME));
+                addons.push(addon);
+            }
+            _ => {
+                println!(
+                    "[{}] Skipping {} addon for a different architecture: {:?}",
+                    manifest.name, binary.machine, addon.source
+                );
+                notes.push(format!(
+                    "skipped {} addon for a different architecture: {:?}",
+                    binary.machine, addon.source
+                ));
+            }
+        }
+    }
+
+    // Copy licenses to build directory.
+    if let Some(manifest_license_path) = &manifest.license_file {
+        let license_path = build_directory.join(LICENSE_DIRECTORY_NAME);
+        println!("[{}] Writing license data", manifest.name);
+        fs::create_dir_all(&license_path)?;
+        let license_file_name = license_file_name(
+            &manifest.name,
+            manifest.version.as_ref(),
+            license_file_names,
+        );
+        fs::write(
+            license_path.join(&license_file_name),
+            source
+                .read(manifest_license_path)
+                .context("Failed to read license")?,
+        )
+        .context("Failed to write license")?;
+        files.push(format!("{}/{}", LICENSE_DIRECTORY_NAME, license_file_name));
+    }
+
+    Ok(PackedShaderPackFiles {
+        files,
+        shader_count: shaders.len(),
+        texture_count: textures.len(),
+        preset_count: presets.len(),
+        addon_count: addons.len(),
+    })
+}
+
+/// Convert the parsed metadata of an effect into its collection manifest representation,
+/// leaving out uniforms that are set by ReShade instead of the user.
+fn collection_effect(shader: &FileDeclaration, effect: &EffectFile) -> CollectionEffect {
+    CollectionEffect {
+        file_name: shader.output.to_string_lossy().replace('\\', "/"),
+        techniques: effect
+            .techniques
+            .iter()
+            .map(|technique| CollectionEffectTechnique {
+                name: technique.name.clone(),
+                label: technique.ui_label.clone(),
+                tooltip: technique.ui_tooltip.clone(),
+            })
+            .collect(),
+        uniforms: effect
+            .uniforms
+            .iter()
+            .filter(|uniform| uniform.source.is_none())
+            .map(|uniform| CollectionEffectUniform {
+                name: uniform.name.clone(),
+                value_type: uniform.value_type.clone(),
+                default_value: uniform.default_value.clone(),
+                ui_type: uniform.ui_type.clone(),
+                label: uniform.ui_label.clone(),
+                tooltip: uniform.ui_tooltip.clone(),
+                category: uniform.ui_category.clone(),
+                min: uniform.ui_min.clone(),
+                max: uniform.ui_max.clone(),
+                step: uniform.ui_step.clone(),
+                items: uniform.ui_items.clone(),
+            })
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requirements<'a>(
+        requirements: &[(&'a str, &str)],
+    ) -> Vec<(&'a str, ReShadeVersionRequirement)> {
+        requirements
+            .iter()
+            .map(|(name, requirement)| (*name, requirement.parse().unwrap()))
+            .collect()
+    }
+
+    #[test]
+    fn reshade_versions_are_combined() {
+        let base = "5.x || 6.x".parse().unwrap();
+        let combined =
+            combine_reshade_versions(&base, &requirements(&[("A", ">=5.5"), ("B", "<6.2")]))
+                .unwrap();
+        assert_eq!(combined.to_string(), ">=5.5.0, <6.2.0");
+    }
+
+    #[test]
+    fn every_conflicting_shaderpack_is_reported_regardless_of_order() {
+        let base = "*".parse().unwrap();
+        let mut shaderpacks = requirements(&[("A", "6.x"), ("B", "6.x"), ("Outlier", "5.x")]);
+        let expected = BTreeMap::from([
+            ("A".to_owned(), vec!["Outlier (5.x)".to_owned()]),
+            ("B".to_owned(), vec!["Outlier (5.x)".to_owned()]),
+            (
+                "Outlier".to_owned(),
+                vec!["A (6.x)".to_owned(), "B (6.x)".to_owned()],
+            ),
+        ]);
+        assert_eq!(
+            combine_reshade_versions(&base, &shaderpacks).unwrap_err(),
+            expected
+        );
+        shaderpacks.reverse();
+        let conflicts = combine_reshade_versions(&base, &shaderpacks).unwrap_err();
+        assert_eq!(conflicts.keys().collect::<Vec<_>>(), ["A", "B", "Outlier"]);
+        assert_eq!(conflicts["A"], expected["A"]);
+    }
+
+    #[test]
+    fn shaderpacks_without_a_common_version_conflict_with_each_other() {
+        let base = "*".parse().unwrap();
+        let conflicts = combine_reshade_versions(
+            &base,
+            &requirements(&[
+                ("A", "4.x || 5.x"),
+                ("B", "5.x || 6.x"),
+                ("C", "4.x || 6.x"),
+            ]),
+        )
+        .unwrap_err();
+        assert_eq!(
+            conflicts["A"],
+            [
+                "B (>=5.0.0, <7.0.0)".to_owned(),
+                "C (4.x || 6.x)".to_owned()
+            ]
+        );
+        assert_eq!(conflicts.len(), 3);
     }
 }
@@ -0,0 +1,489 @@
+use crate::parse::{parse_toml, parse_toml_value, TomlSource};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::Args;
+use shaded_models::collection_configuration::CollectionConfiguration;
+use std::{
+    collections::BTreeMap,
+    env, fs,
+    path::{Path, PathBuf},
+};
+use toml::{Table, Value};
+
+/// Arguments for loading a collection configuration file.
+#[derive(Debug, Args)]
+pub struct ConfigurationArgs {
+    /// Path to the collection configuration file.
+    #[arg(short = 'c', long = "configuration")]
+    pub configuration_file_path: PathBuf,
+
+    /// The name of a profile in the configuration `[profiles]` table to merge over the rest of the configuration.
+    #[arg(short = 'p', long = "profile")]
+    pub profile: Option<String>,
+
+    /// Set a variable for interpolation into the configuration, taking priority over its `[variables]` table.
+    #[arg(long = "var", value_name = "KEY=VALUE", value_parser = parse_variable)]
+    pub variables: Vec<(String, String)>,
+}
+
+impl ConfigurationArgs {
+    /// Load the configuration file with the selected profile, resolving what it extends and interpolating variables.
+    pub fn load(&self) -> Result<CollectionConfiguration> {
+        self.load_profile(self.profile.as_deref())
+    }
+
+    /// Load the configuration file with the given profile instead of the selected one.
+    pub fn load_profile(&self, profile: Option<&str>) -> Result<CollectionConfiguration> {
+        load_configuration(
+            &self.configuration_file_path,
+            &self.variables.iter().cloned().collect(),
+            profile,
+        )
+    }
+
+    /// Get the names of every profile defined by the configuration file and the files it extends.
+    pub fn profile_names(&self) -> Result<Vec<String>> {
+        let mut table =
+            load_configuration_table(&self.configuration_file_path, &mut vec![], &mut vec![])?;
+        Ok(take_profiles(&mut table)?.keys().cloned().collect())
+    }
+
+    /// Get the profiles a command works with: every profile defined by the configuration if `all_profiles`
+    /// is set, otherwise the selected profile, or none for the configuration without a profile.
+    pub fn selected_profiles(&self, all_profiles: bool) -> Result<Vec<Option<String>>> {
+        if !all_profiles {
+            return Ok(vec![self.profile.clone()]);
+        }
+        let profiles = self.profile_names()?;
+        if profiles.is_empty() {
+            bail!("The configuration does not define any profiles");
+        }
+        Ok(profiles.into_iter().map(Some).collect())
+    }
+}
+
+fn parse_variable(argument: &str) -> Result<(String, String)> {
+    let (key, value) = argument
+        .split_once('=')
+        .ok_or_else(|| anyhow!("Variables must be in the form KEY=VALUE"))?;
+    Ok((key.trim().to_owned(), value.to_owned()))
+}
+
+/// Load and parse the collection configuration file at the given path.
+///
+/// If the configuration `extends` another file, that file is loaded first and this configuration is
+/// merged on top of it. Afterwards, `${name}` in string values is replaced with the variable of that
+/// name and `${env:NAME}` with the environment variable of that name. Variables from `variables`
+/// take priority over the `[variables]` table, whose values may themselves reference environment
+/// variables and any other variable, as long as no variable ends up referencing itself. Variables of the
+/// table are only resolved when a value references them, so unused variables may reference undefined
+/// ones, e.g. `profile` when no profile is selected. `$${` is written as a literal `${`.
+///
+/// If a profile is given, its table from `[profiles]` is merged over the configuration before variables
+/// are interpolated and its name is available as the `profile` variable.
+pub fn load_configuration(
+    configuration_file_path: &Path,
+    variables: &BTreeMap<String, String>,
+    profile: Option<&str>,
+) -> Result<CollectionConfiguration> {
+    let mut sources = vec![];
+    let mut table = load_configuration_table(configuration_file_path, &mut vec![], &mut sources)?;
+
+    let profiles = take_profiles(&mut table)?;
+    if let Some(profile) = profile {
+        let Some(Value::Table(profile_table)) = profiles.get(profile) else {
+            bail!(
+                "Profile '{}' is not defined in the configuration. Available profiles: {}",
+                profile,
+                profiles.keys().cloned().collect::<Vec<_>>().join(", ")
+            );
+        };
+        merge_tables(&mut table, profile_table.clone());
+
+        // Values set by the profile are reported at their location in the profile table.
+        let profile_sources: Vec<_> = sources
+            .iter()
+            .map(|source| TomlSource {
+                file_name: source.file_name.clone(),
+                text: source.text.clone(),
+                prefix: vec!["profiles".to_owned(), profile.to_owned()],
+            })
+            .collect();
+        sources.extend(profile_sources);
+    }
+
+    // Variables of the table are resolved as the rest of the configuration references them.
+    let mut table_variables = BTreeMap::new();
+    if let Some(values) = table.get("variables") {
+        let Value::Table(values) = values else {
+            bail!("The configuration variables must be a table");
+        };
+        for (key, value) in values {
+            let Value::String(value) = value else {
+                bail!("The configuration variable '{}' must be a string", key);
+            };
+            table_variables.insert(key.clone(), value.clone());
+        }
+    }
+    let mut fixed_variables = BTreeMap::new();
+    if let Some(profile) = profile {
+        fixed_variables.insert("profile".to_owned(), profile.to_owned());
+    }
+    fixed_variables.extend(variables.clone());
+    let mut resolver = VariableResolver::new(&table_variables, fixed_variables);
+    for (key, value) in table.iter_mut().filter(|(key, _)| *key != "variables") {
+        interpolate_value(value, &mut |name| resolver.resolve(name))
+            .with_context(|| format!("Failed to interpolate configuration value '{}'", key))?;
+    }
+
+    // Only the variables that were used are resolved, so only those are kept.
+    table.insert(
+        "variables".to_owned(),
+        Value::Table(
+            resolver
+                .resolved
+                .into_iter()
+                .map(|(key, value)| (key, Value::String(value)))
+                .collect(),
+        ),
+    );
+
+    parse_toml_value(Value::Table(table), &sources)
+        .context("Failed to load toml from configuration")
+}
+
+/// Read a configuration file as a TOML table and merge it over the configuration it extends.
+///
+/// # Arguments
+/// * `chain`: The configuration files that are currently being loaded, used to detect cycles.
+/// * `sources`: Receives the loaded configuration files, with extended files before the files extending them.
+fn load_configuration_table(
+    path: &Path,
+    chain: &mut Vec<PathBuf>,
+    sources: &mut Vec<TomlSource>,
+) -> Result<Table> {
+    let canonical_path = path
+        .canonicalize()
+        .with_context(|| format!("Failed to find configuration {:?}", path))?;
+    if chain.contains(&canonical_path) {
+        bail!("Configuration {:?} extends itself", path);
+    }
+    chain.push(canonical_path);
+
+    let text = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read configuration {:?}", path))?;
+    let file_name = path.display().to_string();
+    let mut table: Table = parse_toml(&file_name, &text)
+        .with_context(|| format!("Failed to load toml from configuration {:?}", path))?;
+    let source = TomlSource {
+        file_name,
+        text,
+        prefix: vec![],
+    };
+
+    let Some(extends) = table.remove("extends") else {
+        sources.push(source);
+        return Ok(table);
+    };
+    let Value::String(extends) = extends else {
+        bail!(
+            "The extends value of configuration {:?} must be a path",
+            path
+        );
+    };
+    let base_path = path.parent().unwrap_or(Path::new("")).join(extends);
+    let mut base = load_configuration_table(&base_path, chain, sources)?;
+    sources.push(source);
+    merge_tables(&mut base, table);
+    Ok(base)
+}
+
+/// Remove the `[profiles]` table from a configuration table.
+fn take_profiles(table: &mut Table) -> Result<Table> {
+    match table.remove("profiles") {
+        None => Ok(Table::new()),
+        Some(Value::Table(profiles)) => Ok(profiles),
+        Some(_) => bail!("The configuration profiles must be a table"),
+    }
+}
+
+/// Merge the values of `overlay` into `base`, recursing into tables that are present in both.
+fn merge_tables(base: &mut Table, overlay: Table) {
+    for (key, value) in overlay {
+        match (base.get_mut(&key), value) {
+            (Some(Value::Table(base_table)), Value::Table(overlay_table)) => {
+                merge_tables(base_table, overlay_table)
+            }
+            (_, value) => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Resolves the values of the `[variables]` table, which may reference each other, as they are used.
+struct VariableResolver<'a> {
+    table_variables: &'a BTreeMap<String, String>,
+    /// The variables resolved so far, starting with the fixed variables.
+    resolved: BTreeMap<String, String>,
+}
+
+impl<'a> VariableResolver<'a> {
+    /// # Arguments
+    /// * `fixed`: Variables that take priority over the table and are used as-is, e.g. those set with `--var`.
+    fn new(table_variables: &'a BTreeMap<String, String>, fixed: BTreeMap<String, String>) -> Self {
+        Self {
+            table_variables,
+            resolved: fixed,
+        }
+    }
+
+    /// Get the value of a variable, resolving the variables it references first.
+    fn resolve(&mut self, name: &str) -> Result<String> {
+        self.resolve_with_stack(name, &mut vec![])
+    }
+
+    /// # Arguments
+    /// * `stack`: The variables that are currently being resolved, used to detect cycles.
+    fn resolve_with_stack(&mut self, name: &str, stack: &mut Vec<String>) -> Result<String> {
+        if let Some(value) = self.resolved.get(name) {
+            return Ok(value.clone());
+        }
+        if stack.iter().any(|resolving| resolving == name) {
+            bail!(
+                "Variable '{}' references itself ({} -> {})",
+                name,
+                stack.join(" -> "),
+                name
+            );
+        }
+        let value = self
+            .table_variables
+            .get(name)
+            .ok_or_else(|| anyhow!("Variable '{}' is not defined", name))?;
+
+        stack.push(name.to_owned());
+        let value = interpolate(value, &mut |reference| {
+            self.resolve_with_stack(reference, stack)
+        })
+        .with_context(|| format!("Failed to interpolate variable '{}'", name))?;
+        stack.pop();
+
+        self.resolved.insert(name.to_owned(), value.clone());
+        Ok(value)
+    }
+}
+
+fn interpolate_value(
+    value: &mut Value,
+    variable: &mut dyn FnMut(&str) -> Result<String>,
+) -> Result<()> {
+    match value {
+        Value::String(string) => *string = interpolate(string, variable)?,
+        Value::Array(array) => {
+            for value in array {
+                interpolate_value(value, variable)?;
+            }
+        }
+        Value::Table(table) => {
+            for (_, value) in table.iter_mut() {
+                interpolate_value(value, variable)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Replace every `${name}` and `${env:NAME}` reference in a string.
+///
+/// # Arguments
+/// * `variable`: Get the value of the variable with the given name.
+fn interpolate(text: &str, variable: &mut dyn FnMut(&str) -> Result<String>) -> Result<String> {
+    let mut output = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        if let Some(escaped) = rest.strip_prefix("$${") {
+            output.push_str("${");
+            rest = escaped;
+        } else if let Some(reference) = rest.strip_prefix("${") {
+            let end = reference
+                .find('}')
+                .ok_or_else(|| anyhow!("Unclosed variable reference in '{}'", text))?;
+            let name = reference[..end].trim();
+            let value = match name.strip_prefix("env:") {
+                Some(name) => env::var(name)
+                    .with_context(|| format!("Environment variable '{}' is not set", name))?,
+                None => variable(name)?,
+            };
+            output.push_str(&value);
+            rest = &reference[end + 1..];
+        } else {
+            output.push('$');
+            rest = &rest[1..];
+        }
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn variables(entries: &[(&str, &str)]) -> BTreeMap<String, String> {
+        entries
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn resolves_variables_referencing_each_other() {
+        let table = variables(&[
+            ("output", "${root}/${name}"),
+            ("name", "${base}-${profile}"),
+            ("base", "Collection"),
+            ("root", "out"),
+            ("unused", "${missing}"),
+        ]);
+        let mut resolver = VariableResolver::new(&table, variables(&[("profile", "lite")]));
+
+        assert_eq!(resolver.resolve("output").unwrap(), "out/Collection-lite");
+        assert_eq!(resolver.resolve("name").unwrap(), "Collection-lite");
+        assert!(!resolver.resolved.contains_key("unused"));
+    }
+
+    #[test]
+    fn resolves_variables_with_overrides() {
+        let table = variables(&[
+            ("output", "${root}/out"),
+            ("root", "default"),
+            ("literal", "$${root}"),
+        ]);
+        let mut resolver =
+            VariableResolver::new(&table, variables(&[("root", "${not_interpolated}")]));
+
+        assert_eq!(resolver.resolve("root").unwrap(), "${not_interpolated}");
+        assert_eq!(
+            resolver.resolve("output").unwrap(),
+            "${not_interpolated}/out"
+        );
+        assert_eq!(resolver.resolve("literal").unwrap(), "${root}");
+    }
+
+    #[test]
+    fn rejects_cyclic_variables() {
+        let table = variables(&[("a", "${b}"), ("b", "${c}"), ("c", "${a}")]);
+        let err = VariableResolver::new(&table, BTreeMap::new())
+            .resolve("a")
+            .unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            "Variable 'a' references itself (a -> b -> c -> a)"
+        );
+
+        let table = variables(&[("a", "x${a}")]);
+        let err = VariableResolver::new(&table, BTreeMap::new())
+            .resolve("a")
+            .unwrap_err();
+        assert_eq!(
+            err.root_cause().to_string(),
+            "Variable 'a' references itself (a -> a)"
+        );
+    }
+
+    #[test]
+    fn rejects_undefined_variables() {
+        let table = variables(&[("a", "${missing}")]);
+        let err = VariableResolver::new(&table, BTreeMap::new())
+            .resolve("a")
+            .unwrap_err();
+        assert_eq!(err.to_string(), "Failed to interpolate variable 'a'");
+        assert_eq!(
+            err.root_cause().to_string(),
+            "Variable 'missing' is not defined"
+        );
+    }
+
+    #[test]
+    fn loads_configurations_without_a_profile_when_unused_variables_reference_it() {
+        let directory = env::temp_dir().join(format!(
+            "shaded-configuration-unused-{}",
+            std::process::id()
+        ));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join("collection.toml"),
+            r#"
+            configuration_version = 1
+            reshade_version = 6
+            name = "${base}"
+
+            [variables]
+            base = "Base"
+            title = "${base} (${profile})"
+
+            [profiles.lite]
+            name = "${title}"
+            "#,
+        )
+        .unwrap();
+
+        let path = directory.join("collection.toml");
+        let base = load_configuration(&path, &BTreeMap::new(), None);
+        let lite = load_configuration(&path, &BTreeMap::new(), Some("lite"));
+        fs::remove_dir_all(&directory).unwrap();
+
+        let base = base.unwrap();
+        assert_eq!(base.name, "Base");
+        assert_eq!(base.variables, variables(&[("base", "Base")]));
+        assert_eq!(lite.unwrap().name, "Base (lite)");
+    }
+
+    #[test]
+    fn loads_configurations_with_variables_and_profiles() {
+        let directory =
+            env::temp_dir().join(format!("shaded-configuration-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        fs::write(
+            directory.join("base.toml"),
+            r#"
+            configuration_version = 1
+            reshade_version = 6
+            name = "${title}"
+
+            [variables]
+            title = "${base} (${profile})"
+            base = "Base"
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            directory.join("collection.toml"),
+            r#"
+            extends = "base.toml"
+
+            [profiles.lite]
+            description = "${title}"
+            "#,
+        )
+        .unwrap();
+
+        let configuration = load_configuration(
+            &directory.join("collection.toml"),
+            &variables(&[("base", "Override")]),
+            Some("lite"),
+        );
+        fs::remove_dir_all(&directory).unwrap();
+
+        let configuration = configuration.unwrap();
+        assert_eq!(configuration.name, "Override (lite)");
+        assert_eq!(
+            configuration.description.as_deref(),
+            Some("Override (lite)")
+        );
+    }
+}
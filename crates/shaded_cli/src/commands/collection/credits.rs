@@ -0,0 +1,231 @@
+use shaded_models::collection::{CollectionManifest, CollectionShaderPack};
+use std::fmt::Write;
+
+/// Render the credits of a collection as Markdown, listing every shaderpack with its authors,
+/// license and the files it contributed.
+pub fn render_credits_markdown(collection: &CollectionManifest) -> String {
+    let mut credits = String::new();
+    writeln!(credits, "# {} Credits", escape_markdown(&collection.name)).unwrap();
+    if let Some(description) = &collection.description {
+        writeln!(credits, "\n{}", escape_markdown(description)).unwrap();
+    }
+    writeln!(
+        credits,
+        "\nThis collection is made up of the following shaderpacks by their respective authors."
+    )
+    .unwrap();
+
+    for shaderpack in &collection.shader_packs {
+        writeln!(credits, "\n## {}\n", escape_markdown(&shaderpack.name)).unwrap();
+        if !shaderpack.description.is_empty() {
+            writeln!(credits, "{}\n", escape_markdown(&shaderpack.description)).unwrap();
+        }
+        if let Some(version) = &shaderpack.version {
+            writeln!(
+                credits,
+                "- **Version:** {}",
+                escape_markdown(&version.to_string())
+            )
+            .unwrap();
+        }
+        writeln!(
+            credits,
+            "- **Authors:** {}",
+            escape_markdown(&shaderpack_authors(shaderpack))
+        )
+        .unwrap();
+        writeln!(
+            credits,
+            "- **License:** {}",
+            escape_markdown(shaderpack_license(shaderpack))
+        )
+        .unwrap();
+        if !shaderpack.files.is_empty() {
+            writeln!(credits, "- **Files:**").unwrap();
+            for file in &shaderpack.files {
+                writeln!(credits, "  - `{}`", file.replace('`', "'")).unwrap();
+            }
+        }
+    }
+
+    credits
+}
+
+/// Render the credits of a collection as a standalone HTML page with the same content as
+/// [`render_credits_markdown`].
+pub fn render_credits_html(collection: &CollectionManifest) -> String {
+    let name = escape_html(&collection.name);
+    let mut credits = String::new();
+    writeln!(credits, "<!DOCTYPE html>").unwrap();
+    writeln!(credits, "<html>").unwrap();
+    writeln!(credits, "<head>").unwrap();
+    writeln!(credits, "<meta charset=\"utf-8\">").unwrap();
+    writeln!(credits, "<title>{} Credits</title>", name).unwrap();
+    writeln!(credits, "</head>").unwrap();
+    writeln!(credits, "<body>").unwrap();
+    writeln!(credits, "<h1>{} Credits</h1>", name).unwrap();
+    if let Some(description) = &collection.description {
+        writeln!(credits, "<p>{}</p>", escape_html(description)).unwrap();
+    }
+    writeln!(
+        credits,
+        "<p>This collection is made up of the following shaderpacks by their respective authors.</p>"
+    )
+    .unwrap();
+
+    for shaderpack in &collection.shader_packs {
+        writeln!(credits, "<h2>{}</h2>", escape_html(&shaderpack.name)).unwrap();
+        if !shaderpack.description.is_empty() {
+            writeln!(credits, "<p>{}</p>", escape_html(&shaderpack.description)).unwrap();
+        }
+        writeln!(credits, "<ul>").unwrap();
+        if let Some(version) = &shaderpack.version {
+            writeln!(
+                credits,
+                "<li><strong>Version:</strong> {}</li>",
+                escape_html(&version.to_string())
+            )
+            .unwrap();
+        }
+        writeln!(
+            credits,
+            "<li><strong>Authors:</strong> {}</li>",
+            escape_html(&shaderpack_authors(shaderpack))
+        )
+        .unwrap();
+        writeln!(
+            credits,
+            "<li><strong>License:</strong> {}</li>",
+            escape_html(shaderpack_license(shaderpack))
+        )
+        .unwrap();
+        if !shaderpack.files.is_empty() {
+            writeln!(credits, "<li><strong>Files:</strong><ul>").unwrap();
+            for file in &shaderpack.files {
+                writeln!(credits, "<li><code>{}</code></li>", escape_html(file)).unwrap();
+            }
+            writeln!(credits, "</ul></li>").unwrap();
+        }
+        writeln!(credits, "</ul>").unwrap();
+    }
+
+    writeln!(credits, "</body>").unwrap();
+    writeln!(credits, "</html>").unwrap();
+    credits
+}
+
+fn shaderpack_authors(shaderpack: &CollectionShaderPack) -> String {
+    if shaderpack.authors.is_empty() {
+        "Unknown".to_owned()
+    } else {
+        shaderpack.authors.join(", ")
+    }
+}
+
+fn shaderpack_license(shaderpack: &CollectionShaderPack) -> &str {
+    shaderpack.license.as_deref().unwrap_or("Unknown")
+}
+
+/// Escape characters that Markdown would otherwise treat as formatting.
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`' | '*' | '_' | '[' | ']' | '<' | '>' | '#' | '|'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use semver::Version;
+
+    fn collection() -> CollectionManifest {
+        CollectionManifest {
+            name: "Shaders & <More>".to_owned(),
+            description: Some("The *best* shaders".to_owned()),
+            shader_packs: vec![
+                CollectionShaderPack {
+                    name: "Bloom_FX".to_owned(),
+                    version: Some(Version::new(1, 0, 0)),
+                    authors: vec!["Ann".to_owned(), "Bo \"B\" O'Neil".to_owned()],
+                    description: "Glow for [bright] areas".to_owned(),
+                    license: Some("MIT OR Apache-2.0".to_owned()),
+                    files: vec![
+                        "Shaders/Bloom.fx".to_owned(),
+                        "Textures/Dirt`s.png".to_owned(),
+                    ],
+                    ..Default::default()
+                },
+                CollectionShaderPack {
+                    name: "Plain".to_owned(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn renders_markdown_credits() {
+        assert_eq!(
+            render_credits_markdown(&collection()),
+            "# Shaders & \\<More\\> Credits\n\
+            \n\
+            The \\*best\\* shaders\n\
+            \n\
+            This collection is made up of the following shaderpacks by their respective authors.\n\
+            \n\
+            ## Bloom\\_FX\n\
+            \n\
+            Glow for \\[bright\\] areas\n\
+            \n\
+            - **Version:** 1.0.0\n\
+            - **Authors:** Ann, Bo \"B\" O'Neil\n\
+            - **License:** MIT OR Apache-2.0\n\
+            - **Files:**\n  \
+              - `Shaders/Bloom.fx`\n  \
+              - `Textures/Dirt's.png`\n\
+            \n\
+            ## Plain\n\
+            \n\
+            - **Authors:** Unknown\n\
+            - **License:** Unknown\n"
+        );
+    }
+
+    #[test]
+    fn renders_escaped_html_credits() {
+        let credits = render_credits_html(&collection());
+        assert!(credits.starts_with("<!DOCTYPE html>\n<html>\n"));
+        assert!(credits.ends_with("</body>\n</html>\n"));
+        for expected in [
+            "<title>Shaders &amp; &lt;More&gt; Credits</title>",
+            "<h1>Shaders &amp; &lt;More&gt; Credits</h1>",
+            "<p>The *best* shaders</p>",
+            "<h2>Bloom_FX</h2>",
+            "<li><strong>Version:</strong> 1.0.0</li>",
+            "<li><strong>Authors:</strong> Ann, Bo &quot;B&quot; O&#39;Neil</li>",
+            "<li><strong>License:</strong> MIT OR Apache-2.0</li>",
+            "<li><strong>Files:</strong><ul>\n<li><code>Shaders/Bloom.fx</code></li>\n<li><code>Textures/Dirt`s.png</code></li>\n</ul></li>",
+            "<h2>Plain</h2>\n<ul>\n<li><strong>Authors:</strong> Unknown</li>\n<li><strong>License:</strong> Unknown</li>\n</ul>",
+        ] {
+            assert!(credits.contains(expected), "{} not in {}", expected, credits);
+        }
+        assert!(!credits.contains("<More>"));
+    }
+}
@@ -0,0 +1,519 @@
+use crate::source::ShaderPackSource;
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use shaded_models::{
+    collection_configuration::{
+        CollectionConfiguration, CollectionConfigurationFilters,
+        CollectionConfigurationSearchDirectory, CollectionConfigurationShaderPack,
+    },
+    dependency::resolve_dependencies,
+    shaderpack::{constants::SHADERPACK_MANIFEST_FILENAME, ShaderPackManifest},
+};
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+/// The shaderpacks found by [`discover_shaderpacks`] and what was noticed while finding them.
+pub struct DiscoveredShaderPacks {
+    pub shaderpacks: Vec<(ShaderPackSource, ShaderPackManifest)>,
+    /// Shaderpacks that were included for reasons other than the configuration listing them.
+    pub notes: Vec<String>,
+    /// Parts of the configuration that had no effect.
+    pub warnings: Vec<String>,
+}
+
+impl DiscoveredShaderPacks {
+    /// Print the notes and warnings of the discovery.
+    ///
+    /// # Arguments
+    /// * `profile`: The profile the shaderpacks were discovered for, to label the messages with.
+    pub fn report(&self, profile: Option<&str>) {
+        let label = |message: &String| match profile {
+            Some(profile) => format!(" * [{}] {}", profile, message),
+            None => format!(" * {}", message),
+        };
+        for note in &self.notes {
+            println!("{}", label(note));
+        }
+        for warning in &self.warnings {
+            eprintln!("{}", label(warning).yellow());
+        }
+    }
+}
+
+/// Find and load every shaderpack included by the collection configuration.
+///
+/// Explicitly listed shaderpacks come first, followed by the shaderpacks inside of each search directory
+/// that match the configuration filters. Paths are relative to the parent directory of the configuration
+/// file, archives are read without extracting them and a shaderpack found more than once is only
+/// included the first time. Shaderpacks required by the included ones are added from the filtered out
+/// ones and every shaderpack is ordered after its dependencies. The configuration overrides are applied
+/// to the returned manifests.
+///
+/// Nothing is printed, as shaderpacks are discovered more than once per build. The notes and warnings are
+/// returned to be reported once instead.
+pub fn discover_shaderpacks(
+    configuration_file_path: &Path,
+    configuration: &CollectionConfiguration,
+) -> Result<DiscoveredShaderPacks> {
+    if configuration.shaderpacks.is_empty()
+        && configuration.all_search_directories().next().is_none()
+    {
+        bail!("The configuration does not list any shaderpacks or search directories");
+    }
+
+    let base_directory = configuration_directory(configuration_file_path)?;
+    let filters = ShaderPackFilters::new(&configuration.filters)?;
+
+    let mut seen_sources = BTreeSet::new();
+    let mut shaderpacks = vec![];
+    let mut available = vec![];
+    for shaderpack in &configuration.shaderpacks {
+        let source = match shaderpack {
+            CollectionConfigurationShaderPack::Path(path) => {
+                let path = canonicalize_source_path(&base_directory, path)?;
+                if path.is_file()
+                    && path
+                        .extension()
+                        .is_some_and(|e| e.eq_ignore_ascii_case("zip"))
+                {
+                    ShaderPackSource::open_archive(&path, None)?
+                } else if path.join(SHADERPACK_MANIFEST_FILENAME).exists() {
+                    ShaderPackSource::Directory(path)
+                } else {
+                    bail!(
+                        "The shaderpack directory {:?} does not contain a {}",
+                        path,
+                        SHADERPACK_MANIFEST_FILENAME
+                    );
+                }
+            }
+            CollectionConfigurationShaderPack::Archive { archive, root } => {
+                ShaderPackSource::open_archive(
+                    &canonicalize_source_path(&base_directory, archive)?,
+                    root.as_deref(),
+                )?
+            }
+            CollectionConfigurationShaderPack::Git {
+                git,
+                revision,
+                root,
+            } => ShaderPackSource::open_git(
+                &canonicalize_source_path(&base_directory, git)?,
+                revision,
+                root.as_deref(),
+            )?,
+        };
+        if seen_sources.insert(source.clone()) {
+            let manifest = source.load_manifest()?;
+            shaderpacks.push((source, manifest));
+        }
+    }
+
+    for search_directory in configuration.all_search_directories() {
+        for directory in find_shaderpack_directories(&base_directory, search_directory)? {
+            let relative_path = directory
+                .strip_prefix(&base_directory)
+                .unwrap_or(&directory)
+                .to_string_lossy()
+                .replace('\\', "/");
+            let source = ShaderPackSource::Directory(directory);
+            if !seen_sources.insert(source.clone()) {
+                continue;
+            }
+
+            // Keep shaderpacks that are filtered out available to satisfy dependencies, ignoring
+            // any that cannot be loaded as they would not be included anyway.
+            if !filters.includes_path(&relative_path) {
+                if let Ok(manifest) = source.load_manifest() {
+                    available.push((source, manifest));
+                }
+                continue;
+            }
+            let manifest = source.load_manifest()?;
+            if !filters.includes_name(&manifest.name) {
+                available.push((source, manifest));
+                continue;
+            }
+            shaderpacks.push((source, manifest));
+        }
+    }
+
+    // Add the shaderpacks required by the included ones and order them after their dependencies.
+    let included_sources: BTreeSet<_> = shaderpacks
+        .iter()
+        .map(|(source, _)| source.clone())
+        .collect();
+    let mut shaderpacks = resolve_dependencies(shaderpacks, available)
+        .context("Failed to resolve shaderpack dependencies")?;
+    let notes = shaderpacks
+        .iter()
+        .filter(|(source, _)| !included_sources.contains(source))
+        .map(|(_, manifest)| {
+            format!(
+                "Including {} as a dependency of another shaderpack",
+                manifest.name
+            )
+        })
+        .collect();
+
+    // Apply overrides to each shaderpack, matched by the name in its manifest before any were applied,
+    // then leave out the categories excluded for the whole collection.
+    let mut applied_overrides = BTreeSet::new();
+    for (_, manifest) in &mut shaderpacks {
+        let name = manifest.name.clone();
+        for (index, shaderpack_override) in configuration
+            .overrides
+            .iter()
+            .enumerate()
+            .filter(|(_, shaderpack_override)| shaderpack_override.shaderpack == name)
+        {
+            shaderpack_override.apply(manifest);
+            applied_overrides.insert(index);
+        }
+        for category in &configuration.exclude_categories {
+            *manifest.files_mut(*category) = None;
+        }
+    }
+    let warnings = configuration
+        .overrides
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| !applied_overrides.contains(index))
+        .map(|(_, shaderpack_override)| {
+            format!(
+                "The override for '{}' does not match any included shaderpack",
+                shaderpack_override.shaderpack
+            )
+        })
+        .collect();
+
+    Ok(DiscoveredShaderPacks {
+        shaderpacks,
+        notes,
+        warnings,
+    })
+}
+
+/// Get the canonical directory of the configuration file, which relative paths in the configuration are based on.
+pub fn configuration_directory(configuration_file_path: &Path) -> Result<PathBuf> {
+    let directory = configuration_file_path
+        .parent()
+        .context("Failed to get the parent directory of the configuration file")?;
+    match directory.as_os_str().is_empty() {
+        true => Path::new("."),
+        false => directory,
+    }
+    .canonicalize()
+    .context("Failed to canonicalize the configuration directory")
+}
+
+/// Recursively find every directory with a shaderpack manifest inside of a search directory.
+pub fn find_shaderpack_directories(
+    base_directory: &Path,
+    search_directory: &CollectionConfigurationSearchDirectory,
+) -> Result<Vec<PathBuf>> {
+    Ok(WalkDir::new(
+        base_directory
+            .join(&search_directory.path)
+            .canonicalize()
+            .with_context(|| {
+                format!(
+                    "Failed to canconicalize configuration search directory {:?}",
+                    search_directory.path
+                )
+            })?,
+    )
+    .max_depth(search_directory.max_depth)
+    .sort_by_file_name()
+    .into_iter()
+    .filter_map(|e| e.ok()) // Only grab DirEntry's that are not errors.
+    .filter(|e| {
+        e.metadata()
+            .is_ok_and(|m| m.is_dir()) // DirEntry must be a directory.
+            && e.path().join(SHADERPACK_MANIFEST_FILENAME).exists() // DirEntry must have a shader manifest.
+    })
+    .map(|e| e.into_path())
+    .collect())
+}
+
+fn canonicalize_source_path(base_directory: &Path, path: &Path) -> Result<PathBuf> {
+    base_directory
+        .join(path)
+        .canonicalize()
+        .with_context(|| format!("Failed to find the shaderpack {:?}", path))
+}
+
+/// The compiled glob patterns of [`CollectionConfigurationFilters`].
+struct ShaderPackFilters {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+    include_names: Option<GlobSet>,
+    exclude_names: Option<GlobSet>,
+}
+
+impl ShaderPackFilters {
+    fn new(filters: &CollectionConfigurationFilters) -> Result<Self> {
+        Ok(Self {
+            include: build_glob_set(&filters.include, true)?,
+            exclude: build_glob_set(&filters.exclude, true)?,
+            include_names: build_glob_set(&filters.include_names, false)?,
+            exclude_names: build_glob_set(&filters.exclude_names, false)?,
+        })
+    }
+
+    fn includes_path(&self, path: &str) -> bool {
+        self.include.as_ref().is_none_or(|set| set.is_match(path))
+            && !self.exclude.as_ref().is_some_and(|set| set.is_match(path))
+    }
+
+    fn includes_name(&self, name: &str) -> bool {
+        self.include_names
+            .as_ref()
+            .is_none_or(|set| set.is_match(name))
+            && !self
+                .exclude_names
+                .as_ref()
+                .is_some_and(|set| set.is_match(name))
+    }
+}
+
+/// Compile case-insensitive glob patterns, or `None` if there are no patterns.
+///
+/// # Arguments
+/// * `literal_separator`: Whether `*` should not match across `/`, for patterns matching paths.
+fn build_glob_set(patterns: &[String], literal_separator: bool) -> Result<Option<GlobSet>> {
+    if patterns.is_empty() {
+        return Ok(None);
+    }
+
+    let mut builder = GlobSetBuilder::new();
+    for pattern in patterns {
+        builder.add(
+            GlobBuilder::new(pattern)
+                .case_insensitive(true)
+                .literal_separator(literal_separator)
+                .build()
+                .with_context(|| format!("Invalid filter pattern '{}'", pattern))?,
+        );
+    }
+    Ok(Some(builder.build()?))
+}
+
+/// Whether a shaderpack with the same source and manifest, including any overrides, is in the list.
+pub fn is_listed(
+    shaderpacks: &[(ShaderPackSource, ShaderPackManifest)],
+    source: &ShaderPackSource,
+    manifest: &ShaderPackManifest,
+) -> bool {
+    shaderpacks.iter().any(|(listed_source, listed_manifest)| {
+        listed_source == source && listed_manifest == manifest
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::collection::configuration::load_configuration;
+    use std::{collections::BTreeMap, env, fs};
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|pattern| pattern.to_string()).collect()
+    }
+
+    fn write_shaderpack(directory: &Path, name: &str, extra: &str) {
+        fs::create_dir_all(directory).unwrap();
+        fs::write(
+            directory.join(SHADERPACK_MANIFEST_FILENAME),
+            format!(
+                r#"{{
+                    "ManifestVersion": 1,
+                    "ReShadeVersion": 6,
+                    "Name": "{}",
+                    "Authors": ["Author"],
+                    "Description": "Description"{}
+                }}"#,
+                name, extra
+            ),
+        )
+        .unwrap();
+    }
+
+    #[test]
+    fn filters_shaderpacks_by_path_and_name() {
+        let filters = ShaderPackFilters::new(&CollectionConfigurationFilters {
+            include: patterns(&["packs/*"]),
+            exclude: patterns(&["packs/old-*"]),
+            include_names: patterns(&["*bloom*", "Vignette"]),
+            exclude_names: patterns(&["Legacy *"]),
+        })
+        .unwrap();
+
+        assert!(filters.includes_path("packs/Bloom"));
+        assert!(filters.includes_path("PACKS/bloom"));
+        assert!(!filters.includes_path("packs/old-Bloom"));
+        // Path patterns don't match across directories.
+        assert!(!filters.includes_path("packs/nested/Bloom"));
+        assert!(!filters.includes_path("other/Bloom"));
+
+        assert!(filters.includes_name("Simple Bloom"));
+        assert!(filters.includes_name("vignette"));
+        assert!(!filters.includes_name("Legacy Bloom"));
+        assert!(!filters.includes_name("Sharpen"));
+
+        let empty = ShaderPackFilters::new(&CollectionConfigurationFilters::default()).unwrap();
+        assert!(empty.includes_path("any/path"));
+        assert!(empty.includes_name("Any"));
+        assert!(ShaderPackFilters::new(&CollectionConfigurationFilters {
+            include: patterns(&["packs/[a"]),
+            ..Default::default()
+        })
+        .is_err());
+    }
+
+    #[test]
+    fn lists_shaderpacks_by_source_and_manifest() {
+        let manifest = ShaderPackManifest {
+            name: "Bloom".to_owned(),
+            ..Default::default()
+        };
+        let shaderpacks = [(
+            ShaderPackSource::Directory("bloom".into()),
+            manifest.clone(),
+        )];
+
+        assert!(is_listed(
+            &shaderpacks,
+            &ShaderPackSource::Directory("bloom".into()),
+            &manifest
+        ));
+        assert!(!is_listed(
+            &shaderpacks,
+            &ShaderPackSource::Directory("other".into()),
+            &manifest
+        ));
+        let overridden = ShaderPackManifest {
+            version: Some(semver::Version::new(2, 0, 0)),
+            ..manifest
+        };
+        assert!(!is_listed(
+            &shaderpacks,
+            &ShaderPackSource::Directory("bloom".into()),
+            &overridden
+        ));
+    }
+
+    #[test]
+    fn finds_shaderpack_directories_up_to_the_maximum_depth() {
+        let directory =
+            env::temp_dir().join(format!("shaded-discovery-find-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        write_shaderpack(&directory.join("packs/b"), "B", "");
+        write_shaderpack(&directory.join("packs/a"), "A", "");
+        write_shaderpack(&directory.join("packs/nested/c"), "C", "");
+        fs::create_dir_all(directory.join("packs/empty")).unwrap();
+
+        let find = |max_depth| {
+            find_shaderpack_directories(
+                &directory,
+                &CollectionConfigurationSearchDirectory {
+                    path: "packs".into(),
+                    max_depth,
+                },
+            )
+        };
+        let shallow = find(1);
+        let deep = find(2);
+        let missing = find_shaderpack_directories(
+            &directory,
+            &CollectionConfigurationSearchDirectory {
+                path: "missing".into(),
+                max_depth: 1,
+            },
+        );
+        let packs = directory.join("packs").canonicalize().unwrap();
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(shallow.unwrap(), [packs.join("a"), packs.join("b")]);
+        assert_eq!(
+            deep.unwrap(),
+            [packs.join("a"), packs.join("b"), packs.join("nested/c")]
+        );
+        assert!(missing.is_err());
+    }
+
+    #[test]
+    fn returns_dependency_notes_and_unmatched_override_warnings() {
+        let directory =
+            env::temp_dir().join(format!("shaded-discovery-notes-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        write_shaderpack(
+            &directory.join("packs/Alpha"),
+            "Alpha",
+            r#",
+            "Dependencies": [{ "Name": "Beta", "Version": "^1" }],
+            "Shaders": [{ "Source": "Shaders/Alpha.fx", "Output": "Alpha.fx" }],
+            "Presets": [{ "Source": "Presets/Alpha.ini", "Output": "Alpha.ini" }]"#,
+        );
+        write_shaderpack(
+            &directory.join("packs/Beta"),
+            "Beta",
+            r#", "Version": "1.0.0""#,
+        );
+        fs::write(
+            directory.join("collection.toml"),
+            r#"
+            configuration_version = 1
+            reshade_version = 6
+            name = "Collection"
+            exclude_categories = ["presets"]
+
+            [[search_directories]]
+            path = "packs"
+            max_depth = 1
+
+            [filters]
+            exclude_names = ["Beta"]
+
+            [[overrides]]
+            shaderpack = "Alpha"
+            description = "Overridden"
+
+            [[overrides]]
+            shaderpack = "Missing"
+            description = "Overridden"
+            "#,
+        )
+        .unwrap();
+
+        let path = directory.join("collection.toml");
+        let discovered = load_configuration(&path, &BTreeMap::new(), None)
+            .and_then(|configuration| discover_shaderpacks(&path, &configuration));
+        fs::remove_dir_all(&directory).unwrap();
+
+        let discovered = discovered.unwrap();
+        assert_eq!(
+            discovered
+                .shaderpacks
+                .iter()
+                .map(|(_, manifest)| (manifest.name.as_str(), manifest.description.as_str()))
+                .collect::<Vec<_>>(),
+            [("Beta", "Description"), ("Alpha", "Overridden")]
+        );
+        // Categories excluded for the whole collection are removed after the overrides are applied.
+        let (_, alpha) = &discovered.shaderpacks[1];
+        assert_eq!(alpha.presets, None);
+        assert_eq!(alpha.shaders.as_ref().map(Vec::len), Some(1));
+        assert_eq!(
+            discovered.notes,
+            ["Including Beta as a dependency of another shaderpack"]
+        );
+        assert_eq!(
+            discovered.warnings,
+            ["The override for 'Missing' does not match any included shaderpack"]
+        );
+    }
+}
@@ -0,0 +1,98 @@
+use super::{configuration::ConfigurationArgs, discovery::discover_shaderpacks};
+use crate::source::ShaderPackSource;
+use anyhow::{bail, Result};
+use clap::Parser;
+use colored::Colorize;
+use shaded_models::{license::ShaderPackLicense, shaderpack::ShaderPackManifest};
+
+/// Report the license of every shaderpack found by a collection configuration and check them
+/// against its license policy.
+#[derive(Debug, Parser)]
+pub struct LicensesCommand {
+    #[command(flatten)]
+    configuration: ConfigurationArgs,
+}
+
+impl LicensesCommand {
+    pub fn run(&self) -> Result<()> {
+        let configuration = self.configuration.load()?;
+        let discovered =
+            discover_shaderpacks(&self.configuration.configuration_file_path, &configuration)?;
+        discovered.report(None);
+        let shaderpacks = discovered.shaderpacks;
+
+        let mut violations = 0;
+        for (source, manifest) in &shaderpacks {
+            let license = resolve_shaderpack_license(source, manifest);
+            match configuration.license_policy.check(&license) {
+                Ok(()) => println!(
+                    "{} {}: {}",
+                    "✓".green(),
+                    manifest.name,
+                    describe_shaderpack_license(&license)
+                ),
+                Err(violation) => {
+                    violations += 1;
+                    println!(
+                        "{} {}: {} - {}",
+                        "✗".red(),
+                        manifest.name,
+                        describe_shaderpack_license(&license),
+                        violation
+                    );
+                }
+            }
+        }
+
+        if violations == 0 {
+            println!(
+                "{}",
+                "All shaderpack licenses are allowed by the collection license policy.".green()
+            );
+        } else if configuration.license_policy.fail_on_violation {
+            bail!(
+                "{} of {} shaderpacks are not allowed by the collection license policy.",
+                violations,
+                shaderpacks.len()
+            );
+        } else {
+            println!(
+                "{}",
+                format!(
+                    "{} of {} shaderpacks are not allowed by the collection license policy.",
+                    violations,
+                    shaderpacks.len()
+                )
+                .yellow()
+            );
+        }
+
+        Ok(())
+    }
+}
+
+/// Resolve the license of a shaderpack from its manifest, reading the license file if one is declared.
+pub fn resolve_shaderpack_license(
+    source: &ShaderPackSource,
+    manifest: &ShaderPackManifest,
+) -> ShaderPackLicense {
+    let license_text = manifest
+        .license_file
+        .as_ref()
+        .and_then(|path| source.read(path).ok())
+        .map(|bytes| String::from_utf8_lossy(&bytes).into_owned());
+    ShaderPackLicense::resolve(manifest.license.as_deref(), license_text.as_deref())
+}
+
+/// Describe a resolved shaderpack license for display.
+pub fn describe_shaderpack_license(license: &ShaderPackLicense) -> String {
+    let mut description = match (&license.expression, license.detected) {
+        (Some(expression), false) => expression.clone(),
+        (Some(expression), true) => format!("{} (detected from license file)", expression),
+        (None, _) => "unknown".to_owned(),
+    };
+    if license.forbids_redistribution {
+        description.push_str(", forbids redistribution");
+    }
+    description
+}
@@ -0,0 +1,401 @@
+use super::{
+    configuration::ConfigurationArgs,
+    discovery::{configuration_directory, discover_shaderpacks},
+};
+use crate::source::ShaderPackSource;
+use anyhow::{bail, Context, Result};
+use clap::Parser;
+use colored::Colorize;
+use sha2::{Digest, Sha256};
+use shaded_models::{
+    collection_lock::{
+        constants::{BASE_PROFILE_NAME, COLLECTION_LOCK_FILENAME, COLLECTION_LOCK_VERSION},
+        CollectionLock, CollectionLockChange, CollectionLockShaderPack,
+    },
+    shaderpack::{constants::SHADERPACK_MANIFEST_FILENAME, FileCategory, ShaderPackManifest},
+};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+const LOCK_FILE_HEADER: &str = "# This file is generated by `shaded collection build` and `shaded collection update`.\n# It is not intended for manual editing.\n";
+
+/// Refresh the lock file of a collection configuration to match the current shaderpacks and files,
+/// without building the collection.
+///
+/// Only the selected profile is relocked, the shaderpacks locked for other profiles are kept.
+#[derive(Debug, Parser)]
+pub struct UpdateCommand {
+    #[command(flatten)]
+    configuration: ConfigurationArgs,
+
+    /// Relock every profile in the configuration.
+    #[arg(long = "all-profiles", conflicts_with = "profile")]
+    all_profiles: bool,
+}
+
+impl UpdateCommand {
+    pub fn run(&self) -> Result<()> {
+        let profiles = self.configuration.selected_profiles(self.all_profiles)?;
+        let lock = generate_lock(&self.configuration, &profiles, &[])?;
+        let changes = update_lock(
+            &self.configuration.configuration_file_path,
+            &lock,
+            &profiles,
+        )?;
+        if changes.is_empty() {
+            println!("{} is up to date", COLLECTION_LOCK_FILENAME);
+        }
+        for change in changes {
+            println!(" * {}", change);
+        }
+        Ok(())
+    }
+}
+
+/// Get the path of the lock file belonging to a configuration file.
+pub fn lock_file_path(configuration_file_path: &Path) -> PathBuf {
+    configuration_file_path
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(COLLECTION_LOCK_FILENAME)
+}
+
+/// Lock every shaderpack included by the given profiles, hashing their manifests and declared files.
+///
+/// The lock only covers the given profiles and is merged into the lock file by [`check_lock`] and
+/// [`update_lock`], so profiles that aren't built don't need to load.
+///
+/// # Arguments
+/// * `profiles`: The profiles to lock, where `None` is the configuration without a profile.
+/// * `excluded`: The sources of the shaderpacks to leave out of the lock.
+pub fn generate_lock(
+    configuration_args: &ConfigurationArgs,
+    profiles: &[Option<String>],
+    excluded: &[ShaderPackSource],
+) -> Result<CollectionLock> {
+    let base_directory = configuration_directory(&configuration_args.configuration_file_path)?;
+
+    let mut lock = CollectionLock {
+        lock_version: COLLECTION_LOCK_VERSION,
+        shaderpacks: vec![],
+    };
+    for profile in profiles {
+        let configuration = configuration_args.load_profile(profile.as_deref())?;
+        for (source, manifest) in
+            discover_shaderpacks(&configuration_args.configuration_file_path, &configuration)?
+                .shaderpacks
+        {
+            if excluded.contains(&source) {
+                continue;
+            }
+            let mut shaderpack = lock_shaderpack(&base_directory, &source, &manifest)?;
+            shaderpack
+                .profiles
+                .insert(profile.as_deref().unwrap_or(BASE_PROFILE_NAME).to_owned());
+
+            // Profiles may include different files of the same shaderpack, so lock all of them.
+            match lock
+                .shaderpacks
+                .iter_mut()
+                .find(|locked| locked.source == shaderpack.source)
+            {
+                Some(locked) => {
+                    locked.profiles.extend(shaderpack.profiles);
+                    locked.files.extend(shaderpack.files);
+                }
+                None => lock.shaderpacks.push(shaderpack),
+            }
+        }
+    }
+
+    Ok(lock)
+}
+
+/// Get the names the given profiles are locked under.
+fn locked_profile_names(profiles: &[Option<String>]) -> BTreeSet<String> {
+    profiles
+        .iter()
+        .map(|profile| profile.as_deref().unwrap_or(BASE_PROFILE_NAME).to_owned())
+        .collect()
+}
+
+/// Read the lock file belonging to a configuration file, if there is one.
+pub fn read_lock(configuration_file_path: &Path) -> Result<Option<CollectionLock>> {
+    let path = lock_file_path(configuration_file_path);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let lock: CollectionLock = toml::from_str(
+        &fs::read_to_string(&path).with_context(|| format!("Failed to read {:?}", path))?,
+    )
+    .with_context(|| format!("Failed to parse {:?}", path))?;
+    if lock.lock_version != COLLECTION_LOCK_VERSION {
+        bail!(
+            "{:?} has unsupported lock version {}. Run `shaded collection update` to regenerate it.",
+            path,
+            lock.lock_version
+        );
+    }
+    Ok(Some(lock))
+}
+
+/// Fail if the lock file of a configuration file is missing or does not match the given lock of some
+/// profiles.
+pub fn check_lock(
+    configuration_file_path: &Path,
+    lock: &CollectionLock,
+    profiles: &[Option<String>],
+) -> Result<()> {
+    let Some(existing) = read_lock(configuration_file_path)? else {
+        bail!(
+            "There is no {} next to the configuration. Run `shaded collection update` to create it.",
+            COLLECTION_LOCK_FILENAME
+        );
+    };
+
+    let changes = existing.changes(&existing.merge(lock, &locked_profile_names(profiles)));
+    if !changes.is_empty() {
+        bail!(
+            "The collection inputs no longer match {}. Run `shaded collection update` to refresh it.\n{}",
+            COLLECTION_LOCK_FILENAME,
+            changes
+                .iter()
+                .map(|change| format!(" * {}", change))
+                .collect::<Vec<_>>()
+                .join("\n")
+        );
+    }
+    Ok(())
+}
+
+/// Merge the given lock of some profiles into the lock file of a configuration file and write it if it
+/// has changed, returning how it differs from the previous lock.
+///
+/// A previous lock that can't be read is reported and replaced.
+pub fn update_lock(
+    configuration_file_path: &Path,
+    lock: &CollectionLock,
+    profiles: &[Option<String>],
+) -> Result<Vec<CollectionLockChange>> {
+    // A lock that can't be read is replaced, as updating is how unsupported or broken locks are fixed.
+    let existing = read_lock(configuration_file_path).unwrap_or_else(|err| {
+        eprintln!(
+            "{}",
+            format!(
+                "Replacing {} as it could not be read: {:#}",
+                COLLECTION_LOCK_FILENAME, err
+            )
+            .yellow()
+        );
+        None
+    });
+    let (lock, changes) = match existing {
+        Some(existing) => {
+            let merged = existing.merge(lock, &locked_profile_names(profiles));
+            if merged == existing {
+                return Ok(vec![]);
+            }
+            let changes = existing.changes(&merged);
+            (merged, changes)
+        }
+        None => (
+            lock.clone(),
+            lock.shaderpacks
+                .iter()
+                .map(|shaderpack| CollectionLockChange::Added(shaderpack.name.clone()))
+                .collect(),
+        ),
+    };
+
+    let path = lock_file_path(configuration_file_path);
+    println!("Writing {}", COLLECTION_LOCK_FILENAME);
+    fs::write(
+        &path,
+        format!("{}\n{}", LOCK_FILE_HEADER, toml::to_string_pretty(&lock)?),
+    )
+    .with_context(|| format!("Failed to write {:?}", path))?;
+    Ok(changes)
+}
+
+fn lock_shaderpack(
+    base_directory: &Path,
+    source: &ShaderPackSource,
+    manifest: &ShaderPackManifest,
+) -> Result<CollectionLockShaderPack> {
+    let hash = |path: &Path| -> Result<String> {
+        Ok(format!(
+            "{:x}",
+            Sha256::digest(
+                source
+                    .read(path)
+                    .with_context(|| format!("Failed to read {:?} from {}", path, source))?
+            )
+        ))
+    };
+
+    let mut files = BTreeMap::new();
+    let declared_files = FileCategory::ALL
+        .into_iter()
+        .flat_map(|category| manifest.files(category))
+        .map(|file| file.source.as_path());
+    for path in declared_files.chain(manifest.license_file.as_deref()) {
+        files.insert(path.to_string_lossy().replace('\\', "/"), hash(path)?);
+    }
+
+    Ok(CollectionLockShaderPack {
+        name: manifest.name.clone(),
+        source: source.lock_source(base_directory),
+        profiles: BTreeSet::new(),
+        manifest_hash: hash(Path::new(SHADERPACK_MANIFEST_FILENAME))?,
+        files,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn write_shaderpack(directory: &Path, name: &str) {
+        fs::create_dir_all(directory.join("Shaders")).unwrap();
+        fs::write(directory.join("Shaders/effect.fx"), name).unwrap();
+        fs::write(
+            directory.join(SHADERPACK_MANIFEST_FILENAME),
+            format!(
+                r#"{{
+                    "ManifestVersion": 1,
+                    "ReShadeVersion": 6,
+                    "Name": "{}",
+                    "Authors": ["Author"],
+                    "Description": "Description",
+                    "Shaders": [{{ "Source": "Shaders/effect.fx", "Output": "{}/effect.fx" }}]
+                }}"#,
+                name, name
+            ),
+        )
+        .unwrap();
+    }
+
+    fn configuration_args(directory: &Path) -> ConfigurationArgs {
+        ConfigurationArgs {
+            configuration_file_path: directory.join("collection.toml"),
+            profile: None,
+            variables: vec![],
+        }
+    }
+
+    fn locked_names(lock: &CollectionLock) -> Vec<(&str, Vec<&str>)> {
+        lock.shaderpacks
+            .iter()
+            .map(|shaderpack| {
+                (
+                    shaderpack.name.as_str(),
+                    shaderpack.profiles.iter().map(String::as_str).collect(),
+                )
+            })
+            .collect()
+    }
+
+    /// Create a collection where the base configuration includes both shaderpacks, the `alpha` profile
+    /// includes one of them and the `broken` profile can't be loaded.
+    fn create_collection(name: &str) -> PathBuf {
+        let directory =
+            env::temp_dir().join(format!("shaded-lock-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        write_shaderpack(&directory.join("Alpha"), "Alpha");
+        write_shaderpack(&directory.join("Beta"), "Beta");
+        fs::write(
+            directory.join("collection.toml"),
+            r#"
+            configuration_version = 1
+            reshade_version = 6
+            name = "Collection"
+            shaderpacks = ["Alpha", "Beta"]
+
+            [profiles.alpha]
+            shaderpacks = ["Alpha"]
+
+            [profiles.broken]
+            shaderpacks = ["Missing"]
+            "#,
+        )
+        .unwrap();
+        directory
+    }
+
+    #[test]
+    fn locks_only_the_given_profiles() {
+        let directory = create_collection("generate");
+        let args = configuration_args(&directory);
+
+        let alpha = generate_lock(&args, &[Some("alpha".to_owned())], &[]);
+        let both = generate_lock(&args, &[None, Some("alpha".to_owned())], &[]);
+        let excluded = generate_lock(
+            &args,
+            &[None],
+            &[ShaderPackSource::Directory(
+                directory.join("Beta").canonicalize().unwrap(),
+            )],
+        );
+        let broken = generate_lock(&args, &[Some("broken".to_owned())], &[]);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(locked_names(&alpha.unwrap()), [("Alpha", vec!["alpha"])]);
+        assert_eq!(
+            locked_names(&both.unwrap()),
+            [("Alpha", vec!["", "alpha"]), ("Beta", vec![""])]
+        );
+        assert_eq!(locked_names(&excluded.unwrap()), [("Alpha", vec![""])]);
+        assert!(broken.is_err());
+    }
+
+    #[test]
+    fn checks_and_updates_the_lock_of_the_given_profiles() {
+        let directory = create_collection("check");
+        let args = configuration_args(&directory);
+        let path = &args.configuration_file_path;
+        let base = [None];
+        let alpha = [Some("alpha".to_owned())];
+
+        let missing = check_lock(path, &generate_lock(&args, &base, &[]).unwrap(), &base);
+        let added = update_lock(path, &generate_lock(&args, &base, &[]).unwrap(), &base).unwrap();
+        let base_check = check_lock(path, &generate_lock(&args, &base, &[]).unwrap(), &base);
+        let alpha_check = check_lock(path, &generate_lock(&args, &alpha, &[]).unwrap(), &alpha);
+        let alpha_added =
+            update_lock(path, &generate_lock(&args, &alpha, &[]).unwrap(), &alpha).unwrap();
+        let merged = read_lock(path).unwrap().unwrap();
+
+        fs::write(directory.join("Beta/Shaders/effect.fx"), "Changed").unwrap();
+        let alpha_unchanged = check_lock(path, &generate_lock(&args, &alpha, &[]).unwrap(), &alpha);
+        let base_changed = check_lock(path, &generate_lock(&args, &base, &[]).unwrap(), &base);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(missing.is_err());
+        assert_eq!(
+            added,
+            [
+                CollectionLockChange::Added("Alpha".to_owned()),
+                CollectionLockChange::Added("Beta".to_owned())
+            ]
+        );
+        assert!(base_check.is_ok());
+        // The alpha profile was never locked.
+        assert!(alpha_check.is_err());
+        assert_eq!(
+            alpha_added,
+            [CollectionLockChange::Changed("Alpha".to_owned(), vec![])]
+        );
+        assert_eq!(
+            locked_names(&merged),
+            [("Alpha", vec!["", "alpha"]), ("Beta", vec![""])]
+        );
+        assert!(alpha_unchanged.is_ok());
+        let message = format!("{:#}", base_changed.unwrap_err());
+        assert!(message.contains("Beta"), "{}", message);
+        assert!(message.contains("Shaders/effect.fx"), "{}", message);
+    }
+}
@@ -1,12 +1,20 @@
 pub mod build;
+mod configuration;
+mod credits;
+pub mod discovery;
+pub mod licenses;
+pub mod lock;
 
-use self::build::BuildCommand;
+use self::{build::BuildCommand, licenses::LicensesCommand, lock::UpdateCommand};
 use anyhow::Result;
 use clap::Parser;
+use std::process::ExitCode;
 
 #[derive(Debug, Parser)]
 pub enum PackageSubcommands {
     Build(BuildCommand),
+    Licenses(LicensesCommand),
+    Update(UpdateCommand),
 }
 
 /// Commands for managing shader packages.
@@ -17,9 +25,11 @@ pub struct CollectionCommandBase {
 }
 
 impl CollectionCommandBase {
-    pub fn run(&self) -> Result<()> {
+    pub fn run(&self) -> Result<ExitCode> {
         match &self.subcommand {
             PackageSubcommands::Build(cmd) => cmd.run(),
+            PackageSubcommands::Licenses(cmd) => cmd.run().map(|_| ExitCode::SUCCESS),
+            PackageSubcommands::Update(cmd) => cmd.run().map(|_| ExitCode::SUCCESS),
         }
     }
 }
@@ -0,0 +1,238 @@
+use anyhow::Result;
+use clap::{Parser, ValueEnum};
+use shaded_models::schema::SchemaKind;
+
+/// Print the JSON Schema of a Shaded document, generated from the models Shaded reads it with.
+#[derive(Debug, Parser)]
+pub struct SchemaCommand {
+    /// The kind of document to print the schema of.
+    #[arg(value_enum)]
+    kind: SchemaKindArg,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SchemaKindArg {
+    /// A shaderpack manifest.
+    Shaderpack,
+    /// The manifest inside of a built collection archive.
+    Collection,
+    /// A collection configuration file.
+    Configuration,
+}
+
+impl SchemaCommand {
+    pub fn run(&self) -> Result<()> {
+        let kind = match self.kind {
+            SchemaKindArg::Shaderpack => SchemaKind::ShaderPackManifest,
+            SchemaKindArg::Collection => SchemaKind::CollectionManifest,
+            SchemaKindArg::Configuration => SchemaKind::CollectionConfiguration,
+        };
+        println!("{}", serde_json::to_string_pretty(&kind.schema())?);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use jsonschema::JSONSchema;
+    use serde::{de::DeserializeOwned, Serialize};
+    use serde_json::{json, Value};
+    use shaded_models::{
+        collection::CollectionManifest, collection_configuration::CollectionConfiguration,
+        shaderpack::ShaderPackManifest,
+    };
+    use std::fmt::Debug;
+
+    /// Check that a sample document matches the schema of its kind, is read by its model, and still
+    /// matches the schema after being written back out by the model.
+    fn assert_round_trips<T: DeserializeOwned + Serialize + PartialEq + Debug>(
+        kind: SchemaKind,
+        sample: Value,
+    ) {
+        let schema = serde_json::to_value(kind.schema()).unwrap();
+        let schema = JSONSchema::compile(&schema).unwrap();
+        let assert_valid = |document: &Value| {
+            if let Err(errors) = schema.validate(document) {
+                let errors: Vec<_> = errors.map(|err| err.to_string()).collect();
+                panic!(
+                    "{:?} document does not match its schema: {}\n{:#}",
+                    kind,
+                    errors.join(", "),
+                    document
+                );
+            }
+        };
+
+        assert_valid(&sample);
+        let model: T = serde_json::from_value(sample).unwrap();
+        let written = serde_json::to_value(&model).unwrap();
+        assert_valid(&written);
+        assert_eq!(serde_json::from_value::<T>(written).unwrap(), model);
+    }
+
+    #[test]
+    fn shaderpack_manifest_schema_matches_model() {
+        assert_round_trips::<ShaderPackManifest>(
+            SchemaKind::ShaderPackManifest,
+            json!({
+                "ManifestVersion": 1,
+                "ReShadeVersion": "5.x || >=6.1",
+                "Name": "Bloom",
+                "Version": "1.2.0",
+                "Authors": ["Someone"],
+                "Description": "Makes bright things glow",
+                "License": "MIT OR CC-BY-4.0",
+                "LicenseFile": "LICENSE",
+                "Images": ["https://example.com/bloom.png"],
+                "Shaders": [{ "Source": "Shaders/Bloom.fx", "Output": "Bloom.fx" }],
+                "Textures": [{ "Source": "Textures/Dirt.png", "Output": "Bloom/Dirt.png" }],
+                "Presets": [{ "Source": "Presets/Bloom.ini", "Output": "Bloom/Bloom.ini" }],
+                "Addons": [{ "Source": "Addons/bloom.addon64", "Output": "bloom.addon64" }],
+                "Dependencies": [{ "Name": "Common", "Version": "^1" }, { "Name": "Util" }]
+            }),
+        );
+        assert_round_trips::<ShaderPackManifest>(
+            SchemaKind::ShaderPackManifest,
+            json!({
+                "ManifestVersion": 1,
+                "ReShadeVersion": 6,
+                "Name": "Minimal",
+                "Authors": [],
+                "Description": ""
+            }),
+        );
+    }
+
+    #[test]
+    fn schema_and_model_reject_unknown_fields() {
+        let sample = json!({
+            "ManifestVersion": 1,
+            "ReShadeVersion": 6,
+            "Name": "Typo",
+            "Autors": [],
+            "Description": ""
+        });
+        let schema = serde_json::to_value(SchemaKind::ShaderPackManifest.schema()).unwrap();
+        assert!(!JSONSchema::compile(&schema).unwrap().is_valid(&sample));
+        assert!(serde_json::from_value::<ShaderPackManifest>(sample).is_err());
+    }
+
+    #[test]
+    fn collection_manifest_schema_matches_model() {
+        assert_round_trips::<CollectionManifest>(
+            SchemaKind::CollectionManifest,
+            json!({
+                "ManifestVersion": 1,
+                "ReShadeVersion": ">=6.1.0, <6.3.0",
+                "Name": "Collection",
+                "Version": "0.3.0",
+                "Description": null,
+                "TargetArchitecture": "x64",
+                "Profile": "lite",
+                "ShaderPacks": [
+                    {
+                        "Name": "Bloom",
+                        "Version": "1.2.0",
+                        "Authors": ["Someone"],
+                        "Description": "Makes bright things glow",
+                        "Images": null,
+                        "ShaderCount": 1,
+                        "TextureCount": 0,
+                        "PresetCount": 0,
+                        "AddonCount": 0,
+                        "License": "MIT",
+                        "Files": ["Shaders/Bloom.fx"],
+                        "Provenance": { "Type": "Git", "Revision": "v1", "Commit": "0123abcd" },
+                        "EffectMetadata": {
+                            "MetadataVersion": 1,
+                            "Effects": [{
+                                "FileName": "Bloom.fx",
+                                "Techniques": [{ "Name": "Bloom", "Label": "Bloom", "Tooltip": "Glow" }],
+                                "Uniforms": [{
+                                    "Name": "Intensity",
+                                    "Type": "float",
+                                    "DefaultValue": "1.0",
+                                    "UiType": "slider",
+                                    "Min": "0.0",
+                                    "Max": "2.0"
+                                }]
+                            }]
+                        }
+                    },
+                    {
+                        "Name": "Archived",
+                        "Authors": [],
+                        "Description": "",
+                        "Images": ["https://example.com/archived.png"],
+                        "ShaderCount": 0,
+                        "TextureCount": 0,
+                        "PresetCount": 1,
+                        "AddonCount": 0,
+                        "Provenance": { "Type": "Archive", "FileName": "archived.zip" }
+                    }
+                ]
+            }),
+        );
+    }
+
+    #[test]
+    fn collection_configuration_schema_matches_model() {
+        let configuration: toml::Value = toml::from_str(
+            r#"
+            configuration_version = 1
+            reshade_version = "6.x"
+            name = "Collection"
+            version = "0.3.0"
+            description = "A collection"
+            extends = "base.toml"
+            name_clash_policy = "fail"
+            target_architecture = "both"
+            exclude_categories = ["addons"]
+            max_texture_size = 2048
+            shaderpacks = [
+                "packs/Bloom",
+                { archive = "packs/archived.zip", root = "Archived" },
+                { git = "../repository", revision = "v1" },
+            ]
+
+            [variables]
+            suffix = "lite"
+
+            [search_directory]
+            path = "."
+            max_depth = 2
+
+            [[search_directories]]
+            path = "more"
+            max_depth = 4
+
+            [filters]
+            include = ["packs/**"]
+            exclude_names = ["Test*"]
+
+            [license_policy]
+            allow = ["MIT"]
+            deny = ["GPL-3.0"]
+            allow_non_commercial = false
+            allow_unknown = true
+            fail_on_violation = true
+
+            [credits]
+            html = true
+
+            [[overrides]]
+            shaderpack = "Bloom"
+            exclude_categories = ["presets"]
+            exclude_files = ["Textures/Big.png"]
+            output_prefixes = [{ from = "", to = "Bloom", categories = ["shaders"] }]
+            name = "Bloom (lite)"
+            "#,
+        )
+        .unwrap();
+        assert_round_trips::<CollectionConfiguration>(
+            SchemaKind::CollectionConfiguration,
+            serde_json::to_value(configuration).unwrap(),
+        );
+    }
+}
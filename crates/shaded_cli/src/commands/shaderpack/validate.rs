@@ -1,384 +1,276 @@
-use anyhow::{anyhow, Context, Error, Result};
+use crate::{
+    commands::collection::discovery::find_shaderpack_directories,
+    parse::parse_json,
+    source::ShaderPackSource,
+    validation::{find_dependents, validate_shaderpack, ProblemType, ValidationSection},
+};
+use anyhow::{anyhow, bail, Context, Result};
 use clap::Parser;
 use colored::*;
-use shaded_models::shaderpack::{FileDeclaration, ShaderPackManifest};
+use rayon::prelude::*;
+use shaded_models::{
+    collection_configuration::CollectionConfigurationSearchDirectory,
+    shaderpack::{constants::SHADERPACK_MANIFEST_FILENAME, ShaderPackManifest},
+};
 use std::{
-    collections::HashMap,
     fs,
     path::{Path, PathBuf},
 };
 
-#[derive(Debug)]
-enum ProblemType {
-    Warning(String),
-    Error(Error),
-}
-
-/// Run validation checks on a shaderpack manifest.
+/// Run validation checks on one or more shaderpack manifests.
 #[derive(Debug, Parser)]
 pub struct ValidateCommand {
-    /// Path to the shaderpack manifest file.
+    /// Paths to shaderpack manifest files, or directories to recursively search for shaderpack manifests.
+    #[arg(required = true)]
+    paths: Vec<PathBuf>,
+
+    /// How many directories deep to search the given directories for shaderpack manifests.
+    #[arg(long = "max-depth", default_value_t = 8)]
+    max_depth: usize,
+}
+
+/// The result of validating a single shaderpack manifest, for the summary table.
+struct ValidationSummary {
     manifest_path: PathBuf,
+    /// The name of the shaderpack, if its manifest could be loaded.
+    name: Option<String>,
+    error_count: usize,
+    warning_count: usize,
 }
 
 impl ValidateCommand {
     pub fn run(&self) -> Result<()> {
-        let mut validation_failure = false;
-        let mut validation_warning = false;
-
-        let manifest_directory = self
-            .manifest_path
-            .parent()
-            .context("Unable to find manifest parent directory")?;
-
-        println!("Loading Shaderpack File");
-        let manifest: ShaderPackManifest = serde_json::from_str(
-            &fs::read_to_string(&self.manifest_path)
-                .context("An error occured while reading shaderpack manifest")?,
-        )
-        .context("An error ocucred while parsing shaderpack manifest")?;
-
-        // Informational field validation.
-        {
-            println!("Validating Information");
-            let mut info_validation_problems: HashMap<String, ProblemType> = HashMap::new();
-
-            // Validate name.
-            if let Some(name_problems) = validate_string_entry("name", &manifest.name) {
-                info_validation_problems.extend(name_problems);
-            }
-
-            // Validate license.
-            if let Some(license_problems) = validate_license_entry(
-                "license",
-                &manifest.license_file.map(|f| manifest_directory.join(f)),
-            ) {
-                info_validation_problems.extend(license_problems);
-            }
-
-            // Validate description.
-            if let Some(description_problems) =
-                validate_string_entry("description", &manifest.description)
-            {
-                info_validation_problems.extend(description_problems);
-            }
-
-            // Validate authors.
-            if let Some(authors_problems) = validate_string_entry_vec("authors", &manifest.authors)
-            {
-                info_validation_problems.extend(authors_problems);
-            }
-
-            if info_validation_problems.is_empty() {
-                println!("{}", "  * Validated successfully".green());
-            } else {
-                for problem in info_validation_problems {
-                    match problem.1 {
-                        ProblemType::Warning(warn) => {
-                            validation_warning = true;
-                            eprintln!(
-                                "{}",
-                                format!("  * Warning with {}: {:?}", problem.0, warn).yellow()
-                            );
-                        }
-                        ProblemType::Error(err) => {
-                            validation_failure = true;
-                            eprintln!(
-                                "{}",
-                                format!("  * Error with {}: {:?}", problem.0, err).red()
-                            );
-                        }
-                    }
-                }
-            }
-            println!();
+        let manifest_paths = self.find_manifest_paths()?;
+        if manifest_paths.is_empty() {
+            bail!(
+                "No {} files were found in the given paths",
+                SHADERPACK_MANIFEST_FILENAME
+            );
         }
 
-        // Shader validation.
-        if let Some(shaders) = manifest.shaders {
-            println!("Validating Shaders");
-            let mut info_validation_problems: HashMap<String, ProblemType> = HashMap::new();
-
-            if let Some(file_declaration_problems) =
-                validate_file_declarations(&manifest_directory, shaders)?
-            {
-                info_validation_problems.extend(file_declaration_problems);
-            }
-
-            if info_validation_problems.is_empty() {
-                println!("{}", "  * Validated successfully".green());
-            } else {
-                for problem in info_validation_problems {
-                    match problem.1 {
-                        ProblemType::Warning(warn) => {
-                            validation_warning = true;
-                            eprintln!(
-                                "{}",
-                                format!("  * Warning with {}: {:?}", problem.0, warn).yellow()
-                            );
+        // Validate every shaderpack concurrently, then print the results in a stable order.
+        let manifests: Vec<_> = manifest_paths
+            .par_iter()
+            .map(|manifest_path| load_manifest(manifest_path))
+            .collect();
+        let dependents = find_dependents(manifests.iter().flatten());
+        let results: Vec<_> = manifest_paths
+            .par_iter()
+            .zip(manifests)
+            .map(|(manifest_path, manifest)| -> Result<_> {
+                let manifest = manifest?;
+                let sections = validate_manifest(
+                    manifest_path,
+                    &manifest,
+                    dependents.get(&manifest.name).map_or(&[], Vec::as_slice),
+                )?;
+                Ok((manifest, sections))
+            })
+            .collect();
+
+        let mut summaries = vec![];
+        for (manifest_path, result) in manifest_paths.into_iter().zip(results) {
+            println!("Loading Shaderpack File {}", manifest_path.display());
+            let mut summary = ValidationSummary {
+                manifest_path,
+                name: None,
+                error_count: 0,
+                warning_count: 0,
+            };
+
+            match result {
+                Ok((manifest, sections)) => {
+                    summary.name = Some(manifest.name);
+                    for section in sections {
+                        for (_, problem) in &section.problems {
+                            match problem {
+                                ProblemType::Warning(_) => summary.warning_count += 1,
+                                ProblemType::Error(_) => summary.error_count += 1,
+                            }
                         }
-                        ProblemType::Error(err) => {
-                            validation_failure = true;
 
-                            eprintln!(
-                                "{}",
-                                format!("  * Error with {}: {:?}", problem.0, err).red()
-                            );
+                        println!("Validating {}", section.name);
+                        for note in &section.notes {
+                            println!("  * {}", note);
                         }
+                        print_problems(section.problems);
                     }
                 }
-            }
-            println!();
-        }
-
-        if let Some(textures) = manifest.textures {
-            println!("Validating Textures");
-            let mut info_validation_problems: HashMap<String, ProblemType> = HashMap::new();
-
-            if let Some(file_declaration_problems) =
-                validate_file_declarations(&manifest_directory, textures)?
-            {
-                info_validation_problems.extend(file_declaration_problems);
-            }
-
-            if info_validation_problems.is_empty() {
-                println!("{}", "  * Validated successfully".green());
-            } else {
-                for problem in info_validation_problems {
-                    match problem.1 {
-                        ProblemType::Warning(warn) => {
-                            validation_warning = true;
-                            eprintln!(
-                                "{}",
-                                format!("  * Warning with {}: {:?}", problem.0, warn).yellow()
-                            );
-                        }
-                        ProblemType::Error(err) => {
-                            validation_failure = true;
-
-                            eprintln!(
-                                "{}",
-                                format!("  * Error with {}: {:?}", problem.0, err).red()
-                            );
-                        }
-                    }
-                }
-            }
-            println!();
-        }
-
-        if let Some(presets) = manifest.presets {
-            println!("Validating Presets");
-            let mut info_validation_problems: HashMap<String, ProblemType> = HashMap::new();
-
-            if let Some(file_declaration_problems) =
-                validate_file_declarations(&manifest_directory, presets)?
-            {
-                info_validation_problems.extend(file_declaration_problems);
-            }
-
-            if info_validation_problems.is_empty() {
-                println!("{}", "  * Validated successfully".green());
-            } else {
-                for problem in info_validation_problems {
-                    match problem.1 {
-                        ProblemType::Warning(warn) => {
-                            validation_warning = true;
-                            eprintln!(
-                                "{}",
-                                format!("  * Warning with {}: {:?}", problem.0, warn).yellow()
-                            );
-                        }
-                        ProblemType::Error(err) => {
-                            validation_failure = true;
-
-                            eprintln!(
-                                "{}",
-                                format!("  * Error with {}: {:?}", problem.0, err).red()
-                            );
-                        }
-                    }
+                Err(err) => {
+                    eprintln!("{}", format!("  * {:?}", err).red());
+                    println!();
+                    summary.error_count += 1;
                 }
             }
-            println!();
+            summaries.push(summary);
         }
 
-        if let Some(addons) = manifest.addons {
-            println!("Validating Addons");
-            let mut info_validation_problems: HashMap<String, ProblemType> = HashMap::new();
-
-            if let Some(file_declaration_problems) =
-                validate_file_declarations(&manifest_directory, addons)?
-            {
-                info_validation_problems.extend(file_declaration_problems);
-            }
-
-            if info_validation_problems.is_empty() {
-                println!("{}", "  * Validated successfully".green());
-            } else {
-                for problem in info_validation_problems {
-                    match problem.1 {
-                        ProblemType::Warning(warn) => {
-                            validation_warning = true;
-                            eprintln!(
-                                "{}",
-                                format!("  * Warning with {}: {:?}", problem.0, warn).yellow()
-                            );
-                        }
-                        ProblemType::Error(err) => {
-                            validation_failure = true;
-
-                            eprintln!(
-                                "{}",
-                                format!("  * Error with {}: {:?}", problem.0, err).red()
-                            );
-                        }
-                    }
-                }
-            }
-            println!();
+        if summaries.len() > 1 {
+            print_summary_table(&summaries);
         }
 
-        if validation_failure {
-            Err(anyhow!(
-                "Manifest was invalid due to one or more validation errors occuring"
-                    .yellow()
-                    .bold()
-            ))
-        } else if validation_warning {
+        let invalid_count = summaries
+            .iter()
+            .filter(|summary| summary.error_count > 0)
+            .count();
+        if invalid_count > 0 {
+            Err(anyhow!(format!(
+                "{} of {} shaderpack manifests were invalid due to one or more validation errors occurring",
+                invalid_count,
+                summaries.len()
+            )
+            .yellow()
+            .bold()))
+        } else if summaries.iter().any(|summary| summary.warning_count > 0) {
             println!(
                 "{}",
-                "Shader manifest is valid, but has validation warnings".yellow()
+                "Shader manifests are valid, but have validation warnings".yellow()
             );
             Ok(())
         } else {
-            println!("{}", "Shader manifest is valid".green());
+            println!("{}", "Shader manifests are valid".green());
             Ok(())
         }
     }
-}
-
-// String
-
-fn validate_string_entry(
-    field_name: &'static str,
-    str: &String,
-) -> Option<HashMap<String, ProblemType>> {
-    let mut failures = HashMap::new();
 
-    if str != str.trim() {
-        failures.insert(
-            field_name.to_owned(),
-            ProblemType::Error(anyhow!(
-                "Contains empty whitespace at start or end of entry./"
-            )),
-        );
+    /// Get the manifest paths given directly, and the manifests found inside of the given directories.
+    fn find_manifest_paths(&self) -> Result<Vec<PathBuf>> {
+        let mut manifest_paths = vec![];
+        for path in &self.paths {
+            let found = if path.is_dir() {
+                find_shaderpack_directories(
+                    Path::new(""),
+                    &CollectionConfigurationSearchDirectory {
+                        path: path.clone(),
+                        max_depth: self.max_depth,
+                    },
+                )?
+                .into_iter()
+                .map(|directory| directory.join(SHADERPACK_MANIFEST_FILENAME))
+                .collect()
+            } else {
+                vec![path
+                    .canonicalize()
+                    .with_context(|| format!("Failed to find shaderpack manifest {:?}", path))?]
+            };
+
+            for manifest_path in found {
+                if !manifest_paths.contains(&manifest_path) {
+                    manifest_paths.push(manifest_path);
+                }
+            }
+        }
+        Ok(manifest_paths)
     }
+}
 
-    if failures.is_empty() {
-        None
-    } else {
-        Some(failures)
-    }
+/// Load a shaderpack manifest.
+fn load_manifest(manifest_path: &Path) -> Result<ShaderPackManifest> {
+    parse_json(
+        &manifest_path.display().to_string(),
+        &fs::read(manifest_path).context("An error occurred while reading shaderpack manifest")?,
+    )
+    .context("An error occurred while parsing shaderpack manifest")
 }
 
-fn validate_license_entry(
-    field_name: &'static str,
-    path: &Option<PathBuf>,
-) -> Option<HashMap<String, ProblemType>> {
-    let mut failures = HashMap::new();
+/// Run every validation check on a loaded shaderpack manifest.
+///
+/// # Arguments
+/// * `dependents`: The names of the validated shaderpacks that depend on this one.
+fn validate_manifest(
+    manifest_path: &Path,
+    manifest: &ShaderPackManifest,
+    dependents: &[String],
+) -> Result<Vec<ValidationSection>> {
+    let manifest_directory = manifest_path
+        .parent()
+        .context("Unable to find manifest parent directory")?;
+
+    let source = ShaderPackSource::Directory(manifest_directory.to_owned());
+    validate_shaderpack(&source, manifest, dependents)
+}
 
-    if let Some(path) = path {
-        // Rule(error): License must exist on disk.
-        if let Err(err) = std::fs::canonicalize(&path) {
-            failures.insert(field_name.to_owned(), ProblemType::Error(err.into()));
-        }
-    } else {
-        // Rule(warning): Shaderpacks should contain a LICENSE file.
-        failures.insert(
-            field_name.to_owned(),
-            ProblemType::Warning("No license file has been set".to_owned()),
-        );
+/// Print a table with the error and warning counts of every validated shaderpack.
+fn print_summary_table(summaries: &[ValidationSummary]) {
+    let rows: Vec<[String; 5]> = summaries
+        .iter()
+        .map(|summary| {
+            [
+                summary.name.clone().unwrap_or_else(|| "-".to_owned()),
+                summary.error_count.to_string(),
+                summary.warning_count.to_string(),
+                match (
+                    summary.name.is_some(),
+                    summary.error_count,
+                    summary.warning_count,
+                ) {
+                    (false, _, _) => "unreadable",
+                    (_, 0, 0) => "valid",
+                    (_, 0, _) => "warnings",
+                    _ => "invalid",
+                }
+                .to_owned(),
+                summary.manifest_path.display().to_string(),
+            ]
+        })
+        .collect();
+
+    let headers = ["Shaderpack", "Errors", "Warnings", "Result", "Manifest"];
+    let widths: Vec<usize> = (0..headers.len())
+        .map(|column| {
+            rows.iter()
+                .map(|row| row[column].chars().count())
+                .chain([headers[column].len()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let format_row = |row: [&str; 5]| {
+        row.iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_owned()
     };
 
-    if failures.is_empty() {
-        None
-    } else {
-        Some(failures)
+    println!("{}", format_row(headers).bold());
+    for row in &rows {
+        let line = format_row([&row[0], &row[1], &row[2], &row[3], &row[4]]);
+        println!(
+            "{}",
+            match row[3].as_str() {
+                "valid" => line.green(),
+                "warnings" => line.yellow(),
+                _ => line.red(),
+            }
+        );
     }
+    println!();
 }
 
-fn validate_string_entry_vec(
-    field_name: &'static str,
-    strings: &Vec<String>,
-) -> Option<HashMap<String, ProblemType>> {
-    let mut failures = HashMap::default();
-    for string in strings {
-        if let Some(string_failures) = validate_string_entry(field_name, &string) {
-            failures.extend(string_failures);
-        }
-    }
-
-    if failures.is_empty() {
-        None
+/// Print the problems of a validation section.
+fn print_problems(problems: Vec<(String, ProblemType)>) {
+    if problems.is_empty() {
+        println!("{}", "  * Validated successfully".green());
     } else {
-        Some(failures)
-    }
-}
-
-fn validate_file_declarations(
-    manifest_directory: &Path,
-    declarations: Vec<FileDeclaration>,
-) -> Result<Option<HashMap<String, ProblemType>>> {
-    let mut failures = HashMap::default();
-
-    for declaration in declarations {
-        // Source validation
-        {
-            let source_path: PathBuf = manifest_directory.join(&declaration.source);
-
-            // Rule: Directories cannot start of end with.
-            if declaration.source.to_str().unwrap() != declaration.source.to_str().unwrap().trim() {
-                failures.insert(
-                    source_path.to_str().unwrap_or_default().to_owned(),
-                    ProblemType::Error(anyhow!("contains leading or trailing whitespace")),
-                );
-                continue;
-            }
-
-            // Rule: All files must exist on disk.
-            if let Err(err) = std::fs::canonicalize(&source_path) {
-                failures.insert(
-                    source_path.to_str().unwrap_or_default().to_owned(),
-                    ProblemType::Error(err.into()),
-                );
-                continue;
-            }
-        }
-
-        // Output validation
-        {
-            // Rule: Output paths cannot contain directory escapes
-            let output_string = declaration
-                .output
-                .to_str()
-                .context("Unable to convert output path to string")?
-                .to_string();
-            if output_string.contains("./") {
-                failures.insert(
-                    declaration.output.to_str().unwrap_or_default().to_owned(),
-                    ProblemType::Error(anyhow!(
-                        "Output paths cannot contain directory escapes like '../'"
-                    )),
-                );
-                continue;
+        for problem in problems {
+            match problem.1 {
+                ProblemType::Warning(warn) => {
+                    eprintln!(
+                        "{}",
+                        format!("  * Warning with {}: {:?}", problem.0, warn).yellow()
+                    );
+                }
+                ProblemType::Error(err) => {
+                    eprintln!(
+                        "{}",
+                        format!("  * Error with {}: {:?}", problem.0, err).red()
+                    );
+                }
             }
         }
     }
-
-    if failures.is_empty() {
-        Ok(None)
-    } else {
-        Ok(Some(failures))
-    }
+    println!();
 }
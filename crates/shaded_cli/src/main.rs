@@ -1,8 +1,13 @@
 mod commands;
+mod parse;
+mod source;
+mod validation;
 
 use clap::Parser;
 use colored::Colorize;
-use commands::{collection::CollectionCommandBase, shaderpack::ShaderpackCommandBase};
+use commands::{
+    collection::CollectionCommandBase, schema::SchemaCommand, shaderpack::ShaderpackCommandBase,
+};
 use std::process::ExitCode;
 
 #[derive(Debug, Parser)]
@@ -12,6 +17,9 @@ enum Commands {
 
     /// Commands relating to Shaded Shaderpacks.
     Shaderpack(ShaderpackCommandBase),
+
+    /// Print the JSON Schema of a Shaded document.
+    Schema(SchemaCommand),
 }
 
 #[derive(Debug, Parser)]
@@ -23,12 +31,15 @@ struct ProgramArgs {
 fn main() -> ExitCode {
     let args = ProgramArgs::parse();
 
-    if let Err(err) = match args.cmd {
+    match match args.cmd {
         Commands::Collection(cmd) => cmd.run(),
-        Commands::Shaderpack(cmd) => cmd.run(),
+        Commands::Shaderpack(cmd) => cmd.run().map(|_| ExitCode::SUCCESS),
+        Commands::Schema(cmd) => cmd.run().map(|_| ExitCode::SUCCESS),
     } {
-        eprintln!("{}: {:?}", "Error".red(), err);
-        return ExitCode::from(1);
+        Ok(exit_code) => exit_code,
+        Err(err) => {
+            eprintln!("{}: {:?}", "Error".red(), err);
+            ExitCode::from(1)
+        }
     }
-    ExitCode::SUCCESS
 }
@@ -0,0 +1,497 @@
+use anyhow::{anyhow, Result};
+use serde::de::DeserializeOwned;
+use serde_path_to_error::{Path, Segment};
+use std::ops::Range;
+use toml_edit::{ImDocument, Item, Table, TableLike, Value};
+
+/// A TOML file that part of a merged TOML document was read from.
+pub struct TomlSource {
+    /// The name of the file shown in errors.
+    pub file_name: String,
+    pub text: String,
+    /// The keys of the table in this file that was merged into the root of the document.
+    pub prefix: Vec<String>,
+}
+
+/// Parse a JSON document, reporting where in the file any value that does not match the model is.
+///
+/// # Arguments
+/// * `file_name`: The name of the file shown in errors.
+pub fn parse_json<T: DeserializeOwned>(file_name: &str, json: &[u8]) -> Result<T> {
+    serde_path_to_error::deserialize(&mut serde_json::Deserializer::from_slice(json)).map_err(
+        |err| {
+            let text = String::from_utf8_lossy(json);
+            let inner = err.inner();
+            // serde_json appends the position to its messages, which is shown in the snippet instead.
+            let message = inner.to_string();
+            let message = message
+                .strip_suffix(&format!(
+                    " at line {} column {}",
+                    inner.line(),
+                    inner.column()
+                ))
+                .unwrap_or(&message);
+            let span = json_span(&text, inner.line(), inner.column(), message);
+            anyhow!(
+                "{}",
+                render_error(err.path(), message, file_name, &text, span)
+            )
+        },
+    )
+}
+
+/// Parse a TOML document, reporting where in the file any value that does not match the model is.
+///
+/// # Arguments
+/// * `file_name`: The name of the file shown in errors.
+pub fn parse_toml<T: DeserializeOwned>(file_name: &str, text: &str) -> Result<T> {
+    let deserializer = toml::Deserializer::new(text);
+    serde_path_to_error::deserialize(deserializer).map_err(|err| {
+        let inner = err.inner();
+        anyhow!(
+            "{}",
+            render_error(err.path(), inner.message(), file_name, text, inner.span())
+        )
+    })
+}
+
+/// Read a TOML value merged from one or more files into a model, reporting the file and location of
+/// any value that does not match the model.
+///
+/// # Arguments
+/// * `sources`: The files the value was merged from, from lowest to highest priority. The value of a key
+///   is looked up in the file with the highest priority that sets it.
+pub fn parse_toml_value<T: DeserializeOwned>(
+    value: toml::Value,
+    sources: &[TomlSource],
+) -> Result<T> {
+    serde_path_to_error::deserialize(value).map_err(|err| {
+        let message = err.inner().message();
+        match locate_toml_path(err.path(), sources) {
+            Some((source, span)) => anyhow!(
+                "{}",
+                render_error(err.path(), message, &source.file_name, &source.text, span)
+            ),
+            None => anyhow!("{}", describe_error(err.path(), message)),
+        }
+    })
+}
+
+/// Describe an error with the JSON path it occurred at, e.g. `$.Shaders[0].Source`.
+fn describe_error(path: &Path, error: &(impl std::fmt::Display + ?Sized)) -> String {
+    match path.to_string().as_str() {
+        "." => error.to_string(),
+        path => format!("{}: {}", json_path(path), error),
+    }
+}
+
+fn json_path(path: &str) -> String {
+    match path.starts_with('[') {
+        true => format!("${}", path),
+        false => format!("$.{}", path),
+    }
+}
+
+/// Render an error with its path, the file it occurred in and a snippet of the source pointing at it.
+///
+/// Errors about unknown fields are given a suggestion of the expected field they were most likely meant
+/// to be, e.g. `Authors` for `Autors`.
+fn render_error(
+    path: &Path,
+    message: &str,
+    file_name: &str,
+    text: &str,
+    span: Option<Range<usize>>,
+) -> String {
+    let (message, suggestion) = match suggest_field(message) {
+        Some((field, suggestion)) => (format!("unknown field `{}`", field), Some(suggestion)),
+        None => (message.to_owned(), None),
+    };
+
+    let mut output = describe_error(path, &message);
+    let gutter = match span {
+        Some(span) => {
+            let start = floor_char_boundary(text, span.start.min(text.len()));
+            let line_start = text[..start].rfind('\n').map_or(0, |index| index + 1);
+            let line_end = text[start..]
+                .find('\n')
+                .map_or(text.len(), |index| start + index);
+            let line = text[..start].matches('\n').count() + 1;
+            let source_line = text[line_start..line_end].trim_end_matches('\r');
+            let before = &text[line_start..start];
+            let column = before.chars().count() + 1;
+            let end = floor_char_boundary(text, span.end.clamp(start, line_end));
+            let width = text[start..end].chars().count().max(1);
+
+            let gutter = " ".repeat(line.to_string().len());
+            // Keep tabs in the padding so the caret lines up with the source line.
+            let padding: String = before
+                .chars()
+                .map(|char| if char == '\t' { '\t' } else { ' ' })
+                .collect();
+            output.push_str(&format!(
+                "\n{gutter}--> {file_name}:{line}:{column}\n{gutter} |\n{line} | {source_line}\n{gutter} | {padding}{carets}",
+                carets = "^".repeat(width),
+            ));
+            gutter
+        }
+        None => {
+            output.push_str(&format!("\n --> {}", file_name));
+            String::new()
+        }
+    };
+    if let Some(suggestion) = suggestion {
+        output.push_str(&format!(
+            "\n{} = help: did you mean `{}`?",
+            gutter, suggestion
+        ));
+    }
+    output
+}
+
+fn floor_char_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Find the expected field most similar to the field of an unknown field error.
+///
+/// Returns the unknown field and the suggested field, or `None` if the error is not about an unknown field
+/// or no expected field is similar enough.
+fn suggest_field(message: &str) -> Option<(String, String)> {
+    let (field, expected) = message.strip_prefix("unknown field `")?.split_once('`')?;
+    // The expected fields are listed in backticks, e.g. ", expected one of `Name`, `Version`".
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(|candidate| {
+            let similarity = strsim::jaro_winkler(&field.to_lowercase(), &candidate.to_lowercase());
+            (similarity, candidate)
+        })
+        .filter(|(similarity, _)| *similarity >= 0.8)
+        .max_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, candidate)| (field.to_owned(), candidate.to_owned()))
+}
+
+/// Get the span of a JSON error from the 1-based line and column serde_json reports.
+///
+/// serde_json reports the position after the value it failed on, so for unknown fields the span is moved
+/// back to the quoted field name.
+fn json_span(text: &str, line: usize, column: usize, message: &str) -> Option<Range<usize>> {
+    if line == 0 {
+        return None;
+    }
+    let line_start = match line {
+        1 => 0,
+        _ => text
+            .match_indices('\n')
+            .nth(line - 2)
+            .map(|(index, _)| index + 1)?,
+    };
+    let position = floor_char_boundary(
+        text,
+        (line_start + column.saturating_sub(1)).min(text.len()),
+    );
+
+    if let Some((field, _)) = message
+        .strip_prefix("unknown field `")
+        .and_then(|rest| rest.split_once('`'))
+    {
+        let quoted = format!("\"{}\"", field);
+        let search_end = floor_char_boundary(text, (position + 1).min(text.len()));
+        if let Some(start) = text[line_start..search_end].rfind(&quoted) {
+            let start = line_start + start;
+            return Some(start..start + quoted.len());
+        }
+    }
+    Some(position..position + 1)
+}
+
+/// Find the file and span of the value at a path in a merged TOML document.
+///
+/// If no file sets the full path, the closest parent that is set is used instead.
+fn locate_toml_path<'a>(
+    path: &Path,
+    sources: &'a [TomlSource],
+) -> Option<(&'a TomlSource, Option<Range<usize>>)> {
+    let segments: Vec<&Segment> = path.iter().collect();
+    let documents: Vec<_> = sources
+        .iter()
+        .map(|source| (source, ImDocument::parse(source.text.as_str()).ok()))
+        .collect();
+
+    for length in (0..=segments.len()).rev() {
+        for (source, document) in documents.iter().rev() {
+            let Some(document) = document else {
+                continue;
+            };
+            if let Some(span) =
+                locate_in_table(document.as_table(), &source.prefix, &segments[..length])
+            {
+                return Some((source, span));
+            }
+        }
+    }
+    None
+}
+
+/// A value inside of a TOML document that can be descended into.
+enum TomlNode<'a> {
+    Item(&'a Item),
+    Table(&'a Table),
+    Value(&'a Value),
+}
+
+impl<'a> TomlNode<'a> {
+    fn as_table_like(&self) -> Option<&'a dyn TableLike> {
+        match *self {
+            TomlNode::Item(item) => item.as_table_like(),
+            TomlNode::Table(table) => Some(table),
+            TomlNode::Value(value) => value.as_inline_table().map(|table| table as &dyn TableLike),
+        }
+    }
+
+    /// Get the element at an index of an array or array of tables, and its span.
+    fn index(&self, index: usize) -> Option<(TomlNode<'a>, Option<Range<usize>>)> {
+        let value = match *self {
+            TomlNode::Item(Item::ArrayOfTables(tables)) => {
+                let table = tables.get(index)?;
+                return Some((TomlNode::Table(table), table.span()));
+            }
+            TomlNode::Item(item) => item.as_value()?,
+            TomlNode::Table(_) => return None,
+            TomlNode::Value(value) => value,
+        };
+        let element = value.as_array()?.get(index)?;
+        Some((TomlNode::Value(element), element.span()))
+    }
+}
+
+/// Get the span of the value at a path inside of a table, or `None` if the path is not set.
+///
+/// The span of the key is used for values that are reached through a key, and the span of the value for
+/// array elements. The outer `None` means the path is not set, while the inner `None` means it is set but
+/// has no known location.
+fn locate_in_table(
+    table: &Table,
+    prefix: &[String],
+    segments: &[&Segment],
+) -> Option<Option<Range<usize>>> {
+    let mut node = TomlNode::Table(table);
+    let mut span = None;
+    for key in prefix {
+        let (_, item) = node.as_table_like()?.get_key_value(key)?;
+        node = TomlNode::Item(item);
+    }
+    for segment in segments {
+        (node, span) = match segment {
+            Segment::Map { key } => {
+                let (key, item) = node.as_table_like()?.get_key_value(key)?;
+                (TomlNode::Item(item), key.span().or(span))
+            }
+            Segment::Seq { index } => {
+                let (element, element_span) = node.index(*index)?;
+                (element, element_span.or(span))
+            }
+            Segment::Enum { .. } | Segment::Unknown => return None,
+        };
+    }
+    Some(span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use shaded_models::collection_configuration::CollectionConfiguration;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase", deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Manifest {
+        name: String,
+        authors: Vec<String>,
+        #[serde(default)]
+        shaders: Vec<Shader>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(rename_all = "PascalCase", deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Shader {
+        source: String,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Configuration {
+        name: String,
+        max_texture_size: Option<u32>,
+        #[serde(default)]
+        search_directories: Vec<SearchDirectory>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct SearchDirectory {
+        path: String,
+        max_depth: usize,
+    }
+
+    fn json_error(json: &str) -> String {
+        parse_json::<Manifest>("manifest.json", json.as_bytes())
+            .unwrap_err()
+            .to_string()
+    }
+
+    fn toml_error(text: &str) -> String {
+        parse_toml::<Configuration>("configuration.toml", text)
+            .unwrap_err()
+            .to_string()
+    }
+
+    #[test]
+    fn json_unknown_fields_point_at_the_field_with_a_suggestion() {
+        assert_eq!(
+            json_error("{\n  \"Name\": \"Bloom\",\n  \"Autors\": []\n}"),
+            "$.Autors: unknown field `Autors`\n --> manifest.json:3:3\n  |\n3 |   \"Autors\": []\n  |   ^^^^^^^^\n  = help: did you mean `Authors`?"
+        );
+    }
+
+    #[test]
+    fn json_type_errors_point_at_the_value_with_its_path() {
+        assert_eq!(
+            json_error("{\n  \"Name\": \"Bloom\",\n  \"Authors\": [],\n  \"Shaders\": [{ \"Source\": 5 }]\n}"),
+            "$.Shaders[0].Source: invalid type: integer `5`, expected a string\n --> manifest.json:4:27\n  |\n4 |   \"Shaders\": [{ \"Source\": 5 }]\n  |                           ^"
+        );
+    }
+
+    #[test]
+    fn toml_errors_point_at_the_value_with_its_path() {
+        assert_eq!(
+            toml_error("name = \"Collection\"\nmax_texture_size = \"big\"\n"),
+            "$.max_texture_size: invalid type: string \"big\", expected u32\n --> configuration.toml:2:20\n  |\n2 | max_texture_size = \"big\"\n  |                    ^^^^^"
+        );
+        assert_eq!(
+            toml_error("name = \"Collection\"\n[[search_directories]]\npath = \".\"\nmax_dept = 2\n"),
+            "$.search_directories[0].max_dept: unknown field `max_dept`\n --> configuration.toml:4:1\n  |\n4 | max_dept = 2\n  | ^^^^^^^^\n  = help: did you mean `max_depth`?"
+        );
+    }
+
+    #[test]
+    fn carets_line_up_after_tabs_and_wide_characters() {
+        let error = toml_error(
+            "name = \"C\"\n\tsearch_directories = [{ path = \"Üñ\", max_depth = -1 }]\n",
+        );
+        assert_eq!(
+            error,
+            format!(
+                "$.search_directories[0].max_depth: invalid value: integer `-1`, expected usize\n --> configuration.toml:2:51\n  |\n2 | \tsearch_directories = [{{ path = \"Üñ\", max_depth = -1 }}]\n  | \t{}^^",
+                " ".repeat(49)
+            )
+        );
+    }
+
+    #[test]
+    fn merged_toml_errors_point_at_the_file_that_set_the_value() {
+        let sources = [
+            TomlSource {
+                file_name: "base.toml".to_owned(),
+                text: "name = \"Base\"\nmax_texture_size = 1024\n".to_owned(),
+                prefix: vec![],
+            },
+            TomlSource {
+                file_name: "collection.toml".to_owned(),
+                text: "name = \"Collection\"\n\n[profiles.lite]\nmax_texture_size = \"small\"\n"
+                    .to_owned(),
+                prefix: vec!["profiles".to_owned(), "lite".to_owned()],
+            },
+        ];
+        let value: toml::Value =
+            toml::from_str("name = \"Collection\"\nmax_texture_size = \"small\"\n").unwrap();
+        assert_eq!(
+            parse_toml_value::<Configuration>(value, &sources)
+                .unwrap_err()
+                .to_string(),
+            "$.max_texture_size: invalid type: string \"small\", expected u32\n --> collection.toml:4:1\n  |\n4 | max_texture_size = \"small\"\n  | ^^^^^^^^^^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn configuration_shaderpack_errors_point_at_the_key() {
+        let error = |text: &str| {
+            let value: toml::Value = toml::from_str(text).unwrap();
+            let sources = [TomlSource {
+                file_name: "collection.toml".to_owned(),
+                text: text.to_owned(),
+                prefix: vec![],
+            }];
+            parse_toml_value::<CollectionConfiguration>(value, &sources)
+                .unwrap_err()
+                .to_string()
+        };
+        let configuration =
+            "configuration_version = 1\nreshade_version = 6\nname = \"Collection\"\n";
+
+        assert_eq!(
+            error(&format!(
+                "{}shaderpacks = [\"packs/Bloom\", {{ git = \"repo\", revison = \"v1\" }}]\n",
+                configuration
+            )),
+            "$.shaderpacks[1].revison: unknown field `revison`\n --> collection.toml:4:47\n  |\n4 | shaderpacks = [\"packs/Bloom\", { git = \"repo\", revison = \"v1\" }]\n  |                                               ^^^^^^^\n  = help: did you mean `revision`?"
+        );
+        assert_eq!(
+            error(&format!(
+                "{}[[shaderpacks]]\narchive = \"Bloom.zip\"\nrevision = \"v1\"\n",
+                configuration
+            )),
+            "$.shaderpacks[0]: `revision` can only be set for `git` shaderpacks\n --> collection.toml:4:1\n  |\n4 | [[shaderpacks]]\n  | ^^^^^^^^^^^^^^^"
+        );
+        assert!(error(&format!(
+            "{}shaderpacks = [{{ git = \"repo\" }}]\n",
+            configuration
+        ))
+        .starts_with("$.shaderpacks[0]: missing field `revision`"));
+        assert!(error(&format!("{}shaderpacks = [5]\n", configuration)).starts_with(
+            "$.shaderpacks[0]: invalid type: integer `5`, expected a shaderpack path or a table with an `archive` or `git` key"
+        ));
+        assert_eq!(
+            error(&format!("{}version = \"0.3\"\n", configuration)),
+            "$.version: unexpected end of input while parsing minor version number\n --> collection.toml:4:1\n  |\n4 | version = \"0.3\"\n  | ^^^^^^^"
+        );
+    }
+
+    #[test]
+    fn suggestions_need_a_similar_field() {
+        let message = "unknown field `Autors`, expected one of `Name`, `Authors`, `Shaders`";
+        assert_eq!(
+            suggest_field(message),
+            Some(("Autors".to_owned(), "Authors".to_owned()))
+        );
+        assert_eq!(
+            suggest_field("unknown field `shaders`, expected `Name` or `Shaders`"),
+            Some(("shaders".to_owned(), "Shaders".to_owned()))
+        );
+        assert_eq!(
+            suggest_field("unknown field `Zzz`, expected one of `Name`, `Authors`, `Shaders`"),
+            None
+        );
+        assert_eq!(suggest_field("invalid type: integer `5`"), None);
+
+        // The threshold is a Jaro-Winkler similarity of 0.8, e.g. 0.806 for the first and 0.781 for the second.
+        assert_eq!(
+            suggest_field("unknown field `xtures`, expected `textures`"),
+            Some(("xtures".to_owned(), "textures".to_owned()))
+        );
+        assert_eq!(
+            suggest_field("unknown field `shds`, expected `shaders`"),
+            None
+        );
+        assert!(!json_error("{ \"Name\": \"\", \"Authors\": [], \"Zzz\": 1 }").contains("help"));
+    }
+}
@@ -0,0 +1,607 @@
+use crate::parse::parse_json;
+use anyhow::{bail, Context, Result};
+use shaded_models::{
+    collection::CollectionShaderPackProvenance,
+    collection_lock::CollectionLockSource,
+    shaderpack::{constants::SHADERPACK_MANIFEST_FILENAME, FileDeclaration, ShaderPackManifest},
+};
+use std::{
+    cmp::Ordering,
+    fmt,
+    fs::{self, File},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::{Component, Path, PathBuf},
+    process::{Child, ChildStdin, ChildStdout, Command, Stdio},
+    sync::{Arc, Mutex},
+};
+use zip::ZipArchive;
+
+/// Where the manifest and files of a shaderpack are read from.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone)]
+pub enum ShaderPackSource {
+    /// A directory on disk containing the manifest.
+    Directory(PathBuf),
+    /// A directory inside of a zip archive containing the manifest, read without extracting the archive.
+    Archive {
+        path: PathBuf,
+        /// The path of the directory inside of the archive, empty or ending with `/`.
+        root: String,
+        /// The archive, opened once when the source is opened.
+        archive: SharedHandle<ZipArchive<File>>,
+    },
+    /// A directory inside of a local git repository at a pinned commit, read without touching the working tree.
+    Git {
+        repository: PathBuf,
+        /// The revision that was requested, e.g. a tag.
+        revision: String,
+        /// The full hash of the commit the revision resolved to.
+        commit: String,
+        /// The path of the directory inside of the repository, empty or ending with `/`.
+        root: String,
+        /// The process reading files from the repository, started by the first read.
+        objects: SharedHandle<Option<GitObjectReader>>,
+    },
+}
+
+/// An open resource of a source that is shared between its clones, and ignored when comparing sources.
+pub struct SharedHandle<T>(Arc<Mutex<T>>);
+
+impl<T> SharedHandle<T> {
+    fn new(value: T) -> Self {
+        Self(Arc::new(Mutex::new(value)))
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, T> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<T> Clone for SharedHandle<T> {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl<T> fmt::Debug for SharedHandle<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedHandle").finish_non_exhaustive()
+    }
+}
+
+impl<T> PartialEq for SharedHandle<T> {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl<T> Eq for SharedHandle<T> {}
+
+impl<T> PartialOrd for SharedHandle<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T> Ord for SharedHandle<T> {
+    fn cmp(&self, _: &Self) -> Ordering {
+        Ordering::Equal
+    }
+}
+
+impl fmt::Display for ShaderPackSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShaderPackSource::Directory(path) => write!(f, "{}", path.display()),
+            ShaderPackSource::Archive { path, root, .. } => {
+                write!(f, "{}:/{}", path.display(), root)
+            }
+            ShaderPackSource::Git {
+                repository,
+                revision,
+                root,
+                ..
+            } => write!(f, "{}@{}:/{}", repository.display(), revision, root),
+        }
+    }
+}
+
+impl ShaderPackSource {
+    /// Use a zip archive as a shaderpack source.
+    ///
+    /// # Arguments
+    /// * `root`: The directory inside of the archive containing the manifest. If `None`, the shallowest
+    ///   directory with a manifest is used.
+    pub fn open_archive(path: &Path, root: Option<&Path>) -> Result<Self> {
+        let archive = open_archive(path)?;
+        let root = match root {
+            Some(root) => directory_prefix(root)?,
+            None => find_manifest_root(archive.file_names()).with_context(|| {
+                format!(
+                    "The archive {:?} does not contain a {}",
+                    path, SHADERPACK_MANIFEST_FILENAME
+                )
+            })?,
+        };
+
+        Ok(ShaderPackSource::Archive {
+            path: path.to_owned(),
+            root,
+            archive: SharedHandle::new(archive),
+        })
+    }
+
+    /// Use a local git repository at the given revision as a shaderpack source.
+    ///
+    /// # Arguments
+    /// * `root`: The directory inside of the repository containing the manifest. If `None`, the shallowest
+    ///   directory with a manifest is used.
+    pub fn open_git(repository: &Path, revision: &str, root: Option<&Path>) -> Result<Self> {
+        let commit = String::from_utf8_lossy(
+            &run_git(
+                repository,
+                // Revisions starting with `-` must not be read as options.
+                &[
+                    "rev-parse",
+                    "--verify",
+                    "--end-of-options",
+                    &format!("{}^{{commit}}", revision),
+                ],
+            )
+            .with_context(|| {
+                format!(
+                    "Failed to resolve revision '{}' in git repository {:?}",
+                    revision, repository
+                )
+            })?,
+        )
+        .trim()
+        .to_owned();
+
+        let root = match root {
+            Some(root) => directory_prefix(root)?,
+            None => {
+                let files = run_git(repository, &["ls-tree", "-r", "-z", "--name-only", &commit])
+                    .with_context(|| {
+                    format!(
+                        "Failed to list the files of git repository {:?}",
+                        repository
+                    )
+                })?;
+                find_manifest_root(String::from_utf8_lossy(&files).split('\0')).with_context(
+                    || {
+                        format!(
+                            "The git repository {:?} does not contain a {} at revision '{}'",
+                            repository, SHADERPACK_MANIFEST_FILENAME, revision
+                        )
+                    },
+                )?
+            }
+        };
+
+        Ok(ShaderPackSource::Git {
+            repository: repository.to_owned(),
+            revision: revision.to_owned(),
+            commit,
+            root,
+            objects: SharedHandle::new(None),
+        })
+    }
+
+    /// Where this shaderpack was built from, for recording in the collection manifest.
+    pub fn provenance(&self) -> Option<CollectionShaderPackProvenance> {
+        match self {
+            ShaderPackSource::Directory(_) => None,
+            ShaderPackSource::Archive { path, .. } => {
+                Some(CollectionShaderPackProvenance::Archive {
+                    file_name: path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().into_owned())
+                        .unwrap_or_default(),
+                })
+            }
+            ShaderPackSource::Git {
+                revision, commit, ..
+            } => Some(CollectionShaderPackProvenance::Git {
+                revision: revision.clone(),
+                commit: commit.clone(),
+            }),
+        }
+    }
+
+    /// Describe this source for a collection lock, with paths relative to the given directory where possible.
+    pub fn lock_source(&self, base_directory: &Path) -> CollectionLockSource {
+        let relative = |path: &Path| path.strip_prefix(base_directory).unwrap_or(path).to_owned();
+        match self {
+            ShaderPackSource::Directory(path) => CollectionLockSource::Directory {
+                path: relative(path),
+            },
+            ShaderPackSource::Archive { path, root, .. } => CollectionLockSource::Archive {
+                path: relative(path),
+                root: root.clone(),
+            },
+            ShaderPackSource::Git {
+                repository,
+                revision,
+                commit,
+                root,
+                ..
+            } => CollectionLockSource::Git {
+                repository: relative(repository),
+                revision: revision.clone(),
+                commit: commit.clone(),
+                root: root.clone(),
+            },
+        }
+    }
+
+    /// Read the contents of a file relative to the shaderpack manifest.
+    pub fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self {
+            ShaderPackSource::Directory(directory) => fs::read(directory.join(path)),
+            ShaderPackSource::Archive { root, archive, .. } => {
+                let name = archive_entry_name(root, path)?;
+                let mut archive = archive.lock();
+                let mut file = archive
+                    .by_name(&name)
+                    .map_err(|err| io::Error::new(io::ErrorKind::NotFound, err))?;
+                let mut contents = vec![];
+                file.read_to_end(&mut contents)?;
+                Ok(contents)
+            }
+            ShaderPackSource::Git {
+                repository,
+                commit,
+                root,
+                objects,
+                ..
+            } => {
+                let object = format!("{}:{}", commit, archive_entry_name(root, path)?);
+                let mut objects = objects.lock();
+                let reader = match objects.as_mut() {
+                    Some(reader) => reader,
+                    None => objects.insert(GitObjectReader::spawn(repository)?),
+                };
+                let result = reader.read(&object);
+                // The process can't be reused if its output was only partially read.
+                if result
+                    .as_ref()
+                    .is_err_and(|err| err.kind() != io::ErrorKind::NotFound)
+                {
+                    *objects = None;
+                }
+                result
+            }
+        }
+    }
+
+    /// Describe where a file of this shaderpack is read from, e.g. for error messages.
+    pub fn describe_path(&self, path: &Path) -> String {
+        match self {
+            ShaderPackSource::Directory(directory) => directory.join(path).display().to_string(),
+            _ => format!("{}{}", self, path.display()),
+        }
+    }
+
+    /// Read and parse the shaderpack manifest.
+    pub fn load_manifest(&self) -> Result<ShaderPackManifest> {
+        parse_json(
+            &self.describe_path(Path::new(SHADERPACK_MANIFEST_FILENAME)),
+            &self
+                .read(Path::new(SHADERPACK_MANIFEST_FILENAME))
+                .with_context(|| format!("Failed to read shaderpack manifest in {}", self))?,
+        )
+        .with_context(|| format!("Failed to parse shaderpack manifest in {}", self))
+    }
+
+    /// Copy a file declaration of this shaderpack to the given output directory.
+    pub fn copy_to_output_path(
+        &self,
+        file: &FileDeclaration,
+        output_base_path: &Path,
+    ) -> Result<()> {
+        match self {
+            ShaderPackSource::Directory(directory) => {
+                file.copy_to_output_path(directory, output_base_path)?
+            }
+            ShaderPackSource::Archive { .. } | ShaderPackSource::Git { .. } => file
+                .write_to_output_path(
+                    &self.read(&file.source).with_context(|| {
+                        format!("Failed to read {:?} from {}", file.source, self)
+                    })?,
+                    output_base_path,
+                )?,
+        }
+        Ok(())
+    }
+}
+
+fn open_archive(path: &Path) -> Result<ZipArchive<File>> {
+    ZipArchive::new(File::open(path).with_context(|| format!("Failed to open archive {:?}", path))?)
+        .with_context(|| format!("Failed to read archive {:?}", path))
+}
+
+/// Run a git command inside of a repository and return its standard output.
+fn run_git(repository: &Path, arguments: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(repository)
+        .args(arguments)
+        .output()
+        .context("Failed to run git, make sure it is installed and on the PATH")?;
+    if !output.status.success() {
+        bail!("{}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(output.stdout)
+}
+
+/// A `git cat-file --batch` process that reads the objects of a repository one after another.
+pub struct GitObjectReader {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl GitObjectReader {
+    fn spawn(repository: &Path) -> io::Result<Self> {
+        let mut child = Command::new("git")
+            .arg("-C")
+            .arg(repository)
+            .args(["cat-file", "--batch"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|err| {
+                io::Error::new(
+                    err.kind(),
+                    format!(
+                        "Failed to run git, make sure it is installed and on the PATH: {}",
+                        err
+                    ),
+                )
+            })?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err(io::Error::other("Failed to open the git process pipes"));
+        };
+        Ok(Self {
+            child,
+            stdin,
+            stdout: BufReader::new(stdout),
+        })
+    }
+
+    /// Read the contents of a blob, e.g. `<commit>:<path>`.
+    fn read(&mut self, object: &str) -> io::Result<Vec<u8>> {
+        if object.contains('\n') {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("{:?} is not a valid object name", object),
+            ));
+        }
+        writeln!(self.stdin, "{}", object)?;
+        self.stdin.flush()?;
+
+        // Objects are returned as `<hash> <type> <size>` followed by the contents, or `<object> missing`.
+        let mut header = String::new();
+        self.stdout.read_line(&mut header)?;
+        let header = header.trim_end();
+        let size = match header.rsplitn(3, ' ').collect::<Vec<_>>().as_slice() {
+            [size, "blob", _] => size
+                .parse::<usize>()
+                .map_err(|_| io::Error::other(format!("Unexpected git output '{}'", header)))?,
+            [size, object_type, _] if size.parse::<usize>().is_ok() => {
+                // The contents of other objects still have to be consumed.
+                let mut skipped = vec![0; size.parse::<usize>().unwrap_or_default() + 1];
+                self.stdout.read_exact(&mut skipped)?;
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} is a {}, not a file", object, object_type),
+                ));
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotFound,
+                    format!("{} does not exist", object),
+                ))
+            }
+        };
+
+        // The contents are followed by a newline.
+        let mut contents = vec![0; size + 1];
+        self.stdout.read_exact(&mut contents)?;
+        contents.pop();
+        Ok(contents)
+    }
+}
+
+impl Drop for GitObjectReader {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+    }
+}
+
+/// Find the shallowest directory containing a shaderpack manifest in a list of `/` separated file paths.
+fn find_manifest_root<'a>(file_names: impl Iterator<Item = &'a str>) -> Option<String> {
+    file_names
+        .filter_map(|name| name.strip_suffix(SHADERPACK_MANIFEST_FILENAME))
+        .filter(|root| root.is_empty() || root.ends_with('/'))
+        .min_by_key(|root| root.matches('/').count())
+        .map(str::to_owned)
+}
+
+/// Convert a relative directory path into a `/` separated prefix, empty or ending with `/`.
+fn directory_prefix(path: &Path) -> io::Result<String> {
+    Ok(match archive_entry_name("", path)? {
+        prefix if prefix.is_empty() => prefix,
+        prefix => format!("{}/", prefix),
+    })
+}
+
+/// Get the name of the archive or repository entry for a path relative to the given root, using `/` as the separator.
+///
+/// Paths that are absolute or contain `..` are rejected, as they could point outside of the root.
+fn archive_entry_name(root: &str, path: &Path) -> io::Result<String> {
+    let mut name = root.to_owned();
+    for component in path.components() {
+        match component {
+            Component::Normal(component) => {
+                if !name.is_empty() && !name.ends_with('/') {
+                    name.push('/');
+                }
+                name.push_str(&component.to_string_lossy());
+            }
+            Component::CurDir => {}
+            Component::ParentDir | Component::RootDir | Component::Prefix(_) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{:?} must be a relative path without '..' components", path),
+                ))
+            }
+        }
+    }
+    Ok(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    const MANIFEST: &str = r#"{
+        "ManifestVersion": 1,
+        "ReShadeVersion": 6,
+        "Name": "Bloom",
+        "Authors": ["Author"],
+        "Description": "Description"
+    }"#;
+
+    #[test]
+    fn archive_entry_names_are_joined_with_slashes() {
+        assert_eq!(
+            archive_entry_name("packs/Bloom/", Path::new("./Shaders/bloom.fx")).unwrap(),
+            "packs/Bloom/Shaders/bloom.fx"
+        );
+        assert_eq!(
+            directory_prefix(Path::new("packs/Bloom")).unwrap(),
+            "packs/Bloom/"
+        );
+        assert_eq!(directory_prefix(Path::new(".")).unwrap(), "");
+    }
+
+    #[test]
+    fn archive_entry_names_outside_of_the_root_are_rejected() {
+        for path in ["../secret.fx", "Shaders/../../secret.fx", "/etc/passwd"] {
+            let err = archive_entry_name("packs/Bloom/", Path::new(path)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidInput, "{}", path);
+        }
+    }
+
+    #[test]
+    fn archives_are_read_from_their_manifest_root() {
+        let directory = env::temp_dir().join(format!("shaded-source-zip-{}", std::process::id()));
+        fs::create_dir_all(&directory).unwrap();
+        let path = directory.join("Bloom.zip");
+        let mut writer = ZipWriter::new(File::create(&path).unwrap());
+        for (name, contents) in [
+            ("README.md", "Outside of the shaderpack"),
+            ("Bloom/Shaders/Bloom.fx", "technique Bloom {}"),
+            ("Bloom/shaded-manifest.json", MANIFEST),
+            ("Bloom/Nested/shaded-manifest.json", "{}"),
+        ] {
+            writer
+                .start_file(name, SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(contents.as_bytes()).unwrap();
+        }
+        writer.finish().unwrap();
+
+        // The archive stays open while the source exists, so everything is read before it is removed.
+        let source = ShaderPackSource::open_archive(&path, None).unwrap();
+        let root = match &source {
+            ShaderPackSource::Archive { root, .. } => root.clone(),
+            _ => unreachable!(),
+        };
+        let manifest = source.load_manifest();
+        let shader = source.read(Path::new("Shaders/Bloom.fx"));
+        let missing = source.read(Path::new("Shaders/Missing.fx"));
+        let outside = source.read(Path::new("../README.md"));
+        let nested = ShaderPackSource::open_archive(&path, Some(Path::new("Bloom/Nested")))
+            .unwrap()
+            .read(Path::new(SHADERPACK_MANIFEST_FILENAME));
+        drop(source);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert_eq!(root, "Bloom/");
+        assert_eq!(manifest.unwrap().name, "Bloom");
+        assert_eq!(shader.unwrap(), b"technique Bloom {}");
+        assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(outside.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(nested.unwrap(), b"{}");
+    }
+
+    #[test]
+    fn git_repositories_are_read_at_a_revision_over_one_process() {
+        let directory = env::temp_dir().join(format!("shaded-source-git-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&directory);
+        fs::create_dir_all(directory.join("packs/Bloom/Shaders")).unwrap();
+        fs::write(
+            directory
+                .join("packs/Bloom")
+                .join(SHADERPACK_MANIFEST_FILENAME),
+            MANIFEST,
+        )
+        .unwrap();
+        fs::write(directory.join("packs/Bloom/Shaders/Bloom.fx"), "v1").unwrap();
+        fs::write(directory.join("packs/Bloom/Shaders/Common.fxh"), "common").unwrap();
+        let git = |arguments: &[&str]| {
+            run_git(
+                &directory,
+                &[
+                    &["-c", "user.name=Test", "-c", "user.email=test@example.com"],
+                    arguments,
+                ]
+                .concat(),
+            )
+            .unwrap()
+        };
+        git(&["init", "-q"]);
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "First"]);
+        git(&["tag", "v1"]);
+        // Later changes to the working tree and history must not affect the pinned revision.
+        fs::write(directory.join("packs/Bloom/Shaders/Bloom.fx"), "v2").unwrap();
+        git(&["commit", "-q", "-a", "-m", "Second"]);
+
+        let source = ShaderPackSource::open_git(&directory, "v1", None);
+        let missing_revision = ShaderPackSource::open_git(&directory, "v2", None);
+        let source = source.unwrap();
+        let process_id = |source: &ShaderPackSource| match source {
+            ShaderPackSource::Git { objects, .. } => {
+                objects.lock().as_ref().map(|reader| reader.child.id())
+            }
+            _ => unreachable!(),
+        };
+        let manifest = source.load_manifest();
+        let first_process = process_id(&source);
+        let shader = source.read(Path::new("Shaders/Bloom.fx"));
+        let missing = source.read(Path::new("Shaders/Missing.fx"));
+        let tree = source.read(Path::new("Shaders"));
+        let header = source.read(Path::new("Shaders/Common.fxh"));
+        let last_process = process_id(&source);
+        drop(source);
+        fs::remove_dir_all(&directory).unwrap();
+
+        assert!(format!("{:#}", missing_revision.unwrap_err())
+            .contains("Failed to resolve revision 'v2'"));
+        assert_eq!(manifest.unwrap().name, "Bloom");
+        assert_eq!(shader.unwrap(), b"v1");
+        assert_eq!(missing.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(tree.unwrap_err().kind(), io::ErrorKind::NotFound);
+        assert_eq!(header.unwrap(), b"common");
+        // Missing objects don't stop the process from being reused.
+        assert!(first_process.is_some());
+        assert_eq!(first_process, last_process);
+    }
+}
@@ -0,0 +1,922 @@
+use crate::source::ShaderPackSource;
+use anyhow::{anyhow, Context, Error, Result};
+use semver::{Version, VersionReq};
+use shaded_models::{
+    addon::{
+        constants::{ADDON_ENTRYPOINT_EXPORTS, ADDON_METADATA_EXPORTS},
+        AddonArchitecture, AddonBinary, AddonReadErrorKind,
+    },
+    effect::{EffectFile, EffectTexture, EFFECT_ENTRYPOINT_EXTENSION},
+    image::{ImageFormat, ImageInfo, ImageReadErrorKind},
+    license::{detect_license, expression_includes},
+    preset::Preset,
+    shaderpack::{FileDeclaration, ShaderPackDependency, ShaderPackManifest},
+};
+use spdx::Expression;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+#[derive(Debug)]
+pub enum ProblemType {
+    Warning(String),
+    Error(Error),
+}
+
+/// The problems found while validating one part of a shaderpack.
+#[derive(Debug)]
+pub struct ValidationSection {
+    pub name: &'static str,
+    /// Information gathered about the shaderpack while validating it.
+    pub notes: Vec<String>,
+    pub problems: Vec<(String, ProblemType)>,
+}
+
+impl ValidationSection {
+    fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            notes: vec![],
+            problems: vec![],
+        }
+    }
+}
+
+/// Whether any of the validation sections contain an error.
+pub fn has_errors(sections: &[ValidationSection]) -> bool {
+    sections.iter().any(|section| {
+        section
+            .problems
+            .iter()
+            .any(|(_, problem)| matches!(problem, ProblemType::Error(_)))
+    })
+}
+
+/// Map the name of every shaderpack that is depended on to the names of the shaderpacks depending on it.
+pub fn find_dependents<'a>(
+    manifests: impl IntoIterator<Item = &'a ShaderPackManifest>,
+) -> BTreeMap<String, Vec<String>> {
+    let mut dependents: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for manifest in manifests {
+        for dependency in manifest.dependencies.iter().flatten() {
+            let names = dependents.entry(dependency.name.clone()).or_default();
+            if !names.contains(&manifest.name) {
+                names.push(manifest.name.clone());
+            }
+        }
+    }
+    dependents
+}
+
+/// Run every validation check on a shaderpack, reading its files from the given source.
+///
+/// # Arguments
+/// * `dependents`: The names of the shaderpacks validated alongside this one that depend on it.
+pub fn validate_shaderpack(
+    source: &ShaderPackSource,
+    manifest: &ShaderPackManifest,
+    dependents: &[String],
+) -> Result<Vec<ValidationSection>> {
+    let mut sections = vec![];
+
+    // Informational field validation.
+    {
+        let mut section = ValidationSection::new("Information");
+
+        // Validate name.
+        if let Some(name_problems) = validate_string_entry("name", &manifest.name) {
+            section.problems.extend(name_problems);
+        }
+
+        // Validate version.
+        if let Some(version_problems) =
+            validate_version_entry("version", &manifest.version, dependents)
+        {
+            section.problems.extend(version_problems);
+        }
+
+        // Validate license.
+        if let Some(license_problems) =
+            validate_license_entry(source, "license", &manifest.license_file)
+        {
+            section.problems.extend(license_problems);
+        }
+
+        // Validate license expression.
+        if let Some(license_expression_problems) = validate_license_expression_entry(
+            source,
+            "license",
+            &manifest.license,
+            &manifest.license_file,
+        ) {
+            section.problems.extend(license_expression_problems);
+        }
+
+        // Validate description.
+        if let Some(description_problems) =
+            validate_string_entry("description", &manifest.description)
+        {
+            section.problems.extend(description_problems);
+        }
+
+        // Validate authors.
+        if let Some(authors_problems) = validate_string_entry_vec("authors", &manifest.authors) {
+            section.problems.extend(authors_problems);
+        }
+
+        sections.push(section);
+    }
+
+    // Shader validation.
+    if let Some(shaders) = &manifest.shaders {
+        let mut section = ValidationSection::new("Shaders");
+
+        if let Some(file_declaration_problems) = validate_file_declarations(source, shaders)? {
+            section.problems.extend(file_declaration_problems);
+        }
+
+        sections.push(section);
+    }
+
+    if let Some(textures) = &manifest.textures {
+        let mut section = ValidationSection::new("Textures");
+
+        if let Some(file_declaration_problems) = validate_file_declarations(source, textures)? {
+            section.problems.extend(file_declaration_problems);
+        }
+
+        if let Some(texture_file_problems) = validate_texture_files(source, textures) {
+            section.problems.extend(texture_file_problems);
+        }
+
+        sections.push(section);
+    }
+
+    let effects = manifest.load_effects(|path| source.read(path));
+
+    // Texture reference validation.
+    if manifest.shaders.is_some() || manifest.textures.is_some() {
+        let mut section = ValidationSection::new("Texture References");
+
+        if let Some(texture_reference_problems) = validate_texture_references(
+            source,
+            &effects,
+            manifest.textures.as_deref().unwrap_or_default(),
+        ) {
+            section.problems.extend(texture_reference_problems);
+        }
+
+        sections.push(section);
+    }
+
+    if let Some(presets) = &manifest.presets {
+        let mut section = ValidationSection::new("Presets");
+
+        if let Some(file_declaration_problems) = validate_file_declarations(source, presets)? {
+            section.problems.extend(file_declaration_problems);
+        }
+
+        sections.push(section);
+    }
+
+    // Preset reference validation.
+    if manifest.presets.is_some() {
+        let mut section = ValidationSection::new("Preset References");
+
+        if let Some(preset_reference_problems) =
+            validate_preset_references(&effects, &manifest.load_presets(|path| source.read(path)))
+        {
+            section.problems.extend(preset_reference_problems);
+        }
+
+        sections.push(section);
+    }
+
+    if let Some(addons) = &manifest.addons {
+        let mut section = ValidationSection::new("Addons");
+
+        if let Some(file_declaration_problems) = validate_file_declarations(source, addons)? {
+            section.problems.extend(file_declaration_problems);
+        }
+
+        let addon_binaries: Vec<_> = addons
+            .iter()
+            .map(|addon| {
+                (
+                    addon,
+                    source
+                        .read(&addon.source)
+                        .map_err(AddonReadErrorKind::ReadFailure)
+                        .and_then(|contents| AddonBinary::parse(&contents)),
+                )
+            })
+            .collect();
+        for (addon, binary) in &addon_binaries {
+            if let Ok(binary) = binary {
+                section.notes.push(format!(
+                    "{}: {} binary",
+                    addon.source.display(),
+                    binary.machine
+                ));
+            }
+        }
+
+        if let Some(addon_binary_problems) = validate_addon_binaries(&addon_binaries) {
+            section.problems.extend(addon_binary_problems);
+        }
+
+        sections.push(section);
+    }
+
+    if let Some(dependencies) = &manifest.dependencies {
+        let mut section = ValidationSection::new("Dependencies");
+
+        if let Some(dependency_problems) = validate_dependencies(&manifest.name, dependencies) {
+            section.problems.extend(dependency_problems);
+        }
+
+        sections.push(section);
+    }
+
+    Ok(sections)
+}
+
+// String
+
+fn validate_string_entry(
+    field_name: &'static str,
+    str: &String,
+) -> Option<Vec<(String, ProblemType)>> {
+    let mut failures = vec![];
+
+    if str != str.trim() {
+        failures.push((
+            field_name.to_owned(),
+            ProblemType::Error(anyhow!(
+                "Contains empty whitespace at start or end of entry./"
+            )),
+        ));
+    }
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(failures)
+    }
+}
+
+/// Versions are already checked to be valid semver versions when the manifest is parsed.
+fn validate_version_entry(
+    field_name: &'static str,
+    version: &Option<Version>,
+    dependents: &[String],
+) -> Option<Vec<(String, ProblemType)>> {
+    let mut failures = vec![];
+
+    // Rule(warning): Shaderpacks that others depend on should declare a version.
+    if version.is_none() && !dependents.is_empty() {
+        failures.push((
+            field_name.to_owned(),
+            ProblemType::Warning(format!(
+                "No version is declared, so version requirements of the shaderpacks depending on it can never match: {}",
+                dependents
+                    .iter()
+                    .map(|name| format!("'{}'", name))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+        ));
+    }
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(failures)
+    }
+}
+
+fn validate_license_entry(
+    source: &ShaderPackSource,
+    field_name: &'static str,
+    path: &Option<PathBuf>,
+) -> Option<Vec<(String, ProblemType)>> {
+    let mut failures = vec![];
+
+    if let Some(path) = path {
+        // Rule(error): License must exist.
+        if let Err(err) = source.read(path) {
+            failures.push((field_name.to_owned(), ProblemType::Error(err.into())));
+        }
+    } else {
+        // Rule(warning): Shaderpacks should contain a LICENSE file.
+        failures.push((
+            field_name.to_owned(),
+            ProblemType::Warning("No license file has been set".to_owned()),
+        ));
+    };
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(failures)
+    }
+}
+
+fn validate_license_expression_entry(
+    source: &ShaderPackSource,
+    field_name: &'static str,
+    expression: &Option<String>,
+    license_file_path: &Option<PathBuf>,
+) -> Option<Vec<(String, ProblemType)>> {
+    let mut failures = vec![];
+
+    // Unreadable license files are already reported by the license file checks.
+    let detected_license = license_file_path
+        .as_ref()
+        .and_then(|path| source.read(path).ok())
+        .and_then(|text| detect_license(&String::from_utf8_lossy(&text)));
+
+    match expression {
+        Some(expression) => match Expression::parse(expression) {
+            Ok(parsed) => {
+                // Rule(warning): The declared license should match the license file.
+                if let Some(detected_license) = detected_license {
+                    if !expression_includes(&parsed, detected_license) {
+                        failures.push((
+                            field_name.to_owned(),
+                            ProblemType::Warning(format!(
+                                "Declared license '{}' does not match the license file, which appears to be {}",
+                                expression, detected_license
+                            )),
+                        ));
+                    }
+                }
+            }
+            // Rule(error): Licenses must be valid SPDX expressions.
+            Err(err) => failures.push((
+                field_name.to_owned(),
+                ProblemType::Error(anyhow!("Invalid SPDX license expression: {}", err)),
+            )),
+        },
+        // Rule(warning): Shaderpacks with a recognised license file should also declare it as an SPDX license.
+        None => {
+            if let Some(detected_license) = detected_license {
+                failures.push((
+                    field_name.to_owned(),
+                    ProblemType::Warning(format!(
+                        "No SPDX license has been set, the license file appears to be {}",
+                        detected_license
+                    )),
+                ));
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(failures)
+    }
+}
+
+fn validate_string_entry_vec(
+    field_name: &'static str,
+    strings: &Vec<String>,
+) -> Option<Vec<(String, ProblemType)>> {
+    let mut failures = vec![];
+    for string in strings {
+        if let Some(string_failures) = validate_string_entry(field_name, string) {
+            failures.extend(string_failures);
+        }
+    }
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(failures)
+    }
+}
+
+fn validate_file_declarations(
+    source: &ShaderPackSource,
+    declarations: &[FileDeclaration],
+) -> Result<Option<Vec<(String, ProblemType)>>> {
+    let mut failures = vec![];
+
+    for declaration in declarations {
+        // Source validation
+        {
+            let source_path = declaration.source.to_str().unwrap_or_default().to_owned();
+
+            // Rule: Directories cannot start of end with.
+            if source_path != source_path.trim() {
+                failures.push((
+                    source_path,
+                    ProblemType::Error(anyhow!("contains leading or trailing whitespace")),
+                ));
+                continue;
+            }
+
+            // Rule: All files must exist in the shaderpack source.
+            if let Err(err) = source.read(&declaration.source) {
+                failures.push((source_path, ProblemType::Error(err.into())));
+                continue;
+            }
+        }
+
+        // Output validation
+        {
+            // Rule: Output paths cannot contain directory escapes
+            let output_string = declaration
+                .output
+                .to_str()
+                .context("Unable to convert output path to string")?
+                .to_string();
+            if output_string.contains("./") {
+                failures.push((
+                    declaration.output.to_str().unwrap_or_default().to_owned(),
+                    ProblemType::Error(anyhow!(
+                        "Output paths cannot contain directory escapes like '../'"
+                    )),
+                ));
+                continue;
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(failures))
+    }
+}
+
+// Texture References
+
+fn validate_texture_references(
+    source: &ShaderPackSource,
+    effects: &[(&FileDeclaration, EffectFile)],
+    textures: &[FileDeclaration],
+) -> Option<Vec<(String, ProblemType)>> {
+    let mut failures = vec![];
+    let mut referenced_textures = vec![false; textures.len()];
+    let texture_images: Vec<Option<ImageInfo>> = textures
+        .iter()
+        .map(|texture| read_image(source, texture).ok())
+        .collect();
+
+    for (shader, effect) in effects {
+        for effect_texture in &effect.textures {
+            let Some(texture_source) = &effect_texture.source else {
+                continue;
+            };
+
+            let mut resolved = false;
+            for (index, texture) in textures.iter().enumerate() {
+                if texture_reference_matches(texture_source, &texture.output) {
+                    referenced_textures[index] = true;
+                    resolved = true;
+
+                    if let Some(image) = &texture_images[index] {
+                        failures.extend(validate_texture_declaration(
+                            &format!("{}: {}", shader.source.display(), effect_texture.name),
+                            effect_texture,
+                            image,
+                        ));
+                    }
+                }
+            }
+
+            // Rule(warning): Textures referenced by shaders should be shipped with the shaderpack.
+            if !resolved {
+                failures.push((
+                    format!("{}: {}", shader.source.display(), texture_source),
+                    ProblemType::Warning(
+                        "Texture is referenced by shader but not declared as a texture output"
+                            .to_owned(),
+                    ),
+                ));
+            }
+        }
+    }
+
+    // Rule(warning): Declared textures should be referenced by at least one shader.
+    for (texture, referenced) in textures.iter().zip(referenced_textures) {
+        if !referenced {
+            failures.push((
+                texture.output.display().to_string(),
+                ProblemType::Warning("Texture is not referenced by any declared shader".to_owned()),
+            ));
+        }
+    }
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(failures)
+    }
+}
+
+/// Compare a shader's `texture` declaration against the image it loads from its `source` annotation.
+fn validate_texture_declaration(
+    key: &str,
+    declaration: &EffectTexture,
+    image: &ImageInfo,
+) -> Vec<(String, ProblemType)> {
+    let mut failures = vec![];
+
+    // Rule(warning): Declared dimensions should match the image, otherwise ReShade resizes it on load.
+    let declared_width = declaration.width.unwrap_or(1);
+    let declared_height = declaration.height.unwrap_or(1);
+    if (declaration.width.is_some() || declaration.height.is_some())
+        && (declared_width != image.width || declared_height != image.height)
+    {
+        failures.push((
+            key.to_owned(),
+            ProblemType::Warning(format!(
+                "Texture is declared as {}x{} but the image is {}x{} and will be resized when loaded",
+                declared_width, declared_height, image.width, image.height
+            )),
+        ));
+    }
+
+    // Rule(warning): Texture sources can only be loaded into certain formats.
+    let format = declaration.format.as_deref().unwrap_or("RGBA8");
+    match SOURCE_TEXTURE_FORMATS
+        .iter()
+        .find(|(name, _)| *name == format)
+    {
+        None => failures.push((
+            key.to_owned(),
+            ProblemType::Warning(format!(
+                "Texture format {} cannot be loaded from an image file, use one of {}",
+                format,
+                SOURCE_TEXTURE_FORMATS.map(|(name, _)| name).join(", ")
+            )),
+        )),
+        // Rule(warning): Image channels should not be discarded by the declared format.
+        Some((_, format_channels)) => {
+            if let Some(channels) = image.channels.filter(|c| c > format_channels) {
+                failures.push((
+                    key.to_owned(),
+                    ProblemType::Warning(format!(
+                        "Image has {} channels but texture format {} only stores {}",
+                        channels, format, format_channels
+                    )),
+                ));
+            }
+        }
+    }
+
+    failures
+}
+
+/// Whether a texture `source` annotation resolves to the given texture output path.
+///
+/// ReShade searches texture directories recursively and case-insensitively, so a reference also
+/// matches outputs placed in a sub-directory of the texture directory.
+fn texture_reference_matches(reference: &str, output: &Path) -> bool {
+    let normalize = |path: &str| {
+        path.replace('\\', "/")
+            .trim_start_matches("./")
+            .to_lowercase()
+    };
+    let reference = normalize(reference);
+    let output = normalize(&output.to_string_lossy());
+
+    output == reference || output.ends_with(&format!("/{reference}"))
+}
+
+// Texture Files
+
+/// The largest width or height of a 2D texture supported by Direct3D 11.
+const MAX_TEXTURE_DIMENSION: u32 = 16384;
+
+/// Texture formats that ReShade can load source images into, alongside their channel count.
+const SOURCE_TEXTURE_FORMATS: [(&str, u8); 3] = [("R8", 1), ("RG8", 2), ("RGBA8", 4)];
+
+fn validate_texture_files(
+    source: &ShaderPackSource,
+    textures: &[FileDeclaration],
+) -> Option<Vec<(String, ProblemType)>> {
+    let mut failures = vec![];
+
+    for texture in textures {
+        let key = texture.source.display().to_string();
+
+        // Rule(error): Textures must be readable images in a supported format.
+        let image = match read_image(source, texture) {
+            Ok(image) => image,
+            // Missing files are already reported by the file declaration checks.
+            Err(ImageReadErrorKind::ReadFailure(_)) => continue,
+            Err(err) => {
+                failures.push((key, ProblemType::Error(err.into())));
+                continue;
+            }
+        };
+
+        // Rule(warning): File extensions should match the actual image format.
+        let extension_format = texture
+            .source
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(ImageFormat::from_extension);
+        if extension_format != Some(image.format) {
+            failures.push((
+                key.clone(),
+                ProblemType::Warning(format!(
+                    "File extension does not match the actual image format ({})",
+                    image.format
+                )),
+            ));
+        }
+
+        // Rule(warning): Dimensions should be a power of two.
+        if !image.width.is_power_of_two() || !image.height.is_power_of_two() {
+            failures.push((
+                key.clone(),
+                ProblemType::Warning(format!(
+                    "Dimensions {}x{} are not a power of two",
+                    image.width, image.height
+                )),
+            ));
+        }
+
+        // Rule(warning): Dimensions should not exceed what graphics APIs support.
+        if image.width > MAX_TEXTURE_DIMENSION || image.height > MAX_TEXTURE_DIMENSION {
+            failures.push((
+                key,
+                ProblemType::Warning(format!(
+                    "Dimensions {}x{} exceed the maximum texture size of {}",
+                    image.width, image.height, MAX_TEXTURE_DIMENSION
+                )),
+            ));
+        }
+    }
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(failures)
+    }
+}
+
+/// Read the image of a texture declaration from the shaderpack source and parse its header.
+fn read_image(
+    source: &ShaderPackSource,
+    texture: &FileDeclaration,
+) -> Result<ImageInfo, ImageReadErrorKind> {
+    ImageInfo::parse(
+        &source
+            .read(&texture.source)
+            .map_err(ImageReadErrorKind::ReadFailure)?,
+    )
+}
+
+// Preset References
+
+fn validate_preset_references(
+    effects: &[(&FileDeclaration, EffectFile)],
+    presets: &[(&FileDeclaration, Preset)],
+) -> Option<Vec<(String, ProblemType)>> {
+    let mut failures = vec![];
+    let effects: Vec<(String, EffectFile)> = effects
+        .iter()
+        .filter(|(shader, _)| shader.has_extension(&[EFFECT_ENTRYPOINT_EXTENSION]))
+        .map(|(shader, effect)| (shader.output_file_name(), effect.clone()))
+        .collect();
+
+    // Rule(warning): Presets should only reference effects shipped with the shaderpack.
+    for (declaration, preset) in presets {
+        for error in preset.unresolved_references(&effects) {
+            failures.push((
+                format!("{} ({})", declaration.source.display(), error.reference()),
+                ProblemType::Warning(format!(
+                    "Preset reference does not resolve to any declared shader, {}",
+                    error
+                )),
+            ));
+        }
+    }
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(failures)
+    }
+}
+
+// Addons
+
+fn validate_addon_binaries(
+    addons: &[(&FileDeclaration, Result<AddonBinary, AddonReadErrorKind>)],
+) -> Option<Vec<(String, ProblemType)>> {
+    let mut failures = vec![];
+
+    for (addon, binary) in addons {
+        let key = addon.source.display().to_string();
+
+        // Rule(error): Addons must be PE binaries.
+        let binary = match binary {
+            Ok(binary) => binary,
+            // Missing files are already reported by the file declaration checks.
+            Err(AddonReadErrorKind::ReadFailure(_)) => continue,
+            Err(err) => {
+                failures.push((key, ProblemType::Error(anyhow!(err.to_string()))));
+                continue;
+            }
+        };
+
+        // Rule(error): Addons must be DLLs.
+        if !binary.is_dll {
+            failures.push((
+                key.clone(),
+                ProblemType::Error(anyhow!("Binary is not a DLL")),
+            ));
+        }
+
+        // Rule(error): Addons must be built for an architecture games can run on.
+        let Some(architecture) = binary.machine.architecture() else {
+            failures.push((
+                key,
+                ProblemType::Error(anyhow!(
+                    "Binary is built for {} which ReShade does not support",
+                    binary.machine
+                )),
+            ));
+            continue;
+        };
+
+        // Rule(error): The output extension must match the binary architecture.
+        // Rule(warning): Addons should use an architecture specific extension.
+        let output_extension = addon
+            .output
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or_default();
+        match AddonArchitecture::from_extension(output_extension) {
+            Some(extension_architecture) if extension_architecture != architecture => failures
+                .push((
+                    key.clone(),
+                    ProblemType::Error(anyhow!(
+                        "Output has the .{} extension but the binary is {}",
+                        output_extension,
+                        architecture
+                    )),
+                )),
+            Some(_) => {}
+            None => failures.push((
+                key.clone(),
+                ProblemType::Warning(format!(
+                    "Output should use the .{} extension so ReShade only loads it in {} games",
+                    architecture.extension(),
+                    architecture
+                )),
+            )),
+        }
+
+        // Rule(error): Addon entry points must be exported together.
+        let [init, uninit] = ADDON_ENTRYPOINT_EXPORTS;
+        if binary.has_export(init) != binary.has_export(uninit) {
+            failures.push((
+                key.clone(),
+                ProblemType::Error(anyhow!(
+                    "Binary must export both '{}' and '{}' or neither of them",
+                    init,
+                    uninit
+                )),
+            ));
+        }
+
+        // Rule(warning): Addons should export their name and description.
+        let missing_exports: Vec<&str> = ADDON_METADATA_EXPORTS
+            .into_iter()
+            .filter(|export| !binary.has_export(export))
+            .collect();
+        if !missing_exports.is_empty() {
+            failures.push((
+                key,
+                ProblemType::Warning(format!(
+                    "Binary does not export the ReShade addon metadata {}",
+                    missing_exports.join(", ")
+                )),
+            ));
+        }
+    }
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(failures)
+    }
+}
+
+// Dependencies
+
+fn validate_dependencies(
+    name: &str,
+    dependencies: &[ShaderPackDependency],
+) -> Option<Vec<(String, ProblemType)>> {
+    let mut failures = vec![];
+
+    for (index, dependency) in dependencies.iter().enumerate() {
+        let key = format!("dependency '{}'", dependency.name);
+
+        // Rule(error): Dependencies must have a name.
+        if dependency.name.trim().is_empty() {
+            failures.push((
+                format!("dependency {}", index + 1),
+                ProblemType::Error(anyhow!("Dependency name cannot be empty")),
+            ));
+            continue;
+        }
+
+        // Rule(error): Shaderpacks cannot depend on themselves.
+        if dependency.name == name {
+            failures.push((
+                key.clone(),
+                ProblemType::Error(anyhow!("Shaderpack cannot depend on itself")),
+            ));
+        }
+
+        // Rule(warning): Dependencies should only be declared once.
+        if dependencies[..index]
+            .iter()
+            .any(|other| other.name == dependency.name)
+        {
+            failures.push((
+                key.clone(),
+                ProblemType::Warning("Dependency is declared more than once".to_owned()),
+            ));
+        }
+
+        // Rule(error): Version requirements must be valid semver requirements.
+        if let Some(version) = &dependency.version {
+            if let Err(err) = VersionReq::parse(version) {
+                failures.push((
+                    key,
+                    ProblemType::Error(
+                        anyhow!(err)
+                            .context(format!("'{}' is not a valid version requirement", version)),
+                    ),
+                ));
+            }
+        }
+    }
+
+    if failures.is_empty() {
+        None
+    } else {
+        Some(failures)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(name: &str, dependencies: &[&str]) -> ShaderPackManifest {
+        ShaderPackManifest {
+            name: name.to_owned(),
+            dependencies: Some(
+                dependencies
+                    .iter()
+                    .map(|name| ShaderPackDependency {
+                        name: (*name).to_owned(),
+                        version: None,
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn dependents_are_found_by_name() {
+        let manifests = [
+            manifest("Bloom", &["Common"]),
+            manifest("Tonemap", &["Common", "Common"]),
+            manifest("Common", &[]),
+        ];
+        assert_eq!(
+            find_dependents(&manifests),
+            BTreeMap::from([(
+                "Common".to_owned(),
+                vec!["Bloom".to_owned(), "Tonemap".to_owned()]
+            )])
+        );
+    }
+
+    #[test]
+    fn missing_versions_are_only_reported_for_dependencies() {
+        assert!(validate_version_entry("version", &None, &[]).is_none());
+        assert!(validate_version_entry("version", &Some(Version::new(1, 0, 0)), &[]).is_none());
+
+        let problems = validate_version_entry("version", &None, &["Bloom".to_owned()]).unwrap();
+        assert!(matches!(
+            problems.as_slice(),
+            [(_, ProblemType::Warning(warning))] if warning.ends_with(": 'Bloom'")
+        ));
+    }
+}
@@ -0,0 +1,446 @@
+// References:
+//  - https://learn.microsoft.com/en-us/windows/win32/debug/pe-format
+//  - https://github.com/crosire/reshade/blob/main/include/reshade.hpp
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use thiserror::Error;
+
+/// The architecture of the game process an addon is loaded into.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Clone, Copy,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum AddonArchitecture {
+    X86,
+    X64,
+}
+
+/// The game process architectures a collection is built for.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    Default,
+    Clone,
+    Copy,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetArchitecture {
+    X86,
+    X64,
+    #[default]
+    Both,
+}
+
+/// The target machine type from the COFF header of a PE file.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum PeMachine {
+    I386,
+    Amd64,
+    Arm64,
+    Unknown(u16),
+}
+
+/// Information read from the headers of an addon binary.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct AddonBinary {
+    pub machine: PeMachine,
+    /// Whether the file is marked as a dynamic-link library rather than an executable.
+    pub is_dll: bool,
+    /// The names of all exported symbols.
+    pub exports: Vec<String>,
+}
+
+#[derive(Error, Debug)]
+pub enum AddonReadErrorKind {
+    #[error("failed to read the addon file")]
+    ReadFailure(std::io::Error),
+
+    #[error("file is not a valid PE binary: {0}")]
+    InvalidBinary(&'static str),
+}
+
+impl fmt::Display for AddonArchitecture {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AddonArchitecture::X86 => "32-bit",
+            AddonArchitecture::X64 => "64-bit",
+        })
+    }
+}
+
+impl AddonArchitecture {
+    /// The addon file extension ReShade only loads in game processes of this architecture.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            AddonArchitecture::X86 => "addon32",
+            AddonArchitecture::X64 => "addon64",
+        }
+    }
+
+    /// Get the architecture an addon file extension is restricted to, ignoring case.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        [AddonArchitecture::X86, AddonArchitecture::X64]
+            .into_iter()
+            .find(|architecture| architecture.extension().eq_ignore_ascii_case(extension))
+    }
+}
+
+impl TargetArchitecture {
+    /// Whether addons of the given architecture should be included for this target.
+    pub fn includes(&self, architecture: AddonArchitecture) -> bool {
+        match self {
+            TargetArchitecture::X86 => architecture == AddonArchitecture::X86,
+            TargetArchitecture::X64 => architecture == AddonArchitecture::X64,
+            TargetArchitecture::Both => true,
+        }
+    }
+}
+
+impl fmt::Display for PeMachine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PeMachine::I386 => f.write_str("x86"),
+            PeMachine::Amd64 => f.write_str("x64"),
+            PeMachine::Arm64 => f.write_str("ARM64"),
+            PeMachine::Unknown(machine) => write!(f, "unknown machine type {machine:#06x}"),
+        }
+    }
+}
+
+impl PeMachine {
+    /// The game process architecture that binaries for this machine type can be loaded into.
+    pub fn architecture(&self) -> Option<AddonArchitecture> {
+        match self {
+            PeMachine::I386 => Some(AddonArchitecture::X86),
+            PeMachine::Amd64 => Some(AddonArchitecture::X64),
+            PeMachine::Arm64 | PeMachine::Unknown(_) => None,
+        }
+    }
+}
+
+impl AddonBinary {
+    /// Parse the PE headers and export table of an addon from its file contents.
+    pub fn parse(bytes: &[u8]) -> Result<Self, AddonReadErrorKind> {
+        let invalid = AddonReadErrorKind::InvalidBinary;
+        if !bytes.starts_with(b"MZ") {
+            return Err(invalid("missing DOS header"));
+        }
+
+        let pe_offset = read_u32(bytes, 0x3C).ok_or(invalid("file is truncated"))? as usize;
+        if bytes.get(pe_offset..pe_offset + 4) != Some(b"PE\0\0") {
+            return Err(invalid("missing PE signature"));
+        }
+
+        let coff_header = pe_offset + 4;
+        let machine = read_u16(bytes, coff_header).ok_or(invalid("file is truncated"))?;
+        let section_count = read_u16(bytes, coff_header + 2).ok_or(invalid("file is truncated"))?;
+        let optional_header_size =
+            read_u16(bytes, coff_header + 16).ok_or(invalid("file is truncated"))?;
+        let characteristics =
+            read_u16(bytes, coff_header + 18).ok_or(invalid("file is truncated"))?;
+
+        // The location of the data directories depends on whether this is a PE32 or PE32+ image.
+        let optional_header = coff_header + 20;
+        let (directory_count_offset, directories_offset) =
+            match read_u16(bytes, optional_header).ok_or(invalid("file is truncated"))? {
+                0x10B => (optional_header + 92, optional_header + 96),
+                0x20B => (optional_header + 108, optional_header + 112),
+                _ => return Err(invalid("unknown optional header magic")),
+            };
+
+        let sections: Vec<Section> = (0..section_count as usize)
+            .map(|index| {
+                let offset = optional_header + optional_header_size as usize + index * 40;
+                Some(Section {
+                    virtual_size: read_u32(bytes, offset + 8)?,
+                    virtual_address: read_u32(bytes, offset + 12)?,
+                    raw_size: read_u32(bytes, offset + 16)?,
+                    raw_offset: read_u32(bytes, offset + 20)?,
+                })
+            })
+            .collect::<Option<_>>()
+            .ok_or(invalid("section table is truncated"))?;
+
+        let mut exports = vec![];
+        let directory_count = read_u32(bytes, directory_count_offset).unwrap_or_default();
+        let export_directory = read_u32(bytes, directories_offset).unwrap_or_default();
+        if directory_count > 0 && export_directory != 0 {
+            let export_directory = rva_to_offset(&sections, export_directory)
+                .ok_or(invalid("export directory is outside of any section"))?;
+            let name_count = read_u32(bytes, export_directory + 24)
+                .ok_or(invalid("export directory is truncated"))?;
+            let names = read_u32(bytes, export_directory + 32)
+                .and_then(|rva| rva_to_offset(&sections, rva))
+                .ok_or(invalid("export directory is truncated"))?;
+
+            for index in 0..name_count as usize {
+                let name = read_u32(bytes, names + index * 4)
+                    .and_then(|rva| rva_to_offset(&sections, rva))
+                    .and_then(|offset| bytes.get(offset..))
+                    .and_then(|name| name.split(|byte| *byte == 0).next())
+                    .ok_or(invalid("export name table is truncated"))?;
+                exports.push(String::from_utf8_lossy(name).into_owned());
+            }
+        }
+
+        Ok(Self {
+            machine: match machine {
+                0x014C => PeMachine::I386,
+                0x8664 => PeMachine::Amd64,
+                0xAA64 => PeMachine::Arm64,
+                machine => PeMachine::Unknown(machine),
+            },
+            is_dll: characteristics & IMAGE_FILE_DLL != 0,
+            exports,
+        })
+    }
+
+    /// Whether the binary exports a symbol with the given name.
+    pub fn has_export(&self, name: &str) -> bool {
+        self.exports.iter().any(|export| export == name)
+    }
+}
+
+/// The COFF characteristics flag marking an image as a dynamic-link library.
+const IMAGE_FILE_DLL: u16 = 0x2000;
+
+struct Section {
+    virtual_size: u32,
+    virtual_address: u32,
+    raw_size: u32,
+    raw_offset: u32,
+}
+
+/// Convert a relative virtual address into a file offset using the section table.
+fn rva_to_offset(sections: &[Section], rva: u32) -> Option<usize> {
+    sections.iter().find_map(|section| {
+        let size = section.virtual_size.max(section.raw_size);
+        (rva >= section.virtual_address && rva < section.virtual_address.checked_add(size)?)
+            .then(|| (rva - section.virtual_address) as usize + section.raw_offset as usize)
+    })
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Constant values relating to ReShade addons.
+pub mod constants {
+    /// Addon exports providing the name and description shown in the ReShade addon list.
+    pub const ADDON_METADATA_EXPORTS: [&str; 2] = ["NAME", "DESCRIPTION"];
+
+    /// Optional addon entry points, which ReShade requires to be exported together.
+    pub const ADDON_ENTRYPOINT_EXPORTS: [&str; 2] = ["AddonInit", "AddonUninit"];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECTION_RVA: u32 = 0x1000;
+    const SECTION_OFFSET: usize = 0x200;
+
+    /// Build a minimal PE image with a single section holding an export directory.
+    ///
+    /// # Arguments
+    /// * `exports`: The exported names, or `None` for a binary without an export directory.
+    fn pe(machine: u16, pe32_plus: bool, exports: Option<&[&str]>) -> Vec<u8> {
+        let mut bytes = vec![0; SECTION_OFFSET];
+        bytes[..2].copy_from_slice(b"MZ");
+        bytes[0x3C..0x40].copy_from_slice(&0x40u32.to_le_bytes());
+
+        let optional_header_size: u16 = if pe32_plus { 240 } else { 224 };
+        bytes[0x40..0x44].copy_from_slice(b"PE\0\0");
+        bytes[0x44..0x46].copy_from_slice(&machine.to_le_bytes());
+        bytes[0x46..0x48].copy_from_slice(&1u16.to_le_bytes());
+        bytes[0x54..0x56].copy_from_slice(&optional_header_size.to_le_bytes());
+        bytes[0x56..0x58].copy_from_slice(&(IMAGE_FILE_DLL | 0x0002).to_le_bytes());
+
+        let optional_header = 0x58;
+        let (magic, directory_count_offset): (u16, usize) = match pe32_plus {
+            true => (0x20B, 108),
+            false => (0x10B, 92),
+        };
+        bytes[optional_header..optional_header + 2].copy_from_slice(&magic.to_le_bytes());
+        let directory_count = optional_header + directory_count_offset;
+        bytes[directory_count..directory_count + 4].copy_from_slice(&16u32.to_le_bytes());
+        if exports.is_some() {
+            let directory = directory_count + 4;
+            bytes[directory..directory + 4].copy_from_slice(&SECTION_RVA.to_le_bytes());
+        }
+
+        // The export directory, followed by the name pointer table and the names themselves.
+        let exports = exports.unwrap_or_default();
+        let mut section = vec![0; 40];
+        section[24..28].copy_from_slice(&(exports.len() as u32).to_le_bytes());
+        section[32..36].copy_from_slice(&(SECTION_RVA + 40).to_le_bytes());
+        let mut name_rva = SECTION_RVA + 40 + exports.len() as u32 * 4;
+        for export in exports {
+            section.extend(name_rva.to_le_bytes());
+            name_rva += export.len() as u32 + 1;
+        }
+        for export in exports {
+            section.extend(export.as_bytes());
+            section.push(0);
+        }
+
+        let section_header = optional_header + optional_header_size as usize;
+        let mut header = [0u8; 40];
+        header[..6].copy_from_slice(b".edata");
+        header[8..12].copy_from_slice(&(section.len() as u32).to_le_bytes());
+        header[12..16].copy_from_slice(&SECTION_RVA.to_le_bytes());
+        header[16..20].copy_from_slice(&(section.len() as u32).to_le_bytes());
+        header[20..24].copy_from_slice(&(SECTION_OFFSET as u32).to_le_bytes());
+        bytes[section_header..section_header + 40].copy_from_slice(&header);
+
+        bytes.extend(section);
+        bytes
+    }
+
+    fn invalid_reason(result: Result<AddonBinary, AddonReadErrorKind>) -> &'static str {
+        match result {
+            Err(AddonReadErrorKind::InvalidBinary(reason)) => reason,
+            result => panic!("expected an invalid binary, got {:?}", result),
+        }
+    }
+
+    #[test]
+    fn parses_dll_exports() {
+        let binary =
+            AddonBinary::parse(&pe(0x8664, true, Some(&["AddonInit", "AddonUninit"]))).unwrap();
+        assert_eq!(
+            binary,
+            AddonBinary {
+                machine: PeMachine::Amd64,
+                is_dll: true,
+                exports: vec!["AddonInit".to_owned(), "AddonUninit".to_owned()],
+            }
+        );
+        assert!(binary.has_export("AddonUninit"));
+        assert!(!binary.has_export("NAME"));
+    }
+
+    #[test]
+    fn parses_pe32_binaries() {
+        let binary = AddonBinary::parse(&pe(0x014C, false, Some(&["NAME"]))).unwrap();
+        assert_eq!(binary.machine, PeMachine::I386);
+        assert_eq!(binary.machine.architecture(), Some(AddonArchitecture::X86));
+        assert_eq!(binary.exports, vec!["NAME"]);
+    }
+
+    #[test]
+    fn parses_binaries_without_an_export_table() {
+        let binary = AddonBinary::parse(&pe(0xAA64, true, None)).unwrap();
+        assert_eq!(binary.machine, PeMachine::Arm64);
+        assert_eq!(binary.machine.architecture(), None);
+        assert!(binary.exports.is_empty());
+    }
+
+    #[test]
+    fn rejects_truncated_headers() {
+        let bytes = pe(0x8664, true, Some(&["AddonInit"]));
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(b"not a binary")),
+            "missing DOS header"
+        );
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes[..0x30])),
+            "file is truncated"
+        );
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes[..0x42])),
+            "missing PE signature"
+        );
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes[..0x50])),
+            "file is truncated"
+        );
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes[..0x150])),
+            "section table is truncated"
+        );
+        // The export name table is cut off part way through.
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes[..SECTION_OFFSET + 42])),
+            "export name table is truncated"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let mut bytes = pe(0x8664, true, None);
+        bytes[0x40] = b'X';
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes)),
+            "missing PE signature"
+        );
+
+        let mut bytes = pe(0x8664, true, None);
+        bytes[0x58] = 0;
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes)),
+            "unknown optional header magic"
+        );
+
+        // A PE offset pointing far outside of the file.
+        let mut bytes = pe(0x8664, true, None);
+        bytes[0x3C..0x40].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes)),
+            "missing PE signature"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_rvas() {
+        // The export directory RVA is outside of every section.
+        let mut bytes = pe(0x8664, true, Some(&["AddonInit"]));
+        let directory = 0x58 + 112;
+        bytes[directory..directory + 4].copy_from_slice(&0x9000u32.to_le_bytes());
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes)),
+            "export directory is outside of any section"
+        );
+
+        // The name pointer table RVA is outside of every section.
+        let mut bytes = pe(0x8664, true, Some(&["AddonInit"]));
+        bytes[SECTION_OFFSET + 32..SECTION_OFFSET + 36].copy_from_slice(&0x9000u32.to_le_bytes());
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes)),
+            "export directory is truncated"
+        );
+
+        // A name RVA is outside of every section.
+        let mut bytes = pe(0x8664, true, Some(&["AddonInit"]));
+        bytes[SECTION_OFFSET + 40..SECTION_OFFSET + 44].copy_from_slice(&0x9000u32.to_le_bytes());
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes)),
+            "export name table is truncated"
+        );
+
+        // More names are declared than the file contains.
+        let mut bytes = pe(0x8664, true, Some(&["AddonInit"]));
+        bytes[SECTION_OFFSET + 24..SECTION_OFFSET + 28].copy_from_slice(&1000u32.to_le_bytes());
+        assert_eq!(
+            invalid_reason(AddonBinary::parse(&bytes)),
+            "export name table is truncated"
+        );
+    }
+}
@@ -1,23 +1,44 @@
 // References:
 //  - https://github.com/Pictomancers/shaded-schemas/tree/main/collection
 
+use crate::{addon::TargetArchitecture, reshade::ReShadeVersionRequirement};
+use schemars::JsonSchema;
+use semver::Version;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default, Clone)]
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
 #[serde(rename_all(serialize = "PascalCase", deserialize = "PascalCase"))]
 pub struct CollectionManifest {
     pub manifest_version: u8,
+    /// The ReShade versions supported by the collection and every shaderpack in it.
     #[serde(rename(serialize = "ReShadeVersion", deserialize = "ReShadeVersion"))]
-    pub reshade_version: u8,
+    pub reshade_version: ReShadeVersionRequirement,
     pub name: String,
+    /// The semver version of this collection, e.g. `1.2.0`.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version: Option<Version>,
     pub description: Option<String>,
+    /// The game process architectures the addons of this collection were selected for.
+    #[serde(default)]
+    pub target_architecture: TargetArchitecture,
+    /// The configuration profile this collection was built with.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub profile: Option<String>,
     pub shader_packs: Vec<CollectionShaderPack>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default, Clone)]
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
 #[serde(rename_all(serialize = "PascalCase", deserialize = "PascalCase"))]
 pub struct CollectionShaderPack {
     pub name: String,
+    /// The semver version of this shaderpack, if it declared one.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub version: Option<Version>,
     pub authors: Vec<String>,
     pub description: String,
     pub images: Option<Vec<String>>,
@@ -25,6 +46,178 @@ pub struct CollectionShaderPack {
     pub texture_count: usize,
     pub preset_count: usize,
     pub addon_count: usize,
+    /// The SPDX license expression of this shaderpack, if it is known.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub license: Option<String>,
+    /// The paths of all files this shaderpack contributed to the collection archive.
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub files: Vec<String>,
+    /// Where the shaderpack was built from, if it was not a plain directory.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub provenance: Option<CollectionShaderPackProvenance>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub effect_metadata: Option<CollectionEffectMetadata>,
+}
+
+/// Where a shaderpack in a collection was built from.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Clone,
+)]
+#[serde(tag = "Type")]
+pub enum CollectionShaderPackProvenance {
+    /// A zip archive with the given file name.
+    #[serde(rename_all(serialize = "PascalCase", deserialize = "PascalCase"))]
+    Archive { file_name: String },
+    /// A git repository at the given revision, which resolved to the given full commit hash.
+    #[serde(rename_all(serialize = "PascalCase", deserialize = "PascalCase"))]
+    Git { revision: String, commit: String },
+}
+
+/// Metadata about the effects of a shaderpack extracted from its shader source code.
+///
+/// This section is optional and versioned separately from the manifest, see [`constants::EFFECT_METADATA_VERSION`].
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(rename_all(serialize = "PascalCase", deserialize = "PascalCase"))]
+pub struct CollectionEffectMetadata {
+    pub metadata_version: u8,
+    pub effects: Vec<CollectionEffect>,
+}
+
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(rename_all(serialize = "PascalCase", deserialize = "PascalCase"))]
+pub struct CollectionEffect {
+    /// The file name of the effect relative to the shader directory.
+    pub file_name: String,
+    pub techniques: Vec<CollectionEffectTechnique>,
+    pub uniforms: Vec<CollectionEffectUniform>,
+}
+
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(rename_all(serialize = "PascalCase", deserialize = "PascalCase"))]
+pub struct CollectionEffectTechnique {
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tooltip: Option<String>,
+}
+
+/// A user-configurable uniform of an effect. Numeric values are stored as they were written in the shader.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(rename_all(serialize = "PascalCase", deserialize = "PascalCase"))]
+pub struct CollectionEffectUniform {
+    pub name: String,
+    #[serde(rename(serialize = "Type", deserialize = "Type"))]
+    pub value_type: String,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub default_value: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub ui_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub label: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub tooltip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub min: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub max: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub step: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub items: Vec<String>,
+}
+
+/// Characters that are not allowed in file names on Windows, which collections are installed on.
+const RESERVED_FILE_NAME_CHARACTERS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+/// File names that are reserved for devices on Windows, regardless of their extension.
+const RESERVED_FILE_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// Turn a shaderpack name into a string that is safe to use as a single file name inside of a collection.
+///
+/// Reserved and control characters are replaced with `_`, trailing dots and spaces are removed and
+/// reserved device names are suffixed with `_`.
+pub fn sanitize_file_name(name: &str) -> String {
+    let mut file_name: String = name
+        .chars()
+        .map(|c| {
+            if c.is_control() || RESERVED_FILE_NAME_CHARACTERS.contains(&c) {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect::<String>()
+        .trim_end_matches(['.', ' '])
+        .to_owned();
+
+    let stem = file_name.split('.').next().unwrap_or_default();
+    if file_name.is_empty()
+        || RESERVED_FILE_NAMES
+            .iter()
+            .any(|reserved| reserved.eq_ignore_ascii_case(stem.trim_end()))
+    {
+        file_name.push('_');
+    }
+    file_name
+}
+
+/// Choose the name of the license file of a shaderpack inside of the collection license directory,
+/// e.g. `LICENSE-Bloom`.
+///
+/// Shaderpack names can sanitize to the same file name, e.g. `a/b` and `a_b`, and file names are compared
+/// case-insensitively as collections are installed on Windows. A name that is already taken is told
+/// apart by the shaderpack version, and then by a counter.
+///
+/// # Arguments
+/// * `taken`: The lowercase license file names chosen for other shaderpacks, which the chosen name is added to.
+pub fn license_file_name(
+    name: &str,
+    version: Option<&Version>,
+    taken: &mut BTreeSet<String>,
+) -> String {
+    let base = format!("LICENSE-{}", sanitize_file_name(name));
+    let versioned = version.map(|version| {
+        format!(
+            "LICENSE-{}",
+            sanitize_file_name(&format!("{}-{}", name, version))
+        )
+    });
+    let file_name = [Some(base.clone()), versioned]
+        .into_iter()
+        .flatten()
+        .chain((2..).map(|counter| format!("{}-{}", base, counter)))
+        .find(|file_name| !taken.contains(&file_name.to_lowercase()))
+        .unwrap();
+    taken.insert(file_name.to_lowercase());
+    file_name
+}
+
+/// The on-disk filename of a collection archive built with the given configuration profile.
+///
+/// Without a profile this is [`constants::COLLECTION_ARCHIVE_FILENAME`].
+pub fn collection_archive_filename(profile: Option<&str>) -> String {
+    match profile {
+        Some(profile) => format!(
+            "{}-{}.zip",
+            constants::COLLECTION_ARCHIVE_FILENAME.trim_end_matches(".zip"),
+            sanitize_file_name(profile)
+        ),
+        None => constants::COLLECTION_ARCHIVE_FILENAME.to_owned(),
+    }
 }
 
 /// Constant values relating to `Shaded`` collections.
@@ -53,4 +246,62 @@ pub mod constants {
 
     /// The name of the licenses directory relative to the collection folder root.
     pub const LICENSE_DIRECTORY_NAME: &str = "Licenses";
+
+    /// The on-disk filename of the credits listing every shaderpack in a collection, placed at the root of the archive.
+    pub const CREDITS_FILENAME: &str = "CREDITS.md";
+
+    /// The on-disk filename of the optional HTML version of [`CREDITS_FILENAME`].
+    pub const CREDITS_HTML_FILENAME: &str = "CREDITS.html";
+
+    /// The current version of the optional effect metadata section of a collection shaderpack.
+    pub const EFFECT_METADATA_VERSION: u8 = 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn file_names_are_sanitized() {
+        assert_eq!(sanitize_file_name("Bloom"), "Bloom");
+        assert_eq!(sanitize_file_name("Al/pha: <Beta>?"), "Al_pha_ _Beta__");
+        assert_eq!(sanitize_file_name("a\\b|c*\"d\""), "a_b_c__d_");
+        assert_eq!(sanitize_file_name("Tab\tNew\nLine"), "Tab_New_Line");
+        assert_eq!(sanitize_file_name("Trailing. . "), "Trailing");
+        assert_eq!(sanitize_file_name("Ünïcödé 日本"), "Ünïcödé 日本");
+    }
+
+    #[test]
+    fn reserved_file_names_are_suffixed() {
+        assert_eq!(sanitize_file_name(""), "_");
+        assert_eq!(sanitize_file_name("..."), "_");
+        assert_eq!(sanitize_file_name("CON"), "CON_");
+        assert_eq!(sanitize_file_name("nul.txt"), "nul.txt_");
+        assert_eq!(sanitize_file_name("Com1 .fx"), "Com1 .fx_");
+        assert_eq!(sanitize_file_name("CONSOLE"), "CONSOLE");
+        assert_eq!(sanitize_file_name("LPT10"), "LPT10");
+    }
+
+    #[test]
+    fn license_file_names_do_not_collide() {
+        let mut taken = BTreeSet::new();
+        assert_eq!(
+            license_file_name("a/b", Some(&Version::new(1, 0, 0)), &mut taken),
+            "LICENSE-a_b"
+        );
+        assert_eq!(
+            license_file_name("a_b", Some(&Version::new(2, 0, 0)), &mut taken),
+            "LICENSE-a_b-2.0.0"
+        );
+        assert_eq!(license_file_name("A_B", None, &mut taken), "LICENSE-A_B-2");
+        assert_eq!(
+            license_file_name("a:b", Some(&Version::new(2, 0, 0)), &mut taken),
+            "LICENSE-a_b-3"
+        );
+        assert_eq!(
+            license_file_name("Bloom", None, &mut taken),
+            "LICENSE-Bloom"
+        );
+        assert_eq!(taken.len(), 5);
+    }
 }
@@ -1,17 +1,663 @@
-use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use crate::{
+    addon::TargetArchitecture,
+    license::{is_non_commercial, license_id_matches, ShaderPackLicense},
+    reshade::ReShadeVersionRequirement,
+    shaderpack::{FileCategory, ShaderPackManifest},
+};
+use schemars::JsonSchema;
+use semver::Version;
+use serde::{
+    de::{self, value::MapAccessDeserializer},
+    Deserialize, Deserializer, Serialize,
+};
+use spdx::{Expression, LicenseItem};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default, Clone)]
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(deny_unknown_fields)]
 pub struct CollectionConfiguration {
+    /// A configuration file this configuration is merged on top of, relative to this configuration.
+    ///
+    /// Paths inside of the extended configuration are resolved relative to the configuration being built.
+    #[serde(default)]
+    pub extends: Option<PathBuf>,
+    /// Values that can be interpolated into strings of the configuration with `${name}`.
+    #[serde(default)]
+    pub variables: BTreeMap<String, String>,
     pub configuration_version: u8,
-    pub reshade_version: u8,
+    /// The ReShade versions the collection is built for. Shaderpacks that support none of them are skipped.
+    pub reshade_version: ReShadeVersionRequirement,
     pub name: String,
+    /// The semver version of the collection, e.g. `1.2.0`.
+    #[serde(default)]
+    pub version: Option<Version>,
     pub description: Option<String>,
-    pub search_directory: CollectionConfigurationSearchDirectory,
+    /// A single directory to search for shaderpacks in, kept for compatibility with older configurations.
+    #[serde(default)]
+    pub search_directory: Option<CollectionConfigurationSearchDirectory>,
+    /// Directories to search for shaderpacks in.
+    #[serde(default)]
+    pub search_directories: Vec<CollectionConfigurationSearchDirectory>,
+    /// Shaderpacks that are always included, regardless of [`CollectionConfiguration::filters`].
+    #[serde(default)]
+    pub shaderpacks: Vec<CollectionConfigurationShaderPack>,
+    #[serde(default)]
+    pub filters: CollectionConfigurationFilters,
+    #[serde(default)]
+    pub name_clash_policy: NameClashPolicy,
+    /// The game process architectures to include addons for.
+    #[serde(default)]
+    pub target_architecture: TargetArchitecture,
+    #[serde(default)]
+    pub license_policy: CollectionConfigurationLicensePolicy,
+    #[serde(default)]
+    pub credits: CollectionConfigurationCredits,
+    /// Categories of files to leave out of every shaderpack in the collection.
+    #[serde(default)]
+    pub exclude_categories: Vec<FileCategory>,
+    /// The largest width or height in pixels of textures to include in the collection.
+    pub max_texture_size: Option<u32>,
+    /// Changes applied to individual shaderpacks when they are included in the collection.
+    #[serde(default)]
+    pub overrides: Vec<CollectionConfigurationOverride>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default, Clone)]
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(deny_unknown_fields)]
 pub struct CollectionConfigurationSearchDirectory {
     pub path: PathBuf,
     pub max_depth: usize,
 }
+
+/// A shaderpack source listed explicitly in the configuration.
+///
+/// Tables are told apart by whether they have an `archive` or a `git` key, rather than by trying each
+/// variant in turn, so that errors inside of a table point at the key that caused them.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, JsonSchema, Clone)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum CollectionConfigurationShaderPack {
+    /// A path to a shaderpack directory, or to a zip archive if it has a `.zip` extension.
+    Path(PathBuf),
+    /// A zip archive containing a shaderpack.
+    Archive {
+        archive: PathBuf,
+        /// The directory inside of the archive containing the manifest. If not set, the shallowest
+        /// directory with a manifest is used.
+        root: Option<PathBuf>,
+    },
+    /// A local git repository containing a shaderpack, read at a pinned revision without touching its working tree.
+    Git {
+        git: PathBuf,
+        /// The tag, branch or commit to read the shaderpack from.
+        revision: String,
+        /// The directory inside of the repository containing the manifest. If not set, the shallowest
+        /// directory with a manifest is used.
+        root: Option<PathBuf>,
+    },
+}
+
+/// Every key a [`CollectionConfigurationShaderPack`] table may have, checked after parsing.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct CollectionConfigurationShaderPackTable {
+    archive: Option<PathBuf>,
+    git: Option<PathBuf>,
+    revision: Option<String>,
+    root: Option<PathBuf>,
+}
+
+impl<'de> Deserialize<'de> for CollectionConfigurationShaderPack {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ShaderPackVisitor;
+
+        impl<'de> de::Visitor<'de> for ShaderPackVisitor {
+            type Value = CollectionConfigurationShaderPack;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a shaderpack path or a table with an `archive` or `git` key")
+            }
+
+            fn visit_str<E: de::Error>(self, path: &str) -> Result<Self::Value, E> {
+                Ok(CollectionConfigurationShaderPack::Path(path.into()))
+            }
+
+            fn visit_map<A: de::MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let table = CollectionConfigurationShaderPackTable::deserialize(
+                    MapAccessDeserializer::new(map),
+                )?;
+                match (table.archive, table.git, table.revision) {
+                    (Some(archive), None, None) => Ok(CollectionConfigurationShaderPack::Archive {
+                        archive,
+                        root: table.root,
+                    }),
+                    (Some(_), None, Some(_)) => Err(de::Error::custom(
+                        "`revision` can only be set for `git` shaderpacks",
+                    )),
+                    (None, Some(git), Some(revision)) => {
+                        Ok(CollectionConfigurationShaderPack::Git {
+                            git,
+                            revision,
+                            root: table.root,
+                        })
+                    }
+                    (None, Some(_), None) => Err(de::Error::missing_field("revision")),
+                    (Some(_), Some(_), _) => Err(de::Error::custom(
+                        "a shaderpack can't have both an `archive` and a `git` key",
+                    )),
+                    (None, None, _) => Err(de::Error::custom(
+                        "a shaderpack table must have an `archive` or a `git` key",
+                    )),
+                }
+            }
+        }
+
+        deserializer.deserialize_any(ShaderPackVisitor)
+    }
+}
+
+/// Filters for which shaderpacks found inside of the search directories are included in a collection.
+///
+/// Paths are matched relative to the directory of the configuration file using `/` as the separator.
+/// A shaderpack is included when it matches any include pattern (or there are none) and no exclude pattern.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectionConfigurationFilters {
+    /// Glob patterns for shaderpack directory paths to include, e.g. `packs/**`.
+    pub include: Vec<String>,
+    /// Glob patterns for shaderpack directory paths to exclude.
+    pub exclude: Vec<String>,
+    /// Glob patterns for shaderpack manifest names to include.
+    pub include_names: Vec<String>,
+    /// Glob patterns for shaderpack manifest names to exclude.
+    pub exclude_names: Vec<String>,
+}
+
+impl CollectionConfiguration {
+    /// All directories to search for shaderpacks in, including the legacy [`CollectionConfiguration::search_directory`].
+    pub fn all_search_directories(
+        &self,
+    ) -> impl Iterator<Item = &CollectionConfigurationSearchDirectory> {
+        self.search_directory.iter().chain(&self.search_directories)
+    }
+}
+
+/// How to handle techniques or effect files with the same name being provided by multiple shaderpacks.
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+    Default,
+    Clone,
+    Copy,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum NameClashPolicy {
+    /// Fail the build when a clash is found.
+    Fail,
+    /// Report clashes but continue the build.
+    #[default]
+    Warn,
+    /// Do not check for clashes.
+    Allow,
+}
+
+/// Changes to a shaderpack that are applied when it is included in a collection, without editing its manifest.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(deny_unknown_fields)]
+pub struct CollectionConfigurationOverride {
+    /// The manifest name of the shaderpack to apply this override to.
+    pub shaderpack: String,
+    /// Categories of files to leave out of the collection entirely.
+    #[serde(default)]
+    pub exclude_categories: Vec<FileCategory>,
+    /// Source paths of individual file declarations to leave out of the collection.
+    #[serde(default)]
+    pub exclude_files: Vec<PathBuf>,
+    /// Output path prefixes to replace, applied in order.
+    #[serde(default)]
+    pub output_prefixes: Vec<CollectionConfigurationOutputPrefix>,
+    /// Replaces the shaderpack name shown in the collection.
+    pub name: Option<String>,
+    /// Replaces the shaderpack description shown in the collection.
+    pub description: Option<String>,
+    /// Replaces the shaderpack images shown in the collection.
+    pub images: Option<Vec<String>>,
+}
+
+/// Replaces the leading components of output paths, e.g. to place the shaders of a shaderpack in a sub-directory.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(deny_unknown_fields)]
+pub struct CollectionConfigurationOutputPrefix {
+    /// The prefix to replace. An empty prefix matches every output path.
+    #[serde(default)]
+    pub from: PathBuf,
+    pub to: PathBuf,
+    /// The file categories to remap, or every category if empty.
+    #[serde(default)]
+    pub categories: Vec<FileCategory>,
+}
+
+impl CollectionConfigurationOverride {
+    /// Apply this override to a shaderpack manifest.
+    pub fn apply(&self, manifest: &mut ShaderPackManifest) {
+        for category in FileCategory::ALL {
+            let files = manifest.files_mut(category);
+            if self.exclude_categories.contains(&category) {
+                *files = None;
+                continue;
+            }
+
+            let Some(files) = files else {
+                continue;
+            };
+            files.retain(|file| {
+                !self
+                    .exclude_files
+                    .iter()
+                    .any(|excluded| same_path(excluded, &file.source))
+            });
+            for prefix in self.output_prefixes.iter().filter(|prefix| {
+                prefix.categories.is_empty() || prefix.categories.contains(&category)
+            }) {
+                for file in files.iter_mut() {
+                    if let Ok(remainder) = file.output.strip_prefix(&prefix.from) {
+                        file.output = prefix.to.join(remainder);
+                    }
+                }
+            }
+        }
+
+        if let Some(name) = &self.name {
+            manifest.name = name.clone();
+        }
+        if let Some(description) = &self.description {
+            manifest.description = description.clone();
+        }
+        if let Some(images) = &self.images {
+            manifest.images = Some(images.clone());
+        }
+    }
+}
+
+/// Compare two relative paths component by component, so `./a/b` and `a\b` are treated the same.
+fn same_path(a: &Path, b: &Path) -> bool {
+    let components = |path: &Path| {
+        path.to_string_lossy()
+            .split(['/', '\\'])
+            .filter(|component| !component.is_empty() && *component != ".")
+            .map(str::to_owned)
+            .collect::<Vec<_>>()
+    };
+    components(a) == components(b)
+}
+
+/// Options for the credits file generated at the root of a collection archive.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectionConfigurationCredits {
+    /// Whether to also generate an HTML version of the credits.
+    pub html: bool,
+}
+
+/// Rules for which shaderpack licenses may be redistributed as part of a collection.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(default, deny_unknown_fields)]
+pub struct CollectionConfigurationLicensePolicy {
+    /// SPDX license identifiers or expressions that are always allowed, e.g. `LicenseRef-MyTerms`.
+    ///
+    /// GNU licenses listed without a suffix, e.g. `GPL-3.0`, include their `-only` and `-or-later` variants.
+    pub allow: Vec<String>,
+    /// SPDX license identifiers that are never allowed.
+    ///
+    /// GNU licenses listed without a suffix, e.g. `GPL-3.0`, include their `-only` and `-or-later` variants.
+    pub deny: Vec<String>,
+    /// Whether licenses that forbid commercial use, e.g. `CC-BY-NC-4.0`, are allowed.
+    pub allow_non_commercial: bool,
+    /// Whether shaderpacks without a recognised license are allowed.
+    pub allow_unknown: bool,
+    /// Whether to fail the collection build when a shaderpack is not allowed by this policy.
+    pub fail_on_violation: bool,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum LicensePolicyViolation {
+    #[error("license is unknown")]
+    UnknownLicense,
+
+    #[error("license '{0}' is not a valid SPDX expression")]
+    InvalidExpression(String),
+
+    #[error("license file contains terms forbidding redistribution")]
+    RedistributionForbidden,
+
+    #[error("license '{0}' is denied by the license policy")]
+    Denied(String),
+
+    #[error("license '{0}' does not allow commercial use")]
+    NonCommercial(String),
+}
+
+impl CollectionConfigurationLicensePolicy {
+    /// Check whether a shaderpack license is allowed by this policy.
+    ///
+    /// For expressions containing `OR`, the license is allowed if any of the alternatives are.
+    pub fn check(&self, license: &ShaderPackLicense) -> Result<(), LicensePolicyViolation> {
+        let Some(expression) = &license.expression else {
+            if license.forbids_redistribution {
+                return Err(LicensePolicyViolation::RedistributionForbidden);
+            }
+            if self.allow_unknown {
+                return Ok(());
+            }
+            return Err(LicensePolicyViolation::UnknownLicense);
+        };
+        if self.allow.contains(expression) {
+            return Ok(());
+        }
+        if license.forbids_redistribution {
+            return Err(LicensePolicyViolation::RedistributionForbidden);
+        }
+
+        let parsed = Expression::parse(expression)
+            .map_err(|_| LicensePolicyViolation::InvalidExpression(expression.clone()))?;
+        let mut violation = None;
+        let allowed = parsed.evaluate(|requirement| {
+            let name = match &requirement.license {
+                // GNU licenses are parsed into their deprecated identifier without a suffix.
+                LicenseItem::Spdx { id, or_later } if id.is_gnu() => {
+                    format!(
+                        "{}-{}",
+                        id.name,
+                        if *or_later { "or-later" } else { "only" }
+                    )
+                }
+                LicenseItem::Spdx { id, .. } => id.name.to_owned(),
+                LicenseItem::Other { lic_ref, .. } => format!("LicenseRef-{lic_ref}"),
+            };
+            let listed =
+                |list: &[String]| list.iter().any(|listed| license_id_matches(&name, listed));
+            let result = if listed(&self.allow) {
+                Ok(())
+            } else if listed(&self.deny) {
+                Err(LicensePolicyViolation::Denied(name))
+            } else if requirement.license.id().is_none() && !self.allow_unknown {
+                Err(LicensePolicyViolation::UnknownLicense)
+            } else if is_non_commercial(&name) && !self.allow_non_commercial {
+                Err(LicensePolicyViolation::NonCommercial(name))
+            } else {
+                Ok(())
+            };
+            match result {
+                Ok(()) => true,
+                Err(err) => {
+                    violation.get_or_insert(err);
+                    false
+                }
+            }
+        });
+
+        match (allowed, violation) {
+            (false, Some(violation)) => Err(violation),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shaderpack::FileDeclaration;
+
+    fn files(files: &[(&str, &str)]) -> Option<Vec<FileDeclaration>> {
+        Some(
+            files
+                .iter()
+                .map(|(source, output)| FileDeclaration {
+                    source: source.into(),
+                    output: output.into(),
+                })
+                .collect(),
+        )
+    }
+
+    fn manifest() -> ShaderPackManifest {
+        ShaderPackManifest {
+            name: "Bloom".to_owned(),
+            version: Some(Version::new(1, 2, 0)),
+            description: "Bloom effects".to_owned(),
+            license: Some("MIT".to_owned()),
+            shaders: files(&[
+                ("Shaders/Bloom.fx", "Bloom.fx"),
+                ("Shaders/Common.fxh", "Common.fxh"),
+            ]),
+            textures: files(&[("Textures/Dirt.png", "Dirt.png")]),
+            presets: files(&[("Presets/Bloom.ini", "Bloom.ini")]),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn overrides_replace_metadata_and_files() {
+        let mut manifest = manifest();
+        CollectionConfigurationOverride {
+            shaderpack: "Bloom".to_owned(),
+            exclude_categories: vec![FileCategory::Presets],
+            exclude_files: vec!["./Shaders\\Common.fxh".into(), "Shaders/Missing.fx".into()],
+            output_prefixes: vec![
+                CollectionConfigurationOutputPrefix {
+                    from: "".into(),
+                    to: "Bloom".into(),
+                    categories: vec![FileCategory::Shaders],
+                },
+                CollectionConfigurationOutputPrefix {
+                    from: "Bloom".into(),
+                    to: "Effects/Bloom".into(),
+                    categories: vec![],
+                },
+            ],
+            name: Some("Better Bloom".to_owned()),
+            description: Some("Overridden".to_owned()),
+            images: Some(vec!["https://example.com/bloom.png".to_owned()]),
+        }
+        .apply(&mut manifest);
+
+        assert_eq!(
+            manifest,
+            ShaderPackManifest {
+                name: "Better Bloom".to_owned(),
+                description: "Overridden".to_owned(),
+                images: Some(vec!["https://example.com/bloom.png".to_owned()]),
+                shaders: files(&[("Shaders/Bloom.fx", "Effects/Bloom/Bloom.fx")]),
+                textures: files(&[("Textures/Dirt.png", "Dirt.png")]),
+                presets: None,
+                ..self::manifest()
+            }
+        );
+    }
+
+    #[test]
+    fn overrides_without_changes_keep_the_manifest() {
+        let mut manifest = manifest();
+        CollectionConfigurationOverride {
+            shaderpack: "Bloom".to_owned(),
+            exclude_files: vec!["Other/Bloom.fx".into()],
+            output_prefixes: vec![CollectionConfigurationOutputPrefix {
+                from: "Missing".into(),
+                to: "Elsewhere".into(),
+                categories: vec![],
+            }],
+            ..Default::default()
+        }
+        .apply(&mut manifest);
+        assert_eq!(manifest, self::manifest());
+    }
+
+    #[test]
+    fn compares_paths_by_component() {
+        assert!(same_path(Path::new("a/b"), Path::new("a/b")));
+        assert!(same_path(Path::new("./a/b"), Path::new("a\\b")));
+        assert!(same_path(Path::new("a//b/"), Path::new("a/./b")));
+        assert!(!same_path(Path::new("a/b"), Path::new("a/c")));
+        assert!(!same_path(Path::new("a/b"), Path::new("b")));
+        // Paths are case-sensitive, like in shaderpack archives.
+        assert!(!same_path(Path::new("a/B"), Path::new("a/b")));
+    }
+
+    fn license(expression: Option<&str>) -> ShaderPackLicense {
+        ShaderPackLicense {
+            expression: expression.map(str::to_owned),
+            detected: false,
+            forbids_redistribution: false,
+        }
+    }
+
+    fn policy(allow: &[&str], deny: &[&str]) -> CollectionConfigurationLicensePolicy {
+        CollectionConfigurationLicensePolicy {
+            allow: allow.iter().map(|id| id.to_string()).collect(),
+            deny: deny.iter().map(|id| id.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn license_policy_allows_and_denies_listed_licenses() {
+        let policy = policy(&["LicenseRef-MyTerms", "Custom Terms"], &["MIT", "GPL-3.0"]);
+
+        assert_eq!(policy.check(&license(Some("Apache-2.0"))), Ok(()));
+        assert_eq!(
+            policy.check(&license(Some("MIT"))),
+            Err(LicensePolicyViolation::Denied("MIT".to_owned()))
+        );
+        assert_eq!(policy.check(&license(Some("LicenseRef-MyTerms"))), Ok(()));
+        // Whole expressions may be allowed even if they aren't valid SPDX.
+        assert_eq!(policy.check(&license(Some("Custom Terms"))), Ok(()));
+        assert_eq!(
+            policy.check(&license(Some("Not SPDX"))),
+            Err(LicensePolicyViolation::InvalidExpression(
+                "Not SPDX".to_owned()
+            ))
+        );
+    }
+
+    #[test]
+    fn license_policy_matches_gnu_license_variants() {
+        // Copyleft licenses are allowed unless the policy denies them.
+        assert_eq!(
+            policy(&[], &[]).check(&license(Some("GPL-3.0-only"))),
+            Ok(())
+        );
+
+        let deny_family = policy(&[], &["GPL-3.0"]);
+        for id in ["GPL-3.0-only", "GPL-3.0-or-later"] {
+            assert_eq!(
+                deny_family.check(&license(Some(id))),
+                Err(LicensePolicyViolation::Denied(id.to_owned()))
+            );
+        }
+        assert_eq!(deny_family.check(&license(Some("LGPL-3.0-only"))), Ok(()));
+
+        let deny_variant = policy(&[], &["GPL-3.0-only"]);
+        assert!(deny_variant.check(&license(Some("GPL-3.0-only"))).is_err());
+        assert_eq!(
+            deny_variant.check(&license(Some("GPL-3.0-or-later"))),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn license_policy_checks_non_commercial_licenses() {
+        let mut policy = policy(&[], &[]);
+        assert_eq!(
+            policy.check(&license(Some("CC-BY-NC-SA-4.0"))),
+            Err(LicensePolicyViolation::NonCommercial(
+                "CC-BY-NC-SA-4.0".to_owned()
+            ))
+        );
+        assert!(policy
+            .check(&license(Some("PolyForm-Noncommercial-1.0.0")))
+            .is_err());
+        assert_eq!(policy.check(&license(Some("CC-BY-SA-4.0"))), Ok(()));
+
+        policy.allow_non_commercial = true;
+        assert_eq!(policy.check(&license(Some("CC-BY-NC-SA-4.0"))), Ok(()));
+    }
+
+    #[test]
+    fn license_policy_checks_unknown_licenses() {
+        let mut policy = policy(&[], &[]);
+        assert_eq!(
+            policy.check(&license(None)),
+            Err(LicensePolicyViolation::UnknownLicense)
+        );
+        assert_eq!(
+            policy.check(&license(Some("LicenseRef-Custom"))),
+            Err(LicensePolicyViolation::UnknownLicense)
+        );
+        let forbidden = ShaderPackLicense {
+            forbids_redistribution: true,
+            ..license(Some("MIT"))
+        };
+        assert_eq!(
+            policy.check(&forbidden),
+            Err(LicensePolicyViolation::RedistributionForbidden)
+        );
+
+        policy.allow_unknown = true;
+        assert_eq!(policy.check(&license(None)), Ok(()));
+        assert_eq!(policy.check(&license(Some("LicenseRef-Custom"))), Ok(()));
+        assert_eq!(
+            policy.check(&ShaderPackLicense {
+                forbids_redistribution: true,
+                ..license(None)
+            }),
+            Err(LicensePolicyViolation::RedistributionForbidden)
+        );
+
+        // Allowing the license explicitly overrides terms that appear to forbid redistribution.
+        policy.allow = vec!["MIT".to_owned()];
+        assert_eq!(policy.check(&forbidden), Ok(()));
+    }
+
+    #[test]
+    fn license_policy_checks_compound_expressions() {
+        let policy = policy(&[], &["MIT"]);
+        assert_eq!(policy.check(&license(Some("MIT OR Apache-2.0"))), Ok(()));
+        assert_eq!(
+            policy.check(&license(Some("MIT AND Apache-2.0"))),
+            Err(LicensePolicyViolation::Denied("MIT".to_owned()))
+        );
+        assert_eq!(
+            policy.check(&license(Some("MIT OR CC-BY-NC-4.0"))),
+            Err(LicensePolicyViolation::Denied("MIT".to_owned()))
+        );
+        assert_eq!(
+            policy.check(&license(Some("(MIT OR CC-BY-NC-4.0) OR Zlib"))),
+            Ok(())
+        );
+    }
+}
@@ -0,0 +1,331 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    path::PathBuf,
+};
+
+/// The exact inputs of a collection, used to check that a build uses the same shaderpacks and files
+/// as when the lock was written.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default, Clone)]
+pub struct CollectionLock {
+    pub lock_version: u8,
+    #[serde(default, rename = "shaderpack")]
+    pub shaderpacks: Vec<CollectionLockShaderPack>,
+}
+
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Clone)]
+pub struct CollectionLockShaderPack {
+    pub name: String,
+    pub source: CollectionLockSource,
+    /// The profiles that include the shaderpack, where the configuration without a profile is written as
+    /// [`constants::BASE_PROFILE_NAME`].
+    pub profiles: BTreeSet<String>,
+    /// The SHA-256 hash of the shaderpack manifest file.
+    pub manifest_hash: String,
+    /// The SHA-256 hashes of every file declared by the shaderpack, keyed by their source path.
+    pub files: BTreeMap<String, String>,
+}
+
+/// Where a locked shaderpack is read from. Paths are relative to the configuration file.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Clone)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CollectionLockSource {
+    Directory {
+        path: PathBuf,
+    },
+    Archive {
+        path: PathBuf,
+        root: String,
+    },
+    Git {
+        repository: PathBuf,
+        revision: String,
+        commit: String,
+        root: String,
+    },
+}
+
+/// A difference between two collection locks.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum CollectionLockChange {
+    Added(String),
+    Removed(String),
+    /// A shaderpack with changed files, listing the source paths of the changed files.
+    Changed(String, Vec<String>),
+}
+
+impl fmt::Display for CollectionLockChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CollectionLockChange::Added(name) => write!(f, "{} was added", name),
+            CollectionLockChange::Removed(name) => write!(f, "{} was removed", name),
+            CollectionLockChange::Changed(name, files) if files.is_empty() => {
+                write!(f, "{} has changed", name)
+            }
+            CollectionLockChange::Changed(name, files) => {
+                write!(f, "{} has changed files: {}", name, files.join(", "))
+            }
+        }
+    }
+}
+
+impl CollectionLock {
+    /// Replace the shaderpacks locked for some profiles with the shaderpacks of a lock of only those
+    /// profiles, keeping what is locked for every other profile.
+    ///
+    /// Shaderpacks that are also included by other profiles keep the hashes of files only those profiles
+    /// include, while the hashes of the files of the relocked profiles are replaced.
+    ///
+    /// # Arguments
+    /// * `relocked`: A lock of only the given profiles.
+    pub fn merge(&self, relocked: &CollectionLock, profiles: &BTreeSet<String>) -> CollectionLock {
+        let mut shaderpacks: Vec<CollectionLockShaderPack> = self
+            .shaderpacks
+            .iter()
+            .filter_map(|shaderpack| {
+                let mut shaderpack = shaderpack.clone();
+                shaderpack
+                    .profiles
+                    .retain(|profile| !profiles.contains(profile));
+                (!shaderpack.profiles.is_empty()).then_some(shaderpack)
+            })
+            .collect();
+
+        for new in &relocked.shaderpacks {
+            match shaderpacks
+                .iter_mut()
+                .find(|old| old.source_key() == new.source_key())
+            {
+                Some(old) => {
+                    old.name = new.name.clone();
+                    old.source = new.source.clone();
+                    old.profiles.extend(new.profiles.iter().cloned());
+                    old.manifest_hash = new.manifest_hash.clone();
+                    old.files.extend(new.files.clone());
+                }
+                None => shaderpacks.push(new.clone()),
+            }
+        }
+
+        CollectionLock {
+            lock_version: self.lock_version,
+            shaderpacks,
+        }
+    }
+
+    /// Compare this lock against a newer one, matching shaderpacks by their source.
+    pub fn changes(&self, newer: &CollectionLock) -> Vec<CollectionLockChange> {
+        let mut changes = vec![];
+        for old in &self.shaderpacks {
+            match newer
+                .shaderpacks
+                .iter()
+                .find(|new| new.source_key() == old.source_key())
+            {
+                None => changes.push(CollectionLockChange::Removed(old.name.clone())),
+                Some(new) if new != old => {
+                    let changed_files = old
+                        .files
+                        .keys()
+                        .chain(new.files.keys())
+                        .filter(|file| old.files.get(*file) != new.files.get(*file))
+                        .cloned()
+                        .collect::<BTreeSet<_>>();
+                    changes.push(CollectionLockChange::Changed(
+                        new.name.clone(),
+                        changed_files.into_iter().collect(),
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+        for new in &newer.shaderpacks {
+            if !self
+                .shaderpacks
+                .iter()
+                .any(|old| old.source_key() == new.source_key())
+            {
+                changes.push(CollectionLockChange::Added(new.name.clone()));
+            }
+        }
+        changes
+    }
+}
+
+impl CollectionLockShaderPack {
+    /// The part of the source that identifies a shaderpack, leaving out the resolved git commit.
+    fn source_key(&self) -> CollectionLockSource {
+        match &self.source {
+            CollectionLockSource::Git {
+                repository,
+                revision,
+                root,
+                ..
+            } => CollectionLockSource::Git {
+                repository: repository.clone(),
+                revision: revision.clone(),
+                commit: String::new(),
+                root: root.clone(),
+            },
+            source => source.clone(),
+        }
+    }
+}
+
+/// Constant values relating to collection locks.
+pub mod constants {
+    // WARNING: Changing any value here iss considered a breaking change and WILL break other tools.
+    // please take considerable care when if/when doing so.
+
+    /// The on-disk filename of a collection lock, placed next to the collection configuration file.
+    pub const COLLECTION_LOCK_FILENAME: &str = "shaded-collection.lock";
+
+    /// The profile name the configuration without a profile is locked under.
+    pub const BASE_PROFILE_NAME: &str = "";
+
+    /// The current version of the collection lock format.
+    pub const COLLECTION_LOCK_VERSION: u8 = 1;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shaderpack(
+        name: &str,
+        source: CollectionLockSource,
+        profiles: &[&str],
+        files: &[(&str, &str)],
+    ) -> CollectionLockShaderPack {
+        CollectionLockShaderPack {
+            name: name.to_owned(),
+            source,
+            profiles: profiles.iter().map(|profile| profile.to_string()).collect(),
+            manifest_hash: "manifest".to_owned(),
+            files: files
+                .iter()
+                .map(|(path, hash)| (path.to_string(), hash.to_string()))
+                .collect(),
+        }
+    }
+
+    fn directory(path: &str) -> CollectionLockSource {
+        CollectionLockSource::Directory { path: path.into() }
+    }
+
+    fn git(revision: &str, commit: &str) -> CollectionLockSource {
+        CollectionLockSource::Git {
+            repository: "repository".into(),
+            revision: revision.to_owned(),
+            commit: commit.to_owned(),
+            root: String::new(),
+        }
+    }
+
+    fn lock(shaderpacks: Vec<CollectionLockShaderPack>) -> CollectionLock {
+        CollectionLock {
+            lock_version: constants::COLLECTION_LOCK_VERSION,
+            shaderpacks,
+        }
+    }
+
+    fn profiles(profiles: &[&str]) -> BTreeSet<String> {
+        profiles.iter().map(|profile| profile.to_string()).collect()
+    }
+
+    #[test]
+    fn git_sources_are_matched_without_their_commit() {
+        let old = shaderpack("Bloom", git("v1", "aaaa"), &[""], &[]);
+        assert_eq!(
+            old.source_key(),
+            shaderpack("Bloom", git("v1", "bbbb"), &[""], &[]).source_key()
+        );
+        assert_ne!(
+            old.source_key(),
+            shaderpack("Bloom", git("v2", "aaaa"), &[""], &[]).source_key()
+        );
+        assert_eq!(
+            old.source_key(),
+            shaderpack("Bloom", git("v1", "aaaa"), &[""], &[]).source_key()
+        );
+    }
+
+    #[test]
+    fn changes_are_listed_by_shaderpack() {
+        let old = lock(vec![
+            shaderpack(
+                "Bloom",
+                directory("bloom"),
+                &[""],
+                &[("a.fx", "1"), ("b.fx", "2")],
+            ),
+            shaderpack("Removed", directory("removed"), &[""], &[]),
+            shaderpack("Same", git("v1", "aaaa"), &[""], &[]),
+        ]);
+        let new = lock(vec![
+            shaderpack(
+                "Bloom",
+                directory("bloom"),
+                &[""],
+                &[("a.fx", "1"), ("b.fx", "3"), ("c.fx", "4")],
+            ),
+            shaderpack("Same", git("v1", "aaaa"), &[""], &[]),
+            shaderpack("Added", directory("added"), &[""], &[]),
+        ]);
+
+        assert_eq!(
+            old.changes(&new),
+            [
+                CollectionLockChange::Changed(
+                    "Bloom".to_owned(),
+                    vec!["b.fx".to_owned(), "c.fx".to_owned()]
+                ),
+                CollectionLockChange::Removed("Removed".to_owned()),
+                CollectionLockChange::Added("Added".to_owned()),
+            ]
+        );
+        assert!(new.changes(&new).is_empty());
+
+        // A new commit for the same revision is a change of the shaderpack, not a different shaderpack.
+        let moved = lock(vec![shaderpack("Same", git("v1", "bbbb"), &[""], &[])]);
+        assert_eq!(
+            lock(vec![shaderpack("Same", git("v1", "aaaa"), &[""], &[])]).changes(&moved),
+            [CollectionLockChange::Changed("Same".to_owned(), vec![])]
+        );
+    }
+
+    #[test]
+    fn merging_keeps_the_shaderpacks_of_other_profiles() {
+        let existing = lock(vec![
+            shaderpack(
+                "Bloom",
+                directory("bloom"),
+                &["full", "lite"],
+                &[("a.fx", "1"), ("big.png", "2")],
+            ),
+            shaderpack("Lite only", directory("lite"), &["lite"], &[]),
+            shaderpack("Full only", directory("full"), &["full"], &[]),
+        ]);
+        let relocked = lock(vec![
+            shaderpack("Bloom", directory("bloom"), &["lite"], &[("a.fx", "3")]),
+            shaderpack("New", directory("new"), &["lite"], &[]),
+        ]);
+
+        let merged = existing.merge(&relocked, &profiles(&["lite"]));
+        assert_eq!(
+            merged,
+            lock(vec![
+                shaderpack(
+                    "Bloom",
+                    directory("bloom"),
+                    &["full", "lite"],
+                    &[("a.fx", "3"), ("big.png", "2")]
+                ),
+                shaderpack("Full only", directory("full"), &["full"], &[]),
+                shaderpack("New", directory("new"), &["lite"], &[]),
+            ])
+        );
+        assert_eq!(merged.merge(&relocked, &profiles(&["lite"])), merged);
+    }
+}
@@ -0,0 +1,368 @@
+use crate::shaderpack::ShaderPackManifest;
+use semver::VersionReq;
+use std::collections::{BTreeMap, BTreeSet};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum DependencyErrorKind {
+    #[error("'{0}' depends on '{1}', which could not be found")]
+    Missing(String, String),
+
+    #[error("'{dependent}' requires '{dependency}' {requirement}, but {found}")]
+    Incompatible {
+        dependent: String,
+        dependency: String,
+        requirement: String,
+        found: String,
+    },
+
+    #[error(
+        "the requirements on '{dependency}' conflict, {selected} was selected for the first of them: {}",
+        .requirements.join(", ")
+    )]
+    Conflicting {
+        dependency: String,
+        selected: String,
+        /// Every requirement on the dependency, e.g. `'Bloom' requires ^1.2`.
+        requirements: Vec<String>,
+    },
+
+    #[error("'{0}' has an invalid version requirement '{2}' for '{1}'")]
+    InvalidRequirement(String, String, String),
+
+    #[error("shaderpacks depend on each other in a cycle: {}", .0.join(" -> "))]
+    Circular(Vec<String>),
+}
+
+/// Add the dependencies of the included shaderpacks from the available ones and order the result so
+/// that every shaderpack comes after its dependencies.
+///
+/// Included shaderpacks are always used to satisfy a dependency when their name matches. Otherwise the
+/// available shaderpack with the highest version matching the requirement is added.
+///
+/// Resolution is first-match only and does not backtrack: a dependency is resolved once, for the first
+/// requirement on it that is found, and later requirements must accept that version. If one does not,
+/// resolution fails listing every requirement on the dependency instead of trying other versions.
+///
+/// # Arguments
+/// * `included`: The shaderpacks to resolve the dependencies of, alongside any data about them.
+/// * `available`: The shaderpacks that can be added to satisfy a dependency.
+pub fn resolve_dependencies<T>(
+    included: Vec<(T, ShaderPackManifest)>,
+    available: Vec<(T, ShaderPackManifest)>,
+) -> Result<Vec<(T, ShaderPackManifest)>, DependencyErrorKind> {
+    let included_count = included.len();
+    let pool: Vec<_> = included.into_iter().chain(available).collect();
+    let mut selected: Vec<usize> = (0..included_count).collect();
+    // The requirements on each added shaderpack, as the name of the dependent and the requirement.
+    let mut requirements: BTreeMap<usize, Vec<(String, Option<String>)>> = BTreeMap::new();
+
+    // Select a shaderpack for every dependency, adding newly selected shaderpacks to the queue.
+    let mut edges: Vec<(usize, usize)> = vec![];
+    let mut queue = selected.clone();
+    while let Some(index) = queue.pop() {
+        let dependent = &pool[index].1;
+        for dependency in dependent.dependencies.iter().flatten() {
+            let requirement = match &dependency.version {
+                Some(version) => Some(VersionReq::parse(version).map_err(|_| {
+                    DependencyErrorKind::InvalidRequirement(
+                        dependent.name.clone(),
+                        dependency.name.clone(),
+                        version.clone(),
+                    )
+                })?),
+                None => None,
+            };
+            let satisfies = |manifest: &ShaderPackManifest| match &requirement {
+                Some(requirement) => manifest
+                    .version
+                    .as_ref()
+                    .is_some_and(|version| requirement.matches(version)),
+                None => true,
+            };
+            let incompatible = |found: String| DependencyErrorKind::Incompatible {
+                dependent: dependent.name.clone(),
+                dependency: dependency.name.clone(),
+                requirement: dependency.version.clone().unwrap_or_default(),
+                found,
+            };
+
+            if let Some(&selected_index) = selected
+                .iter()
+                .find(|&&i| pool[i].1.name == dependency.name)
+            {
+                let selected_version = &pool[selected_index].1.version;
+                let Some(requirements) = requirements.get_mut(&selected_index) else {
+                    if !satisfies(&pool[selected_index].1) {
+                        return Err(incompatible(match selected_version {
+                            Some(version) => format!("version {} is included", version),
+                            None => "the included shaderpack has no version".to_owned(),
+                        }));
+                    }
+                    edges.push((index, selected_index));
+                    continue;
+                };
+
+                requirements.push((dependent.name.clone(), dependency.version.clone()));
+                if !satisfies(&pool[selected_index].1) {
+                    return Err(DependencyErrorKind::Conflicting {
+                        dependency: dependency.name.clone(),
+                        selected: match selected_version {
+                            Some(version) => format!("version {}", version),
+                            None => "an unversioned shaderpack".to_owned(),
+                        },
+                        requirements: requirements
+                            .iter()
+                            .map(|(dependent, requirement)| match requirement {
+                                Some(requirement) => {
+                                    format!("'{}' requires {}", dependent, requirement)
+                                }
+                                None => format!("'{}' requires any version", dependent),
+                            })
+                            .collect(),
+                    });
+                }
+                edges.push((index, selected_index));
+                continue;
+            }
+
+            let candidates: Vec<usize> = (included_count..pool.len())
+                .filter(|i| pool[*i].1.name == dependency.name)
+                .collect();
+            let Some(&candidate) = candidates
+                .iter()
+                .filter(|&&i| satisfies(&pool[i].1))
+                .max_by_key(|&&i| &pool[i].1.version)
+            else {
+                if candidates.is_empty() {
+                    return Err(DependencyErrorKind::Missing(
+                        dependent.name.clone(),
+                        dependency.name.clone(),
+                    ));
+                }
+                return Err(incompatible(format!(
+                    "the available versions are {}",
+                    candidates
+                        .iter()
+                        .map(|&i| match &pool[i].1.version {
+                            Some(version) => version.to_string(),
+                            None => "unversioned".to_owned(),
+                        })
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )));
+            };
+            selected.push(candidate);
+            requirements.insert(
+                candidate,
+                vec![(dependent.name.clone(), dependency.version.clone())],
+            );
+            queue.push(candidate);
+            edges.push((index, candidate));
+        }
+    }
+
+    // Order the selected shaderpacks so dependencies come first, keeping the original order otherwise.
+    let mut order = vec![];
+    let mut visited = BTreeSet::new();
+    for &index in &selected {
+        visit(index, &edges, &pool, &mut visited, &mut vec![], &mut order)?;
+    }
+
+    let mut pool: Vec<_> = pool.into_iter().map(Some).collect();
+    Ok(order
+        .into_iter()
+        .filter_map(|index| pool[index].take())
+        .collect())
+}
+
+fn visit<T>(
+    index: usize,
+    edges: &[(usize, usize)],
+    pool: &[(T, ShaderPackManifest)],
+    visited: &mut BTreeSet<usize>,
+    path: &mut Vec<usize>,
+    order: &mut Vec<usize>,
+) -> Result<(), DependencyErrorKind> {
+    if let Some(position) = path.iter().position(|&i| i == index) {
+        return Err(DependencyErrorKind::Circular(
+            path[position..]
+                .iter()
+                .chain([&index])
+                .map(|&i| pool[i].1.name.clone())
+                .collect(),
+        ));
+    }
+    if !visited.insert(index) {
+        return Ok(());
+    }
+
+    path.push(index);
+    for &(_, dependency) in edges.iter().filter(|(dependent, _)| *dependent == index) {
+        visit(dependency, edges, pool, visited, path, order)?;
+    }
+    path.pop();
+    order.push(index);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shaderpack::ShaderPackDependency;
+    use semver::Version;
+
+    fn manifest(
+        name: &str,
+        version: Option<&str>,
+        dependencies: &[(&str, Option<&str>)],
+    ) -> ShaderPackManifest {
+        ShaderPackManifest {
+            name: name.to_owned(),
+            version: version.map(|version| Version::parse(version).unwrap()),
+            dependencies: Some(
+                dependencies
+                    .iter()
+                    .map(|(name, version)| ShaderPackDependency {
+                        name: (*name).to_owned(),
+                        version: version.map(str::to_owned),
+                    })
+                    .collect(),
+            ),
+            ..Default::default()
+        }
+    }
+
+    fn pack(
+        source: &'static str,
+        name: &str,
+        version: Option<&str>,
+        dependencies: &[(&str, Option<&str>)],
+    ) -> (&'static str, ShaderPackManifest) {
+        (source, manifest(name, version, dependencies))
+    }
+
+    fn sources(
+        result: Result<Vec<(&'static str, ShaderPackManifest)>, DependencyErrorKind>,
+    ) -> Vec<&'static str> {
+        result
+            .unwrap()
+            .into_iter()
+            .map(|(source, _)| source)
+            .collect()
+    }
+
+    #[test]
+    fn dependencies_are_added_and_ordered_first() {
+        let included = vec![
+            pack("bloom", "Bloom", None, &[("Common", Some("^1"))]),
+            pack("tonemap", "Tonemap", None, &[]),
+        ];
+        let available = vec![
+            pack("common-1.0", "Common", Some("1.0.0"), &[("Base", None)]),
+            pack("common-1.4", "Common", Some("1.4.0"), &[("Base", None)]),
+            pack("common-2.0", "Common", Some("2.0.0"), &[]),
+            pack("base", "Base", Some("0.1.0"), &[]),
+            pack("unused", "Unused", None, &[]),
+        ];
+        assert_eq!(
+            sources(resolve_dependencies(included, available)),
+            ["base", "common-1.4", "bloom", "tonemap"]
+        );
+    }
+
+    #[test]
+    fn included_shaderpacks_satisfy_dependencies() {
+        let included = vec![
+            pack("bloom", "Bloom", None, &[("Common", Some("^1"))]),
+            pack("common", "Common", Some("1.0.0"), &[]),
+        ];
+        let available = vec![pack("common-1.4", "Common", Some("1.4.0"), &[])];
+        assert_eq!(
+            sources(resolve_dependencies(included, available)),
+            ["common", "bloom"]
+        );
+    }
+
+    #[test]
+    fn missing_dependencies_are_reported() {
+        let included = vec![pack("bloom", "Bloom", None, &[("Common", None)])];
+        assert_eq!(
+            resolve_dependencies(included, vec![]).unwrap_err(),
+            DependencyErrorKind::Missing("Bloom".to_owned(), "Common".to_owned())
+        );
+    }
+
+    #[test]
+    fn version_mismatches_are_reported() {
+        let included = vec![pack("bloom", "Bloom", None, &[("Common", Some("^2"))])];
+        let available = vec![
+            pack("common-1.0", "Common", Some("1.0.0"), &[]),
+            pack("common", "Common", None, &[]),
+        ];
+        assert_eq!(
+            resolve_dependencies(included, available)
+                .unwrap_err()
+                .to_string(),
+            "'Bloom' requires 'Common' ^2, but the available versions are 1.0.0, unversioned"
+        );
+
+        let included = vec![
+            pack("bloom", "Bloom", None, &[("Common", Some("^2"))]),
+            pack("common", "Common", Some("1.0.0"), &[]),
+        ];
+        assert_eq!(
+            resolve_dependencies(included, vec![])
+                .unwrap_err()
+                .to_string(),
+            "'Bloom' requires 'Common' ^2, but version 1.0.0 is included"
+        );
+
+        let included = vec![pack("bloom", "Bloom", None, &[("Common", Some("latest"))])];
+        assert_eq!(
+            resolve_dependencies(included, vec![]).unwrap_err(),
+            DependencyErrorKind::InvalidRequirement(
+                "Bloom".to_owned(),
+                "Common".to_owned(),
+                "latest".to_owned()
+            )
+        );
+    }
+
+    #[test]
+    fn conflicting_requirements_are_reported_without_backtracking() {
+        // Common 1.0.0 would satisfy both, but 1.4.0 is picked for the first requirement.
+        let included = vec![
+            pack("bloom", "Bloom", None, &[("Common", Some("~1.0"))]),
+            pack("tonemap", "Tonemap", None, &[("Common", Some("^1"))]),
+        ];
+        let available = vec![
+            pack("common-1.0", "Common", Some("1.0.0"), &[]),
+            pack("common-1.4", "Common", Some("1.4.0"), &[]),
+        ];
+        let err = resolve_dependencies(included, available).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "the requirements on 'Common' conflict, version 1.4.0 was selected for the first of them: \
+             'Tonemap' requires ^1, 'Bloom' requires ~1.0"
+        );
+    }
+
+    #[test]
+    fn cycles_are_reported() {
+        let included = vec![pack("a", "A", None, &[("B", None)])];
+        let available = vec![
+            pack("b", "B", None, &[("C", None)]),
+            pack("c", "C", None, &[("A", None)]),
+        ];
+        assert_eq!(
+            resolve_dependencies(included, available).unwrap_err(),
+            DependencyErrorKind::Circular(vec![
+                "A".to_owned(),
+                "B".to_owned(),
+                "C".to_owned(),
+                "A".to_owned()
+            ])
+        );
+    }
+}
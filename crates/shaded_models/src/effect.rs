@@ -0,0 +1,905 @@
+// References:
+//  - https://github.com/crosire/reshade-shaders/blob/slim/REFERENCE.md
+
+use std::collections::{HashMap, HashSet};
+
+/// File extensions of ReShade FX effect and header files.
+pub const EFFECT_FILE_EXTENSIONS: [&str; 2] = ["fx", "fxh"];
+
+/// File extension of ReShade FX effect files that are compiled and can be referenced by presets.
+pub const EFFECT_ENTRYPOINT_EXTENSION: &str = "fx";
+
+/// Metadata extracted from a ReShade FX effect (`.fx`) or header (`.fxh`) file.
+///
+/// This is not a full ReShade FX compiler, the source is tokenized and scanned for top-level
+/// declarations. Simple `#define NAME VALUE` macros are used to resolve annotation values and
+/// `#if` conditions, and only one branch of each conditional block is scanned. Conditions that
+/// depend on macros not defined in the file, e.g. ones provided by ReShade, are assumed to be true
+/// so that the first such branch is used. Declarations with the same name are only reported once.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct EffectFile {
+    pub techniques: Vec<EffectTechnique>,
+    pub uniforms: Vec<EffectUniform>,
+    pub textures: Vec<EffectTexture>,
+}
+
+/// A `technique` declaration inside of an effect file.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct EffectTechnique {
+    /// The name of the technique as referenced by presets.
+    pub name: String,
+    pub ui_label: Option<String>,
+    pub ui_tooltip: Option<String>,
+}
+
+/// A `uniform` variable declaration inside of an effect file.
+///
+/// Annotation values that are not strings are kept as they were written in the source, e.g. `-1.0`.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct EffectUniform {
+    pub name: String,
+    /// The type of the variable, e.g. `float3`.
+    pub value_type: String,
+    /// The initializer of the variable as it was written in the source, if one was set.
+    pub default_value: Option<String>,
+    /// The special value provided by ReShade from the `source` annotation, e.g. `timer`.
+    /// Uniforms with a source are not user-configurable.
+    pub source: Option<String>,
+    pub ui_type: Option<String>,
+    pub ui_label: Option<String>,
+    pub ui_tooltip: Option<String>,
+    pub ui_category: Option<String>,
+    pub ui_min: Option<String>,
+    pub ui_max: Option<String>,
+    pub ui_step: Option<String>,
+    /// The entries of a combo box or radio button list from the `ui_items` annotation.
+    pub ui_items: Vec<String>,
+}
+
+/// A `texture` declaration inside of an effect file.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct EffectTexture {
+    /// The variable name of the texture.
+    pub name: String,
+    /// The image file path from the `source` annotation, if one was set.
+    pub source: Option<String>,
+    /// The `Width` property, if it was set to a constant value.
+    pub width: Option<u32>,
+    /// The `Height` property, if it was set to a constant value.
+    pub height: Option<u32>,
+    /// The `Format` property, e.g. `RGBA8`.
+    pub format: Option<String>,
+}
+
+impl EffectFile {
+    /// Parse effect metadata from the given effect source code.
+    pub fn parse(source: &str) -> Self {
+        let (tokens, defines) = tokenize(source);
+        let mut effect = Self::default();
+        let mut index = 0;
+
+        while index < tokens.len() {
+            match &tokens[index] {
+                Token::Ident(keyword) if keyword == "technique" => {
+                    let Some(Token::Ident(name)) = tokens.get(index + 1) else {
+                        index += 1;
+                        continue;
+                    };
+                    index += 2;
+
+                    let mut annotations = Annotations::new();
+                    if tokens.get(index) == Some(&Token::Punct('<')) {
+                        index = parse_annotations(&tokens, index + 1, &defines, &mut annotations);
+                    }
+
+                    effect.techniques.push(EffectTechnique {
+                        name: name.clone(),
+                        ui_label: annotation_string(&annotations, "ui_label"),
+                        ui_tooltip: annotation_string(&annotations, "ui_tooltip"),
+                    });
+                }
+                Token::Ident(keyword) if keyword == "uniform" => {
+                    index += 1;
+
+                    // Qualifiers may come before the type, so the last two identifiers are the
+                    // type and the name, e.g. `uniform static const float3 Color`.
+                    let mut idents = vec![];
+                    while let Some(Token::Ident(ident)) = tokens.get(index) {
+                        idents.push(ident.clone());
+                        index += 1;
+                    }
+                    let [.., value_type, name] = idents.as_slice() else {
+                        continue;
+                    };
+
+                    // Skip optional array dimensions, e.g. `uniform float Weights[4]`.
+                    if tokens.get(index) == Some(&Token::Punct('[')) {
+                        while tokens
+                            .get(index)
+                            .is_some_and(|token| token != &Token::Punct(']'))
+                        {
+                            index += 1;
+                        }
+                        index += 1;
+                    }
+
+                    // Skip an optional semantic.
+                    if tokens.get(index) == Some(&Token::Punct(':')) {
+                        index += 2;
+                    }
+
+                    let mut annotations = Annotations::new();
+                    if tokens.get(index) == Some(&Token::Punct('<')) {
+                        index = parse_annotations(&tokens, index + 1, &defines, &mut annotations);
+                    }
+
+                    let mut default_value = None;
+                    if tokens.get(index) == Some(&Token::Punct('=')) {
+                        let start = index + 1;
+                        while tokens
+                            .get(index)
+                            .is_some_and(|token| token != &Token::Punct(';'))
+                        {
+                            index += 1;
+                        }
+                        default_value = Some(tokens_text(&tokens[start..index]));
+                    }
+
+                    effect.uniforms.push(EffectUniform {
+                        name: name.clone(),
+                        value_type: value_type.clone(),
+                        default_value,
+                        source: annotation_string(&annotations, "source"),
+                        ui_type: annotation_string(&annotations, "ui_type"),
+                        ui_label: annotation_string(&annotations, "ui_label"),
+                        ui_tooltip: annotation_string(&annotations, "ui_tooltip"),
+                        ui_category: annotation_string(&annotations, "ui_category"),
+                        ui_min: annotation_text(&annotations, "ui_min"),
+                        ui_max: annotation_text(&annotations, "ui_max"),
+                        ui_step: annotation_text(&annotations, "ui_step"),
+                        ui_items: annotation_string(&annotations, "ui_items")
+                            .map(|items| {
+                                items
+                                    .split('\0')
+                                    .filter(|item| !item.is_empty())
+                                    .map(str::to_owned)
+                                    .collect()
+                            })
+                            .unwrap_or_default(),
+                    });
+                }
+                Token::Ident(keyword) if keyword == "texture" || keyword == "texture2D" => {
+                    let Some(Token::Ident(name)) = tokens.get(index + 1) else {
+                        index += 1;
+                        continue;
+                    };
+                    index += 2;
+
+                    // Skip an optional semantic, e.g. `texture BackBuffer : COLOR;`
+                    if tokens.get(index) == Some(&Token::Punct(':')) {
+                        index += 2;
+                    }
+
+                    let mut annotations = Annotations::new();
+                    if tokens.get(index) == Some(&Token::Punct('<')) {
+                        index = parse_annotations(&tokens, index + 1, &defines, &mut annotations);
+                    }
+
+                    // Parse the texture properties, e.g. `{ Width = 256; Format = R8; }`.
+                    let mut properties = Annotations::new();
+                    if tokens.get(index) == Some(&Token::Punct('{')) {
+                        index = parse_properties(&tokens, index + 1, &defines, &mut properties);
+                    }
+                    let property_number = |name| match properties.get(name).map(Vec::as_slice) {
+                        Some([Token::Number(number)]) => number.parse().ok(),
+                        _ => None,
+                    };
+
+                    effect.textures.push(EffectTexture {
+                        name: name.clone(),
+                        source: annotation_string(&annotations, "source"),
+                        width: property_number("Width"),
+                        height: property_number("Height"),
+                        format: annotation_text(&properties, "Format"),
+                    });
+                }
+                _ => index += 1,
+            }
+        }
+
+        let mut names = HashSet::new();
+        effect
+            .techniques
+            .retain(|technique| names.insert(technique.name.clone()));
+        names.clear();
+        effect
+            .uniforms
+            .retain(|uniform| names.insert(uniform.name.clone()));
+        names.clear();
+        effect
+            .textures
+            .retain(|texture| names.insert(texture.name.clone()));
+        effect
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
+enum Token {
+    Ident(String),
+    String(String),
+    Number(String),
+    Punct(char),
+}
+
+/// Annotation values keyed by annotation name.
+type Annotations = HashMap<String, Vec<Token>>;
+
+/// Parse `name = value;` pairs starting directly after an opening `<` up to and including the
+/// closing `>`, returning the index of the token after it.
+fn parse_annotations(
+    tokens: &[Token],
+    mut index: usize,
+    defines: &HashMap<String, Token>,
+    annotations: &mut Annotations,
+) -> usize {
+    let mut name = None;
+    while let Some(token) = tokens.get(index) {
+        index += 1;
+        match token {
+            Token::Punct('>') => break,
+            // Annotations may be prefixed by a type (`string source = ...`), so the identifier
+            // directly before the `=` is the annotation name.
+            Token::Ident(ident) => name = Some(ident.clone()),
+            Token::Punct('=') => {
+                let Some(name) = name.take() else {
+                    continue;
+                };
+                let mut value = vec![];
+                while let Some(token) = tokens.get(index) {
+                    match token {
+                        Token::Punct(';') => {
+                            index += 1;
+                            break;
+                        }
+                        Token::Punct('>') => break,
+                        Token::Ident(ident) => {
+                            value.push(defines.get(ident).unwrap_or(token).clone())
+                        }
+                        token => value.push(token.clone()),
+                    }
+                    index += 1;
+                }
+                annotations.insert(name, value);
+            }
+            _ => name = None,
+        }
+    }
+    index
+}
+
+/// Parse `Name = value;` pairs starting directly after an opening `{` up to and including the
+/// closing `}`, returning the index of the token after it.
+fn parse_properties(
+    tokens: &[Token],
+    mut index: usize,
+    defines: &HashMap<String, Token>,
+    properties: &mut Annotations,
+) -> usize {
+    while let Some(token) = tokens.get(index) {
+        index += 1;
+        match (token, tokens.get(index)) {
+            (Token::Punct('}'), _) => break,
+            (Token::Ident(name), Some(Token::Punct('='))) => {
+                let start = index + 1;
+                while tokens
+                    .get(index)
+                    .is_some_and(|token| !matches!(token, Token::Punct(';' | '}')))
+                {
+                    index += 1;
+                }
+                let value = tokens[start..index]
+                    .iter()
+                    .map(|token| match token {
+                        Token::Ident(ident) => defines.get(ident).unwrap_or(token).clone(),
+                        token => token.clone(),
+                    })
+                    .collect();
+                properties.insert(name.clone(), value);
+            }
+            _ => {}
+        }
+    }
+    index
+}
+
+/// The value of a string annotation, concatenating adjacent string literals.
+fn annotation_string(annotations: &Annotations, name: &str) -> Option<String> {
+    let value = annotations.get(name)?;
+    value
+        .iter()
+        .map(|token| match token {
+            Token::String(string) => Some(string.as_str()),
+            _ => None,
+        })
+        .collect::<Option<String>>()
+        .filter(|_| !value.is_empty())
+}
+
+/// The value of an annotation of any type as it was written in the source.
+fn annotation_text(annotations: &Annotations, name: &str) -> Option<String> {
+    annotation_string(annotations, name)
+        .or_else(|| annotations.get(name).map(|value| tokens_text(value)))
+}
+
+/// Join tokens back into source text without any whitespace.
+fn tokens_text(tokens: &[Token]) -> String {
+    tokens
+        .iter()
+        .map(|token| match token {
+            Token::Ident(text) | Token::Number(text) => text.clone(),
+            Token::String(string) => format!("{string:?}"),
+            Token::Punct(char) => char.to_string(),
+        })
+        .collect()
+}
+
+/// The state of an `#if` block while tokenizing.
+struct Conditional {
+    /// Whether the code around the block is being tokenized.
+    parent_active: bool,
+    /// Whether an earlier branch of the block was used.
+    taken: bool,
+    /// Whether the current branch is being tokenized.
+    active: bool,
+}
+
+impl Conditional {
+    fn new(parent_active: bool, condition: Option<bool>) -> Self {
+        let mut conditional = Self {
+            parent_active,
+            taken: false,
+            active: false,
+        };
+        conditional.next_branch(condition);
+        conditional
+    }
+
+    /// Move on to the next `#elif` or `#else` branch, where an unknown condition counts as true.
+    fn next_branch(&mut self, condition: Option<bool>) {
+        self.active = self.parent_active && !self.taken && condition != Some(false);
+        self.taken |= self.active;
+    }
+}
+
+/// Split effect source code into tokens, skipping comments, preprocessor directives and inactive
+/// conditional branches.
+///
+/// Returns the tokens alongside the single-token values of any `#define` directives found.
+fn tokenize(source: &str) -> (Vec<Token>, HashMap<String, Token>) {
+    let mut tokens = vec![];
+    let mut defines = HashMap::new();
+    let mut defined_names = HashSet::new();
+    let mut conditionals: Vec<Conditional> = vec![];
+    let mut active = true;
+    let mut chars = source.chars().peekable();
+    let mut line_start = true;
+
+    while let Some(char) = chars.next() {
+        match char {
+            '\n' => line_start = true,
+            char if char.is_whitespace() => {}
+            '#' if line_start => {
+                let mut directive = String::new();
+                while let Some(char) = chars.next() {
+                    match char {
+                        '\\' if chars.peek() == Some(&'\n') => {
+                            chars.next();
+                            directive.push(' ');
+                        }
+                        '\n' => break,
+                        char => directive.push(char),
+                    }
+                }
+                let directive = directive.trim_start();
+                let (name, arguments) = directive
+                    .find(|char: char| !char.is_ascii_alphabetic())
+                    .map_or((directive, ""), |index| directive.split_at(index));
+                let is_defined = |name: &str| defined_names.contains(name.trim()).then_some(true);
+                match name {
+                    "if" => conditionals.push(Conditional::new(
+                        active,
+                        evaluate_condition(arguments, &defines, &defined_names),
+                    )),
+                    "ifdef" => conditionals.push(Conditional::new(active, is_defined(arguments))),
+                    "ifndef" => conditionals.push(Conditional::new(
+                        active,
+                        is_defined(arguments).map(|defined| !defined),
+                    )),
+                    "elif" => {
+                        let condition = evaluate_condition(arguments, &defines, &defined_names);
+                        if let Some(conditional) = conditionals.last_mut() {
+                            conditional.next_branch(condition);
+                        }
+                    }
+                    "else" => {
+                        if let Some(conditional) = conditionals.last_mut() {
+                            conditional.next_branch(Some(true));
+                        }
+                    }
+                    "endif" => {
+                        conditionals.pop();
+                    }
+                    "define" if active => {
+                        let (define_tokens, _) = tokenize(arguments);
+                        if let Some(Token::Ident(name)) = define_tokens.first() {
+                            defined_names.insert(name.clone());
+                            if let [_, value] = define_tokens.as_slice() {
+                                defines.entry(name.clone()).or_insert_with(|| value.clone());
+                            }
+                        }
+                    }
+                    "undef" if active => {
+                        defined_names.remove(arguments.trim());
+                        defines.remove(arguments.trim());
+                    }
+                    _ => {}
+                }
+                active = conditionals
+                    .last()
+                    .is_none_or(|conditional| conditional.active);
+                line_start = true;
+            }
+            '/' if chars.peek() == Some(&'/') => {
+                for char in chars.by_ref() {
+                    if char == '\n' {
+                        line_start = true;
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = '\0';
+                for char in chars.by_ref() {
+                    if previous == '*' && char == '/' {
+                        break;
+                    }
+                    previous = char;
+                }
+            }
+            '"' => {
+                let mut string = String::new();
+                while let Some(char) = chars.next() {
+                    match char {
+                        '\\' => match chars.next() {
+                            Some('n') => string.push('\n'),
+                            Some('t') => string.push('\t'),
+                            Some('0') => string.push('\0'),
+                            Some(escaped) => string.push(escaped),
+                            None => {}
+                        },
+                        '"' => break,
+                        char => string.push(char),
+                    }
+                }
+                if active {
+                    tokens.push(Token::String(string));
+                }
+                line_start = false;
+            }
+            char if char.is_ascii_alphabetic() || char == '_' => {
+                let mut ident = String::from(char);
+                while let Some(&char) = chars.peek() {
+                    if !char.is_ascii_alphanumeric() && char != '_' {
+                        break;
+                    }
+                    ident.push(char);
+                    chars.next();
+                }
+                if active {
+                    tokens.push(Token::Ident(ident));
+                }
+                line_start = false;
+            }
+            char if char.is_ascii_digit() => {
+                let mut number = String::from(char);
+                while let Some(&char) = chars.peek() {
+                    if !char.is_ascii_alphanumeric() && char != '.' {
+                        break;
+                    }
+                    number.push(char);
+                    chars.next();
+                }
+                if active {
+                    tokens.push(Token::Number(number));
+                }
+                line_start = false;
+            }
+            char => {
+                if active {
+                    tokens.push(Token::Punct(char));
+                }
+                line_start = false;
+            }
+        }
+    }
+
+    (tokens, defines)
+}
+
+/// Evaluate the expression of an `#if` or `#elif` directive.
+///
+/// Returns `None` if the result depends on a macro that is not defined in the file.
+fn evaluate_condition(
+    expression: &str,
+    defines: &HashMap<String, Token>,
+    defined_names: &HashSet<String>,
+) -> Option<bool> {
+    let (tokens, _) = tokenize(expression);
+    let mut evaluator = ConditionEvaluator {
+        tokens: &tokens,
+        index: 0,
+        defines,
+        defined_names,
+    };
+    let value = evaluator.or()?;
+    (evaluator.index == tokens.len()).then_some(value != 0)
+}
+
+/// A recursive descent evaluator for the integer expressions of `#if` directives.
+///
+/// Every method returns `None` for a value that cannot be known.
+struct ConditionEvaluator<'a> {
+    tokens: &'a [Token],
+    index: usize,
+    defines: &'a HashMap<String, Token>,
+    defined_names: &'a HashSet<String>,
+}
+
+impl ConditionEvaluator<'_> {
+    /// Consume the given operator if it is next.
+    fn accept(&mut self, operator: &str) -> bool {
+        let matches = operator.chars().enumerate().all(|(offset, char)| {
+            self.tokens.get(self.index + offset) == Some(&Token::Punct(char))
+        });
+        // Don't mistake the start of `<=` or `==` for `<` or `=`.
+        let extended = matches!(
+            self.tokens.get(self.index + operator.len()),
+            Some(Token::Punct('='))
+        ) && matches!(operator, "<" | ">" | "!");
+        if matches && !extended {
+            self.index += operator.len();
+        }
+        matches && !extended
+    }
+
+    fn or(&mut self) -> Option<i64> {
+        let mut value = self.and();
+        while self.accept("||") {
+            let right = self.and();
+            value = match (value, right) {
+                (Some(left), _) if left != 0 => Some(1),
+                (_, Some(right)) if right != 0 => Some(1),
+                (Some(_), Some(_)) => Some(0),
+                _ => None,
+            };
+        }
+        value
+    }
+
+    fn and(&mut self) -> Option<i64> {
+        let mut value = self.equality();
+        while self.accept("&&") {
+            let right = self.equality();
+            value = match (value, right) {
+                (Some(0), _) | (_, Some(0)) => Some(0),
+                (Some(_), Some(_)) => Some(1),
+                _ => None,
+            };
+        }
+        value
+    }
+
+    fn equality(&mut self) -> Option<i64> {
+        let mut value = self.relational();
+        loop {
+            let operator: fn(i64, i64) -> bool = if self.accept("==") {
+                |a, b| a == b
+            } else if self.accept("!=") {
+                |a, b| a != b
+            } else {
+                return value;
+            };
+            let right = self.relational();
+            value = Some(operator(value?, right?) as i64);
+        }
+    }
+
+    fn relational(&mut self) -> Option<i64> {
+        let mut value = self.additive();
+        loop {
+            let operator: fn(i64, i64) -> bool = if self.accept("<=") {
+                |a, b| a <= b
+            } else if self.accept(">=") {
+                |a, b| a >= b
+            } else if self.accept("<") {
+                |a, b| a < b
+            } else if self.accept(">") {
+                |a, b| a > b
+            } else {
+                return value;
+            };
+            let right = self.additive();
+            value = Some(operator(value?, right?) as i64);
+        }
+    }
+
+    fn additive(&mut self) -> Option<i64> {
+        let mut value = self.unary();
+        loop {
+            let operator: fn(i64, i64) -> Option<i64> = if self.accept("+") {
+                i64::checked_add
+            } else if self.accept("-") {
+                i64::checked_sub
+            } else {
+                return value;
+            };
+            let right = self.unary();
+            value = operator(value?, right?);
+        }
+    }
+
+    fn unary(&mut self) -> Option<i64> {
+        if self.accept("!") {
+            self.unary().map(|value| (value == 0) as i64)
+        } else if self.accept("-") {
+            self.unary()?.checked_neg()
+        } else if self.accept("+") {
+            self.unary()
+        } else {
+            self.primary()
+        }
+    }
+
+    fn primary(&mut self) -> Option<i64> {
+        let token = self.tokens.get(self.index)?.clone();
+        self.index += 1;
+        match token {
+            Token::Punct('(') => {
+                let value = self.or();
+                self.accept(")").then_some(())?;
+                value
+            }
+            Token::Number(number) => parse_integer(&number),
+            Token::Ident(ident) if ident == "defined" => {
+                let parenthesized = self.accept("(");
+                let Some(Token::Ident(name)) = self.tokens.get(self.index) else {
+                    return None;
+                };
+                self.index += 1;
+                if parenthesized {
+                    self.accept(")").then_some(())?;
+                }
+                self.defined_names.contains(name).then_some(1)
+            }
+            Token::Ident(ident) => match self.defines.get(&ident) {
+                Some(Token::Number(number)) => parse_integer(number),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Parse a decimal or hexadecimal integer literal with an optional `u` or `l` suffix.
+fn parse_integer(literal: &str) -> Option<i64> {
+    let literal = literal.trim_end_matches(['u', 'U', 'l', 'L']);
+    match literal
+        .strip_prefix("0x")
+        .or_else(|| literal.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok(),
+        None => literal.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_uniforms_with_annotations() {
+        let effect = EffectFile::parse(
+            r#"
+            uniform float3 Tint < __UNIFORM_COLOR_FLOAT3
+                ui_type = "color";
+                ui_label = "Tint " "Color";
+                ui_tooltip = "The colour to tint the image with";
+                ui_category = "Colour";
+                ui_min = 0.0; ui_max = 1.0; ui_step = 0.01;
+            > = float3(1.0, 0.5, 0.25);
+
+            uniform float Timer < source = "timer"; >;
+            uniform static const int Weights[4] : WEIGHTS < ui_min = -1; >;
+            "#,
+        );
+
+        assert_eq!(
+            effect.uniforms,
+            vec![
+                EffectUniform {
+                    name: "Tint".to_owned(),
+                    value_type: "float3".to_owned(),
+                    default_value: Some("float3(1.0,0.5,0.25)".to_owned()),
+                    ui_type: Some("color".to_owned()),
+                    ui_label: Some("Tint Color".to_owned()),
+                    ui_tooltip: Some("The colour to tint the image with".to_owned()),
+                    ui_category: Some("Colour".to_owned()),
+                    ui_min: Some("0.0".to_owned()),
+                    ui_max: Some("1.0".to_owned()),
+                    ui_step: Some("0.01".to_owned()),
+                    ..Default::default()
+                },
+                EffectUniform {
+                    name: "Timer".to_owned(),
+                    value_type: "float".to_owned(),
+                    source: Some("timer".to_owned()),
+                    ..Default::default()
+                },
+                EffectUniform {
+                    name: "Weights".to_owned(),
+                    value_type: "int".to_owned(),
+                    ui_min: Some("-1".to_owned()),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn splits_ui_items() {
+        let effect = EffectFile::parse(
+            r#"uniform int Mode < ui_type = "combo"; ui_items = "Off\0Low\0" "High\0"; > = 1;"#,
+        );
+
+        assert_eq!(effect.uniforms[0].ui_items, vec!["Off", "Low", "High"]);
+        assert_eq!(effect.uniforms[0].default_value.as_deref(), Some("1"));
+    }
+
+    #[test]
+    fn parses_techniques_and_textures() {
+        let effect = EffectFile::parse(
+            r#"
+            #define LUT_SIZE 32
+            // technique Commented { }
+            /* technique AlsoCommented { } */
+            texture LutTex < source = "lut.png"; > { Width = LUT_SIZE * LUT_SIZE; Height = LUT_SIZE; Format = RGBA8; };
+            texture NoiseTex { Width = 256; Height = 256; };
+            texture BackBufferTex : COLOR;
+            technique Lut < ui_label = "LUT"; ui_tooltip = "Applies a LUT"; > { pass { } }
+            technique Plain { pass { } }
+            "#,
+        );
+
+        assert_eq!(
+            effect.textures,
+            vec![
+                EffectTexture {
+                    name: "LutTex".to_owned(),
+                    source: Some("lut.png".to_owned()),
+                    width: None,
+                    height: Some(32),
+                    format: Some("RGBA8".to_owned()),
+                },
+                EffectTexture {
+                    name: "NoiseTex".to_owned(),
+                    width: Some(256),
+                    height: Some(256),
+                    ..Default::default()
+                },
+                EffectTexture {
+                    name: "BackBufferTex".to_owned(),
+                    ..Default::default()
+                },
+            ]
+        );
+        assert_eq!(
+            effect.techniques,
+            vec![
+                EffectTechnique {
+                    name: "Lut".to_owned(),
+                    ui_label: Some("LUT".to_owned()),
+                    ui_tooltip: Some("Applies a LUT".to_owned()),
+                },
+                EffectTechnique {
+                    name: "Plain".to_owned(),
+                    ..Default::default()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn only_scans_active_conditional_branches() {
+        let effect = EffectFile::parse(
+            r#"
+            #define QUALITY 2
+            #if QUALITY >= 2 && defined(QUALITY)
+            technique High { }
+            #elif QUALITY == 1
+            technique Medium { }
+            #else
+            technique Low { }
+            #endif
+
+            #if 0
+            uniform float Disabled;
+            #endif
+
+            #ifndef QUALITY
+            uniform float NoQuality;
+            #else
+            uniform float HasQuality;
+            #endif
+            "#,
+        );
+
+        let techniques: Vec<_> = effect.techniques.iter().map(|t| t.name.as_str()).collect();
+        let uniforms: Vec<_> = effect.uniforms.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(techniques, vec!["High"]);
+        assert_eq!(uniforms, vec!["HasQuality"]);
+    }
+
+    #[test]
+    fn uses_first_branch_of_unknown_conditions() {
+        let effect = EffectFile::parse(
+            r#"
+            #if __RENDERER__ >= 0xb000
+            technique Modern { }
+            uniform float Strength < ui_max = 2.0; >;
+            #else
+            technique Modern { }
+            uniform float Strength < ui_max = 1.0; >;
+            #endif
+
+            #ifndef SETTING
+                #define SETTING 3
+            #endif
+            #if SETTING == 3
+            technique Setting { }
+            #endif
+
+            #if 0
+                #if __RESHADE__
+                technique Nested { }
+                #endif
+            #endif
+            "#,
+        );
+
+        let techniques: Vec<_> = effect.techniques.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(techniques, vec!["Modern", "Setting"]);
+        assert_eq!(effect.uniforms.len(), 1);
+        assert_eq!(effect.uniforms[0].ui_max.as_deref(), Some("2.0"));
+    }
+
+    #[test]
+    fn evaluates_condition_expressions() {
+        let defines = HashMap::from([
+            ("ONE".to_owned(), Token::Number("1".to_owned())),
+            ("HEX".to_owned(), Token::Number("0x10u".to_owned())),
+        ]);
+        let defined_names = HashSet::from(["ONE".to_owned(), "HEX".to_owned()]);
+        let evaluate = |expression| evaluate_condition(expression, &defines, &defined_names);
+
+        assert_eq!(evaluate("1"), Some(true));
+        assert_eq!(evaluate("!ONE"), Some(false));
+        assert_eq!(evaluate("HEX == 16 && ONE != 2"), Some(true));
+        assert_eq!(evaluate("(ONE + 1) * 2"), None);
+        assert_eq!(evaluate("-ONE < 0 || UNKNOWN"), Some(true));
+        assert_eq!(evaluate("UNKNOWN && 0"), Some(false));
+        assert_eq!(evaluate("UNKNOWN > 1"), None);
+        assert_eq!(evaluate("defined ONE && !defined(HEX)"), Some(false));
+        assert_eq!(evaluate("ONE <= 1 && ONE >= 1"), Some(true));
+    }
+}
@@ -0,0 +1,657 @@
+use std::fmt;
+use thiserror::Error;
+
+/// Image file formats that ReShade is able to load as texture sources.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+    Bmp,
+    Tga,
+    Dds,
+}
+
+/// Information read from the header of an image file.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ImageInfo {
+    pub format: ImageFormat,
+    pub width: u32,
+    pub height: u32,
+    /// The number of colour channels stored in the image, if it could be determined.
+    pub channels: Option<u8>,
+}
+
+#[derive(Error, Debug)]
+pub enum ImageReadErrorKind {
+    #[error("failed to read the image file")]
+    ReadFailure(std::io::Error),
+
+    #[error("file is not a PNG, JPEG, BMP, TGA or DDS image")]
+    UnrecognizedFormat,
+
+    #[error("{0} image is corrupt: {1}")]
+    Corrupt(ImageFormat, &'static str),
+}
+
+impl fmt::Display for ImageFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ImageFormat::Png => "PNG",
+            ImageFormat::Jpeg => "JPEG",
+            ImageFormat::Bmp => "BMP",
+            ImageFormat::Tga => "TGA",
+            ImageFormat::Dds => "DDS",
+        })
+    }
+}
+
+impl ImageFormat {
+    /// The file extensions commonly used for this format.
+    pub fn extensions(&self) -> &'static [&'static str] {
+        match self {
+            ImageFormat::Png => &["png"],
+            ImageFormat::Jpeg => &["jpg", "jpeg"],
+            ImageFormat::Bmp => &["bmp"],
+            ImageFormat::Tga => &["tga"],
+            ImageFormat::Dds => &["dds"],
+        }
+    }
+
+    /// Get the format that is expected for the given file extension, ignoring case.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        [
+            ImageFormat::Png,
+            ImageFormat::Jpeg,
+            ImageFormat::Bmp,
+            ImageFormat::Tga,
+            ImageFormat::Dds,
+        ]
+        .into_iter()
+        .find(|format| {
+            format
+                .extensions()
+                .iter()
+                .any(|candidate| candidate.eq_ignore_ascii_case(extension))
+        })
+    }
+}
+
+impl ImageInfo {
+    /// Parse the header of an image from its file contents, detecting the format from its contents.
+    ///
+    /// Besides the header, the file is checked for truncation and, for PNG images, for chunk checksum
+    /// mismatches. Pixel data is not decoded.
+    pub fn parse(bytes: &[u8]) -> Result<Self, ImageReadErrorKind> {
+        if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+            parse_png(bytes)
+        } else if bytes.starts_with(&[0xFF, 0xD8]) {
+            parse_jpeg(bytes)
+        } else if bytes.starts_with(b"BM") {
+            parse_bmp(bytes)
+        } else if bytes.starts_with(b"DDS ") {
+            parse_dds(bytes)
+        } else {
+            // TGA files have no magic number, so the header fields have to be consistent instead.
+            parse_tga(bytes)
+        }
+    }
+}
+
+fn read_u16_le(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32_le(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn read_u16_be(bytes: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(
+        bytes.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn read_u32_be(bytes: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(
+        bytes.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+fn parse_png(bytes: &[u8]) -> Result<ImageInfo, ImageReadErrorKind> {
+    let corrupt = |reason| ImageReadErrorKind::Corrupt(ImageFormat::Png, reason);
+    let mut info = None;
+    let mut offset = 8;
+
+    loop {
+        let length = read_u32_be(bytes, offset).ok_or(corrupt("file is truncated"))? as usize;
+        let chunk = bytes
+            .get(offset + 4..offset + 8 + length)
+            .ok_or(corrupt("file is truncated"))?;
+        let checksum =
+            read_u32_be(bytes, offset + 8 + length).ok_or(corrupt("file is truncated"))?;
+        if crc32(chunk) != checksum {
+            return Err(corrupt("chunk checksum mismatch"));
+        }
+
+        let (chunk_type, data) = chunk.split_at(4);
+        match chunk_type {
+            b"IHDR" if info.is_none() && length == 13 => {
+                info = Some(ImageInfo {
+                    format: ImageFormat::Png,
+                    width: read_u32_be(data, 0).unwrap_or_default(),
+                    height: read_u32_be(data, 4).unwrap_or_default(),
+                    channels: match data[9] {
+                        0 => Some(1),
+                        2 | 3 => Some(3),
+                        4 => Some(2),
+                        6 => Some(4),
+                        _ => return Err(corrupt("invalid colour type")),
+                    },
+                });
+            }
+            _ if info.is_none() => return Err(corrupt("first chunk is not a valid IHDR chunk")),
+            b"IEND" => break,
+            _ => {}
+        }
+        offset += 12 + length;
+    }
+
+    let info = info.ok_or(corrupt("missing IHDR chunk"))?;
+    if info.width == 0 || info.height == 0 {
+        return Err(corrupt("image has no pixels"));
+    }
+    Ok(info)
+}
+
+fn parse_jpeg(bytes: &[u8]) -> Result<ImageInfo, ImageReadErrorKind> {
+    let corrupt = |reason| ImageReadErrorKind::Corrupt(ImageFormat::Jpeg, reason);
+    let mut offset = 2;
+
+    // Walk the marker segments until a start of frame segment containing the dimensions is found.
+    let info = loop {
+        if bytes.get(offset) != Some(&0xFF) {
+            return Err(corrupt("invalid marker segment"));
+        }
+        let marker = *bytes.get(offset + 1).ok_or(corrupt("file is truncated"))?;
+        match marker {
+            // Fill bytes before a marker.
+            0xFF => offset += 1,
+            // Standalone markers without a length.
+            0x01 | 0xD0..=0xD7 => offset += 2,
+            0xD9 | 0xDA => return Err(corrupt("missing start of frame segment")),
+            _ => {
+                let length = read_u16_be(bytes, offset + 2).ok_or(corrupt("file is truncated"))?;
+                // The length includes its own two bytes.
+                if length < 2 {
+                    return Err(corrupt("invalid marker segment length"));
+                }
+                // SOF0-SOF15, excluding the DHT, JPG and DAC markers that share the range.
+                if matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+                    let segment = bytes
+                        .get(offset + 4..offset + 2 + length as usize)
+                        .ok_or(corrupt("file is truncated"))?;
+                    break ImageInfo {
+                        format: ImageFormat::Jpeg,
+                        height: read_u16_be(segment, 1).ok_or(corrupt("invalid frame header"))?
+                            as u32,
+                        width: read_u16_be(segment, 3).ok_or(corrupt("invalid frame header"))?
+                            as u32,
+                        channels: segment.get(5).copied(),
+                    };
+                }
+                offset += 2 + length as usize;
+            }
+        }
+    };
+
+    if !bytes[offset..]
+        .windows(2)
+        .any(|marker| marker == [0xFF, 0xD9])
+    {
+        return Err(corrupt(
+            "missing end of image marker, file may be truncated",
+        ));
+    }
+    if info.width == 0 || info.height == 0 {
+        return Err(corrupt("image has no pixels"));
+    }
+    Ok(info)
+}
+
+fn parse_bmp(bytes: &[u8]) -> Result<ImageInfo, ImageReadErrorKind> {
+    let corrupt = |reason| ImageReadErrorKind::Corrupt(ImageFormat::Bmp, reason);
+    let truncated = || corrupt("file is truncated");
+
+    let file_size = read_u32_le(bytes, 2).ok_or_else(truncated)? as usize;
+    let pixel_offset = read_u32_le(bytes, 10).ok_or_else(truncated)? as usize;
+    let header_size = read_u32_le(bytes, 14).ok_or_else(truncated)?;
+    let (width, height, bit_count) = if header_size == 12 {
+        (
+            read_u16_le(bytes, 18).ok_or_else(truncated)? as i64,
+            read_u16_le(bytes, 20).ok_or_else(truncated)? as i64,
+            read_u16_le(bytes, 24).ok_or_else(truncated)?,
+        )
+    } else {
+        (
+            read_u32_le(bytes, 18).ok_or_else(truncated)? as i32 as i64,
+            // A negative height marks a top-down image.
+            (read_u32_le(bytes, 22).ok_or_else(truncated)? as i32 as i64).abs(),
+            read_u16_le(bytes, 28).ok_or_else(truncated)?,
+        )
+    };
+
+    if bytes.len() < file_size || bytes.len() <= pixel_offset {
+        return Err(truncated());
+    }
+    if width <= 0 || height == 0 {
+        return Err(corrupt("image has no pixels"));
+    }
+    Ok(ImageInfo {
+        format: ImageFormat::Bmp,
+        width: width as u32,
+        height: height as u32,
+        channels: match bit_count {
+            32 => Some(4),
+            1 | 4 | 8 | 16 | 24 => Some(3),
+            _ => return Err(corrupt("invalid bit count")),
+        },
+    })
+}
+
+/// Parse a TGA header, which is only recognized if its fields are consistent with each other.
+fn parse_tga(bytes: &[u8]) -> Result<ImageInfo, ImageReadErrorKind> {
+    let corrupt = |reason| ImageReadErrorKind::Corrupt(ImageFormat::Tga, reason);
+    let header = bytes
+        .get(..18)
+        .ok_or(ImageReadErrorKind::UnrecognizedFormat)?;
+    let (id_length, color_map_type, image_type, depth, descriptor) = (
+        header[0] as usize,
+        header[1],
+        header[2],
+        header[16],
+        header[17],
+    );
+    let color_map_length = read_u16_le(header, 5).unwrap_or_default() as usize;
+    let color_map_depth = header[7];
+    let width = read_u16_le(header, 12).unwrap_or_default() as u32;
+    let height = read_u16_le(header, 14).unwrap_or_default() as u32;
+    let alpha_bits = descriptor & 0x0F;
+
+    let consistent = match (color_map_type, image_type) {
+        // Colour mapped images index into a colour map of 8 or 16 bit entries.
+        (1, 1 | 9) => {
+            color_map_length > 0
+                && matches!(color_map_depth, 15 | 16 | 24 | 32)
+                && matches!(depth, 8 | 16)
+        }
+        // True colour images have no colour map.
+        (0, 2 | 10) => {
+            color_map_length == 0
+                && matches!(depth, 15 | 16 | 24 | 32)
+                && alpha_bits <= 8
+                && (depth == 32 || alpha_bits <= 1)
+        }
+        // Greyscale images have no colour map.
+        (0, 3 | 11) => color_map_length == 0 && matches!(depth, 8 | 16),
+        _ => false,
+    };
+    // The two highest descriptor bits are unused and must be zero.
+    if !consistent || descriptor & 0xC0 != 0 || width == 0 || height == 0 {
+        return Err(ImageReadErrorKind::UnrecognizedFormat);
+    }
+    let color_map_bytes = color_map_length * (color_map_depth as usize).div_ceil(8);
+
+    // Run-length encoded image data can only be validated by decoding it, so only check the
+    // size of uncompressed images.
+    let data_offset = 18 + id_length + color_map_bytes;
+    let expected_length = match image_type {
+        1..=3 => data_offset + width as usize * height as usize * (depth as usize).div_ceil(8),
+        _ => data_offset + 1,
+    };
+    if bytes.len() < expected_length {
+        return Err(corrupt("file is truncated"));
+    }
+
+    Ok(ImageInfo {
+        format: ImageFormat::Tga,
+        width,
+        height,
+        channels: match (image_type, depth) {
+            (3 | 11, _) => Some(1),
+            (_, 32) => Some(4),
+            _ => Some(3),
+        },
+    })
+}
+
+fn parse_dds(bytes: &[u8]) -> Result<ImageInfo, ImageReadErrorKind> {
+    let corrupt = |reason| ImageReadErrorKind::Corrupt(ImageFormat::Dds, reason);
+    if read_u32_le(bytes, 4) != Some(124) {
+        return Err(corrupt("invalid header size"));
+    }
+    if bytes.len() <= 128 {
+        return Err(corrupt("file is truncated"));
+    }
+
+    let info = ImageInfo {
+        format: ImageFormat::Dds,
+        height: read_u32_le(bytes, 12).unwrap_or_default(),
+        width: read_u32_le(bytes, 16).unwrap_or_default(),
+        // Block compressed formats do not map to a channel count.
+        channels: None,
+    };
+    if info.width == 0 || info.height == 0 {
+        return Err(corrupt("image has no pixels"));
+    }
+    Ok(info)
+}
+
+/// CRC-32 as used by PNG chunks.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for byte in bytes {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corrupt_reason(
+        result: Result<ImageInfo, ImageReadErrorKind>,
+    ) -> (ImageFormat, &'static str) {
+        match result {
+            Err(ImageReadErrorKind::Corrupt(format, reason)) => (format, reason),
+            result => panic!("expected a corrupt image, got {:?}", result),
+        }
+    }
+
+    fn png_chunk(chunk_type: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = (data.len() as u32).to_be_bytes().to_vec();
+        chunk.extend(chunk_type);
+        chunk.extend(data);
+        chunk.extend(crc32(&chunk[4..]).to_be_bytes());
+        chunk
+    }
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut ihdr = width.to_be_bytes().to_vec();
+        ihdr.extend(height.to_be_bytes());
+        ihdr.extend([8, 6, 0, 0, 0]);
+        let mut bytes = b"\x89PNG\r\n\x1a\n".to_vec();
+        bytes.extend(png_chunk(b"IHDR", &ihdr));
+        bytes.extend(png_chunk(b"IDAT", &[0x78, 0x9C, 0x01, 0x02]));
+        bytes.extend(png_chunk(b"IEND", &[]));
+        bytes
+    }
+
+    fn jpeg() -> Vec<u8> {
+        let mut bytes = vec![0xFF, 0xD8];
+        bytes.extend([0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00]);
+        // SOF0 with 8 bit precision, a height of 3, a width of 2 and 3 components.
+        bytes.extend([0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x03, 0x00, 0x02, 0x03]);
+        bytes.extend([0; 9]);
+        bytes.extend([0xFF, 0xDA, 0x00, 0x02, 0x12, 0x34]);
+        bytes.extend([0xFF, 0xD9]);
+        bytes
+    }
+
+    fn bmp(width: i32, height: i32, bit_count: u16) -> Vec<u8> {
+        let pixel_bytes = 16;
+        let mut bytes = b"BM".to_vec();
+        bytes.extend((54u32 + pixel_bytes).to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes.extend(54u32.to_le_bytes());
+        bytes.extend(40u32.to_le_bytes());
+        bytes.extend(width.to_le_bytes());
+        bytes.extend(height.to_le_bytes());
+        bytes.extend(1u16.to_le_bytes());
+        bytes.extend(bit_count.to_le_bytes());
+        bytes.extend([0; 24]);
+        bytes.extend(vec![0; pixel_bytes as usize]);
+        bytes
+    }
+
+    fn tga(color_map_type: u8, image_type: u8, depth: u8, descriptor: u8) -> Vec<u8> {
+        let mut bytes = vec![0, color_map_type, image_type, 0, 0, 0, 0, 0];
+        bytes.extend([0; 4]);
+        bytes.extend(2u16.to_le_bytes());
+        bytes.extend(2u16.to_le_bytes());
+        bytes.extend([depth, descriptor]);
+        bytes.extend(vec![0; 4 * (depth as usize).div_ceil(8)]);
+        bytes
+    }
+
+    fn dds(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = b"DDS ".to_vec();
+        bytes.extend(124u32.to_le_bytes());
+        bytes.extend([0; 4]);
+        bytes.extend(height.to_le_bytes());
+        bytes.extend(width.to_le_bytes());
+        bytes.resize(128 + 16, 0);
+        bytes
+    }
+
+    #[test]
+    fn parses_png() {
+        let info = ImageInfo::parse(&png(2, 3)).unwrap();
+        assert_eq!(
+            info,
+            ImageInfo {
+                format: ImageFormat::Png,
+                width: 2,
+                height: 3,
+                channels: Some(4),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_png() {
+        let bytes = png(2, 3);
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&bytes[..bytes.len() - 4])),
+            (ImageFormat::Png, "file is truncated")
+        );
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&bytes[..10])),
+            (ImageFormat::Png, "file is truncated")
+        );
+
+        let mut flipped = bytes.clone();
+        flipped[40] ^= 0xFF;
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&flipped)),
+            (ImageFormat::Png, "chunk checksum mismatch")
+        );
+
+        let mut no_header = b"\x89PNG\r\n\x1a\n".to_vec();
+        no_header.extend(png_chunk(b"IEND", &[]));
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&no_header)),
+            (ImageFormat::Png, "first chunk is not a valid IHDR chunk")
+        );
+
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&png(0, 3))),
+            (ImageFormat::Png, "image has no pixels")
+        );
+    }
+
+    #[test]
+    fn parses_jpeg() {
+        let info = ImageInfo::parse(&jpeg()).unwrap();
+        assert_eq!(
+            info,
+            ImageInfo {
+                format: ImageFormat::Jpeg,
+                width: 2,
+                height: 3,
+                channels: Some(3),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_jpeg() {
+        let bytes = jpeg();
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&bytes[..bytes.len() - 2])),
+            (
+                ImageFormat::Jpeg,
+                "missing end of image marker, file may be truncated"
+            )
+        );
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&bytes[..14])),
+            (ImageFormat::Jpeg, "file is truncated")
+        );
+
+        let mut bad_length = bytes.clone();
+        bad_length[5] = 0;
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&bad_length)),
+            (ImageFormat::Jpeg, "invalid marker segment length")
+        );
+
+        let no_frame = [0xFF, 0xD8, 0xFF, 0xDA, 0x00, 0x02, 0xFF, 0xD9];
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&no_frame)),
+            (ImageFormat::Jpeg, "missing start of frame segment")
+        );
+
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&[0xFF, 0xD8, 0x00, 0x00])),
+            (ImageFormat::Jpeg, "invalid marker segment")
+        );
+    }
+
+    #[test]
+    fn parses_bmp() {
+        // A negative height marks a top-down image.
+        let info = ImageInfo::parse(&bmp(2, -2, 32)).unwrap();
+        assert_eq!(
+            info,
+            ImageInfo {
+                format: ImageFormat::Bmp,
+                width: 2,
+                height: 2,
+                channels: Some(4),
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_bmp() {
+        let bytes = bmp(2, 2, 24);
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&bytes[..bytes.len() - 1])),
+            (ImageFormat::Bmp, "file is truncated")
+        );
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&bytes[..20])),
+            (ImageFormat::Bmp, "file is truncated")
+        );
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&bmp(2, 2, 7))),
+            (ImageFormat::Bmp, "invalid bit count")
+        );
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&bmp(0, 2, 24))),
+            (ImageFormat::Bmp, "image has no pixels")
+        );
+    }
+
+    #[test]
+    fn parses_tga() {
+        let info = ImageInfo::parse(&tga(0, 2, 32, 8)).unwrap();
+        assert_eq!(
+            info,
+            ImageInfo {
+                format: ImageFormat::Tga,
+                width: 2,
+                height: 2,
+                channels: Some(4),
+            }
+        );
+        assert_eq!(
+            ImageInfo::parse(&tga(0, 3, 8, 0)).unwrap().channels,
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_tga() {
+        let bytes = tga(0, 2, 24, 0);
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&bytes[..bytes.len() - 1])),
+            (ImageFormat::Tga, "file is truncated")
+        );
+    }
+
+    #[test]
+    fn does_not_recognize_inconsistent_tga_headers() {
+        let unrecognized = |bytes: &[u8]| {
+            matches!(
+                ImageInfo::parse(bytes),
+                Err(ImageReadErrorKind::UnrecognizedFormat)
+            )
+        };
+
+        // A colour map type that doesn't match the image type.
+        assert!(unrecognized(&tga(1, 2, 24, 0)));
+        assert!(unrecognized(&tga(0, 1, 8, 0)));
+        // A depth that doesn't match the image type.
+        assert!(unrecognized(&tga(0, 3, 24, 0)));
+        // Alpha bits on an image without an alpha channel, and unused descriptor bits.
+        assert!(unrecognized(&tga(0, 2, 24, 8)));
+        assert!(unrecognized(&tga(0, 2, 32, 0xC8)));
+        // Arbitrary bytes.
+        assert!(unrecognized(b"this is some text that is not an image"));
+        assert!(unrecognized(&[0; 64]));
+        assert!(unrecognized(&[0, 0, 2]));
+    }
+
+    #[test]
+    fn parses_dds() {
+        let info = ImageInfo::parse(&dds(4, 8)).unwrap();
+        assert_eq!(
+            info,
+            ImageInfo {
+                format: ImageFormat::Dds,
+                width: 4,
+                height: 8,
+                channels: None,
+            }
+        );
+    }
+
+    #[test]
+    fn rejects_corrupt_dds() {
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&dds(4, 8)[..128])),
+            (ImageFormat::Dds, "file is truncated")
+        );
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(b"DDS \x00")),
+            (ImageFormat::Dds, "invalid header size")
+        );
+        assert_eq!(
+            corrupt_reason(ImageInfo::parse(&dds(0, 8))),
+            (ImageFormat::Dds, "image has no pixels")
+        );
+    }
+}
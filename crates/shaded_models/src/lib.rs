@@ -1,3 +1,12 @@
+pub mod addon;
 pub mod collection;
 pub mod collection_configuration;
+pub mod collection_lock;
+pub mod dependency;
+pub mod effect;
+pub mod image;
+pub mod license;
+pub mod preset;
+pub mod reshade;
+pub mod schema;
 pub mod shaderpack;
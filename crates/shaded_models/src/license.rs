@@ -0,0 +1,369 @@
+// References:
+//  - https://spdx.org/licenses/
+//  - https://spdx.github.io/spdx-spec/v2.3/SPDX-license-expressions/
+
+use spdx::Expression;
+
+/// Phrases that identify a license when all of them are found in its title, checked in order.
+///
+/// Only the first few lines of a license file are matched against these, as the text of a license often
+/// mentions other licenses, e.g. the GPL-3.0-only refers to the GNU Affero General Public License. Licenses
+/// that are variants of another must come before it, e.g. the non-commercial Creative Commons licenses
+/// before the plain attribution ones.
+const LICENSE_TITLES: &[(&str, &[&str])] = &[
+    (
+        "AGPL-3.0-only",
+        &["gnu affero general public license version 3"],
+    ),
+    (
+        "LGPL-3.0-only",
+        &["gnu lesser general public license version 3"],
+    ),
+    (
+        "LGPL-2.1-only",
+        &["gnu lesser general public license version 2.1"],
+    ),
+    (
+        "LGPL-2.0-only",
+        &["gnu library general public license version 2"],
+    ),
+    ("GPL-3.0-only", &["gnu general public license version 3"]),
+    ("GPL-2.0-only", &["gnu general public license version 2"]),
+    ("MPL-2.0", &["mozilla public license version 2.0"]),
+    ("Apache-2.0", &["apache license version 2.0"]),
+    ("CC0-1.0", &["cc0 1.0 universal"]),
+    (
+        "CC-BY-NC-ND-4.0",
+        &["attribution-noncommercial-noderivatives 4.0"],
+    ),
+    (
+        "CC-BY-NC-SA-4.0",
+        &["attribution-noncommercial-sharealike 4.0"],
+    ),
+    ("CC-BY-NC-4.0", &["attribution-noncommercial 4.0"]),
+    ("CC-BY-ND-4.0", &["attribution-noderivatives 4.0"]),
+    ("CC-BY-SA-4.0", &["attribution-sharealike 4.0"]),
+    ("CC-BY-4.0", &["attribution 4.0 international"]),
+    (
+        "CC-BY-NC-ND-3.0",
+        &["attribution-noncommercial-noderivs 3.0"],
+    ),
+    (
+        "CC-BY-NC-SA-3.0",
+        &["attribution-noncommercial-sharealike 3.0"],
+    ),
+    ("CC-BY-NC-3.0", &["attribution-noncommercial 3.0"]),
+    ("CC-BY-ND-3.0", &["attribution-noderivs 3.0"]),
+    ("CC-BY-SA-3.0", &["attribution-sharealike 3.0"]),
+    ("CC-BY-3.0", &["creative commons", "attribution 3.0"]),
+    (
+        "Unlicense",
+        &["this is free and unencumbered software released into the public domain"],
+    ),
+    ("WTFPL", &["do what the fuck you want to public license"]),
+];
+
+/// How many non-empty lines at the start of a license file are treated as its title.
+const LICENSE_TITLE_LINES: usize = 3;
+
+/// Phrases that identify a license without a standard title when all of them are found in its text,
+/// checked in order after [`LICENSE_TITLES`].
+///
+/// Licenses that are variants of another must come before it, e.g. `BSD-3-Clause` before `BSD-2-Clause`.
+const LICENSE_FINGERPRINTS: &[(&str, &[&str])] = &[
+    (
+        "BSD-3-Clause",
+        &[
+            "redistribution and use in source and binary forms",
+            "neither the name",
+        ],
+    ),
+    (
+        "BSD-2-Clause",
+        &["redistribution and use in source and binary forms"],
+    ),
+    (
+        "ISC",
+        &["permission to use, copy, modify, and/or distribute this software for any purpose"],
+    ),
+    (
+        "MIT",
+        &[
+            "permission is hereby granted, free of charge",
+            "the above copyright notice and this permission notice shall be included",
+        ],
+    ),
+    (
+        "Zlib",
+        &[
+            "provided 'as-is'",
+            "altered source versions must be plainly marked",
+        ],
+    ),
+];
+
+/// Detect the SPDX identifier of a license from the text of a license file.
+///
+/// GNU licenses are detected as their `-only` variant, as the license text is the same for the `-or-later`
+/// one and only the notices of the licensed files can tell them apart.
+pub fn detect_license(text: &str) -> Option<&'static str> {
+    let title = normalize_license_text(
+        &text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .take(LICENSE_TITLE_LINES)
+            .collect::<Vec<_>>()
+            .join("\n"),
+    );
+    let text = normalize_license_text(text);
+    let find = |fingerprints: &[(&'static str, &[&str])], text: &str| {
+        fingerprints
+            .iter()
+            .find(|(_, phrases)| phrases.iter().all(|phrase| text.contains(phrase)))
+            .map(|(id, _)| *id)
+    };
+    find(LICENSE_TITLES, &title).or_else(|| find(LICENSE_FINGERPRINTS, &text))
+}
+
+/// Whether a license expression includes the license with the given SPDX identifier.
+///
+/// The GNU `-only` and `-or-later` variants of a license are treated as the same license.
+pub fn expression_includes(expression: &Expression, license_id: &str) -> bool {
+    expression.requirements().any(|requirement| {
+        requirement
+            .req
+            .license
+            .id()
+            .is_some_and(|id| gnu_license_family(id.name) == gnu_license_family(license_id))
+    })
+}
+
+/// Whether a license identifier is matched by an identifier listed in a license policy.
+///
+/// A GNU license listed without a suffix, e.g. `GPL-3.0`, matches both its `-only` and `-or-later`
+/// variants, while a listed variant only matches itself.
+pub fn license_id_matches(license_id: &str, listed: &str) -> bool {
+    license_id == listed || gnu_license_family(license_id) == listed
+}
+
+/// Get the identifier of a GNU license without its `-only` or `-or-later` suffix.
+fn gnu_license_family(license_id: &str) -> &str {
+    license_id
+        .strip_suffix("-only")
+        .or_else(|| license_id.strip_suffix("-or-later"))
+        .unwrap_or(license_id)
+}
+
+/// SPDX identifiers of the licenses that forbid commercial use.
+const NON_COMMERCIAL_LICENSES: &[&str] = &[
+    "CC-BY-NC-1.0",
+    "CC-BY-NC-2.0",
+    "CC-BY-NC-2.5",
+    "CC-BY-NC-3.0",
+    "CC-BY-NC-3.0-DE",
+    "CC-BY-NC-4.0",
+    "CC-BY-NC-ND-1.0",
+    "CC-BY-NC-ND-2.0",
+    "CC-BY-NC-ND-2.5",
+    "CC-BY-NC-ND-3.0",
+    "CC-BY-NC-ND-3.0-DE",
+    "CC-BY-NC-ND-3.0-IGO",
+    "CC-BY-NC-ND-4.0",
+    "CC-BY-NC-SA-1.0",
+    "CC-BY-NC-SA-2.0",
+    "CC-BY-NC-SA-2.0-DE",
+    "CC-BY-NC-SA-2.0-FR",
+    "CC-BY-NC-SA-2.0-UK",
+    "CC-BY-NC-SA-2.5",
+    "CC-BY-NC-SA-3.0",
+    "CC-BY-NC-SA-3.0-DE",
+    "CC-BY-NC-SA-3.0-IGO",
+    "CC-BY-NC-SA-4.0",
+    "NCGL-UK-2.0",
+    "PolyForm-Noncommercial-1.0.0",
+];
+
+/// Whether the license with the given SPDX identifier forbids commercial use.
+pub fn is_non_commercial(license_id: &str) -> bool {
+    NON_COMMERCIAL_LICENSES.contains(&license_id)
+}
+
+/// Phrases in a license file that indicate custom terms forbidding redistribution.
+const NO_REDISTRIBUTION_PHRASES: &[&str] = &[
+    "do not redistribute",
+    "don't redistribute",
+    "not be redistributed",
+    "not permitted to redistribute",
+    "redistribution is not permitted",
+    "redistribution is prohibited",
+    "redistribution is not allowed",
+];
+
+/// The license of a shaderpack, resolved from its manifest and license file.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct ShaderPackLicense {
+    /// The SPDX expression declared in the manifest, or detected from the license file if none was declared.
+    pub expression: Option<String>,
+    /// Whether [`ShaderPackLicense::expression`] was detected from the license file rather than declared.
+    pub detected: bool,
+    /// Whether the license file contains custom terms that forbid redistribution.
+    pub forbids_redistribution: bool,
+}
+
+impl ShaderPackLicense {
+    /// Resolve the license of a shaderpack from its declared SPDX expression and license file text.
+    pub fn resolve(declared: Option<&str>, license_text: Option<&str>) -> Self {
+        let forbids_redistribution = license_text
+            .map(normalize_license_text)
+            .is_some_and(|text| {
+                NO_REDISTRIBUTION_PHRASES
+                    .iter()
+                    .any(|phrase| text.contains(phrase))
+            });
+
+        match declared {
+            Some(declared) => Self {
+                expression: Some(declared.to_owned()),
+                detected: false,
+                forbids_redistribution,
+            },
+            None => Self {
+                expression: license_text.and_then(detect_license).map(str::to_owned),
+                detected: true,
+                forbids_redistribution,
+            },
+        }
+    }
+}
+
+/// Normalize whitespace, casing and quotes so line wrapping and formatting do not affect matching.
+fn normalize_license_text(text: &str) -> String {
+    text.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+        .replace(['‘', '’'], "'")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn license_text(id: &str) -> &'static str {
+        spdx::license_id(id)
+            .unwrap_or_else(|| panic!("{} is not an SPDX license", id))
+            .text()
+    }
+
+    #[test]
+    fn detects_licenses_from_their_text() {
+        let cases = [
+            ("AGPL-3.0-only", "AGPL-3.0-only"),
+            ("GPL-3.0-only", "GPL-3.0-only"),
+            ("GPL-2.0-only", "GPL-2.0-only"),
+            ("LGPL-3.0-only", "LGPL-3.0-only"),
+            ("LGPL-2.1-only", "LGPL-2.1-only"),
+            ("LGPL-2.0-only", "LGPL-2.0-only"),
+            ("MPL-2.0", "MPL-2.0"),
+            ("Apache-2.0", "Apache-2.0"),
+            ("CC0-1.0", "CC0-1.0"),
+            ("CC-BY-4.0", "CC-BY-4.0"),
+            ("CC-BY-SA-4.0", "CC-BY-SA-4.0"),
+            ("CC-BY-ND-4.0", "CC-BY-ND-4.0"),
+            ("CC-BY-NC-4.0", "CC-BY-NC-4.0"),
+            ("CC-BY-NC-SA-4.0", "CC-BY-NC-SA-4.0"),
+            ("CC-BY-NC-ND-4.0", "CC-BY-NC-ND-4.0"),
+            ("CC-BY-3.0", "CC-BY-3.0"),
+            ("CC-BY-SA-3.0", "CC-BY-SA-3.0"),
+            ("CC-BY-NC-3.0", "CC-BY-NC-3.0"),
+            ("BSD-3-Clause", "BSD-3-Clause"),
+            ("BSD-2-Clause", "BSD-2-Clause"),
+            ("ISC", "ISC"),
+            ("MIT", "MIT"),
+            ("Zlib", "Zlib"),
+            ("Unlicense", "Unlicense"),
+            ("WTFPL", "WTFPL"),
+        ];
+        for (id, expected) in cases {
+            assert_eq!(detect_license(license_text(id)), Some(expected), "{}", id);
+        }
+    }
+
+    #[test]
+    fn does_not_detect_other_licenses() {
+        for id in ["MPL-1.1", "GFDL-1.3-only", "Artistic-2.0", "GPL-1.0-only"] {
+            assert_eq!(detect_license(license_text(id)), None, "{}", id);
+        }
+    }
+
+    #[test]
+    fn ignores_licenses_mentioned_after_the_title() {
+        let text = "My Shaders\n\
+            Copyright (c) 2024 Someone\n\
+            All rights reserved.\n\
+            These shaders were inspired by a project under the\n\
+            GNU Affero General Public License version 3 and the\n\
+            Mozilla Public License version 2.0.\n";
+        assert_eq!(detect_license(text), None);
+    }
+
+    #[test]
+    fn detects_licenses_regardless_of_wrapping() {
+        let text = "  GNU GENERAL PUBLIC LICENSE\r\n     Version 3,\r\n 29 June 2007\r\n";
+        assert_eq!(detect_license(text), Some("GPL-3.0-only"));
+    }
+
+    #[test]
+    fn resolves_declared_and_detected_licenses() {
+        let mit = license_text("MIT");
+        assert_eq!(
+            ShaderPackLicense::resolve(Some("MIT OR Apache-2.0"), Some(mit)),
+            ShaderPackLicense {
+                expression: Some("MIT OR Apache-2.0".to_owned()),
+                detected: false,
+                forbids_redistribution: false,
+            }
+        );
+        assert_eq!(
+            ShaderPackLicense::resolve(None, Some(mit)),
+            ShaderPackLicense {
+                expression: Some("MIT".to_owned()),
+                detected: true,
+                forbids_redistribution: false,
+            }
+        );
+        assert!(
+            ShaderPackLicense::resolve(None, Some("All rights reserved. Do not redistribute."))
+                .forbids_redistribution
+        );
+    }
+
+    #[test]
+    fn matches_gnu_license_variants() {
+        let expression = Expression::parse("GPL-3.0-or-later OR MIT").unwrap();
+        assert!(expression_includes(&expression, "GPL-3.0"));
+        assert!(expression_includes(&expression, "GPL-3.0-only"));
+        assert!(expression_includes(&expression, "MIT"));
+        assert!(!expression_includes(&expression, "AGPL-3.0-only"));
+
+        assert!(license_id_matches("GPL-3.0-or-later", "GPL-3.0"));
+        assert!(license_id_matches("GPL-3.0-only", "GPL-3.0-only"));
+        assert!(!license_id_matches("GPL-3.0-or-later", "GPL-3.0-only"));
+        assert!(!license_id_matches("LGPL-3.0-only", "GPL-3.0"));
+    }
+
+    #[test]
+    fn detects_non_commercial_licenses() {
+        for id in NON_COMMERCIAL_LICENSES {
+            assert!(
+                spdx::license_id(id).is_some(),
+                "{} is not an SPDX license",
+                id
+            );
+        }
+        assert!(is_non_commercial("CC-BY-NC-SA-4.0"));
+        assert!(is_non_commercial("PolyForm-Noncommercial-1.0.0"));
+        assert!(!is_non_commercial("CC-BY-SA-4.0"));
+        assert!(!is_non_commercial("NCSA"));
+    }
+}
@@ -0,0 +1,323 @@
+// References:
+//  - https://github.com/crosire/reshade/blob/main/source/runtime.cpp
+
+use crate::effect::EffectFile;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// A ReShade preset (`.ini`) file.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct Preset {
+    /// The techniques enabled by this preset from the `Techniques=` entry.
+    pub techniques: Vec<PresetTechnique>,
+    /// The order of techniques from the `TechniqueSorting=` entry.
+    pub technique_sorting: Vec<PresetTechnique>,
+    /// Uniform values keyed by the effect file name of their section, then the uniform name.
+    pub sections: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// A technique reference inside of a preset, written as `Technique@Effect.fx` or just `Technique`.
+#[derive(Debug, PartialEq, Eq, Default, Clone)]
+pub struct PresetTechnique {
+    pub name: String,
+    pub effect_file: Option<String>,
+}
+
+/// A reference inside of a preset that does not resolve to any of the available effects.
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum PresetReferenceError {
+    #[error("technique '{0}' is not provided by any effect")]
+    UnknownTechnique(String),
+
+    #[error("technique '{0}' is not provided by effect '{1}'")]
+    TechniqueNotInEffect(String, String),
+
+    #[error("effect file '{0}' referenced by technique '{1}' does not exist")]
+    UnknownTechniqueEffect(String, String),
+
+    #[error("uniform section '[{0}]' does not match any effect file")]
+    UnknownSection(String),
+}
+
+impl PresetReferenceError {
+    /// The reference as it is written in the preset, e.g. `Bloom@Bloom.fx` or `[Bloom.fx]`.
+    pub fn reference(&self) -> String {
+        match self {
+            PresetReferenceError::UnknownTechnique(technique) => technique.clone(),
+            PresetReferenceError::TechniqueNotInEffect(technique, effect_file)
+            | PresetReferenceError::UnknownTechniqueEffect(effect_file, technique) => {
+                format!("{}@{}", technique, effect_file)
+            }
+            PresetReferenceError::UnknownSection(section) => format!("[{}]", section),
+        }
+    }
+}
+
+impl PresetTechnique {
+    fn parse(entry: &str) -> Self {
+        match entry.split_once('@') {
+            Some((name, effect_file)) => Self {
+                name: name.trim().to_owned(),
+                effect_file: Some(effect_file.trim().to_owned()),
+            },
+            None => Self {
+                name: entry.trim().to_owned(),
+                effect_file: None,
+            },
+        }
+    }
+}
+
+impl Preset {
+    /// Parse a preset from the given ini contents.
+    pub fn parse(source: &str) -> Self {
+        let mut preset = Self::default();
+        let mut section: Option<String> = None;
+
+        for line in source.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with(';') || line.starts_with('#') {
+                continue;
+            }
+
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                let name = name.trim().to_owned();
+                preset.sections.entry(name.clone()).or_default();
+                section = Some(name);
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            let (key, value) = (key.trim(), value.trim());
+
+            match &section {
+                Some(section) => {
+                    preset
+                        .sections
+                        .entry(section.clone())
+                        .or_default()
+                        .insert(key.to_owned(), value.to_owned());
+                }
+                None if key == "Techniques" => preset.techniques = parse_technique_list(value),
+                None if key == "TechniqueSorting" => {
+                    preset.technique_sorting = parse_technique_list(value)
+                }
+                None => {}
+            }
+        }
+
+        preset
+    }
+
+    /// Find all techniques, effect files and uniform sections of this preset that do not resolve
+    /// to one of the given effects.
+    ///
+    /// # Arguments
+    /// * `effects`: The available effects alongside the file name ReShade identifies them by (e.g. `Bloom.fx`).
+    pub fn unresolved_references(
+        &self,
+        effects: &[(String, EffectFile)],
+    ) -> Vec<PresetReferenceError> {
+        let find_effect = |file_name: &str| {
+            effects
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(file_name))
+                .map(|(_, effect)| effect)
+        };
+        let mut errors = vec![];
+
+        for technique in self.techniques.iter().chain(&self.technique_sorting) {
+            let error = match &technique.effect_file {
+                Some(effect_file) => match find_effect(effect_file) {
+                    Some(effect) if effect.techniques.iter().any(|t| t.name == technique.name) => {
+                        continue
+                    }
+                    Some(_) => PresetReferenceError::TechniqueNotInEffect(
+                        technique.name.clone(),
+                        effect_file.clone(),
+                    ),
+                    None => PresetReferenceError::UnknownTechniqueEffect(
+                        effect_file.clone(),
+                        technique.name.clone(),
+                    ),
+                },
+                None if effects
+                    .iter()
+                    .flat_map(|(_, effect)| &effect.techniques)
+                    .any(|t| t.name == technique.name) =>
+                {
+                    continue
+                }
+                None => PresetReferenceError::UnknownTechnique(technique.name.clone()),
+            };
+            if !errors.contains(&error) {
+                errors.push(error);
+            }
+        }
+
+        for section in self.sections.keys() {
+            if find_effect(section).is_none() {
+                errors.push(PresetReferenceError::UnknownSection(section.clone()));
+            }
+        }
+
+        errors
+    }
+}
+
+fn parse_technique_list(value: &str) -> Vec<PresetTechnique> {
+    value
+        .split(',')
+        .filter(|entry| !entry.trim().is_empty())
+        .map(PresetTechnique::parse)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::effect::EffectTechnique;
+
+    fn technique(name: &str, effect_file: Option<&str>) -> PresetTechnique {
+        PresetTechnique {
+            name: name.to_owned(),
+            effect_file: effect_file.map(str::to_owned),
+        }
+    }
+
+    fn effect(file_name: &str, techniques: &[&str]) -> (String, EffectFile) {
+        (
+            file_name.to_owned(),
+            EffectFile {
+                techniques: techniques
+                    .iter()
+                    .map(|name| EffectTechnique {
+                        name: name.to_string(),
+                        ..Default::default()
+                    })
+                    .collect(),
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn parses_technique_lists_and_sections() {
+        let preset = Preset::parse(
+            "
+            ; A comment
+            # Another comment
+            PreprocessorDefinitions=
+            Techniques=Bloom@Bloom.fx, Vignette ,,Sharpen@ Sharpen.fx
+            TechniqueSorting=Vignette,Bloom@Bloom.fx
+
+            [Bloom.fx]
+            ; Comments inside of a section
+            BloomStrength = 0.5
+            BloomColor=1.0,0.9,0.8
+
+            [ Vignette.fx ]
+            Ignored line
+            Radius=2=3
+            [Empty.fx]
+            ",
+        );
+
+        assert_eq!(
+            preset.techniques,
+            [
+                technique("Bloom", Some("Bloom.fx")),
+                technique("Vignette", None),
+                technique("Sharpen", Some("Sharpen.fx")),
+            ]
+        );
+        assert_eq!(
+            preset.technique_sorting,
+            [
+                technique("Vignette", None),
+                technique("Bloom", Some("Bloom.fx"))
+            ]
+        );
+        assert_eq!(
+            preset.sections,
+            BTreeMap::from([
+                (
+                    "Bloom.fx".to_owned(),
+                    BTreeMap::from([
+                        ("BloomStrength".to_owned(), "0.5".to_owned()),
+                        ("BloomColor".to_owned(), "1.0,0.9,0.8".to_owned()),
+                    ])
+                ),
+                (
+                    "Vignette.fx".to_owned(),
+                    BTreeMap::from([("Radius".to_owned(), "2=3".to_owned())])
+                ),
+                ("Empty.fx".to_owned(), BTreeMap::new()),
+            ])
+        );
+    }
+
+    #[test]
+    fn keys_inside_of_sections_are_not_technique_lists() {
+        let preset = Preset::parse("[Bloom.fx]\nTechniques=Bloom\n");
+        assert!(preset.techniques.is_empty());
+        assert_eq!(preset.sections["Bloom.fx"]["Techniques"], "Bloom");
+    }
+
+    #[test]
+    fn resolves_references_to_effects() {
+        let preset = Preset::parse(
+            "
+            Techniques=Bloom@bloom.FX,Vignette,Sharpen@Bloom.fx,Missing,Grain@Grain.fx
+            TechniqueSorting=Missing,Bloom@Bloom.fx
+            [Bloom.fx]
+            Strength=1
+            [Unknown.fx]
+            Value=1
+            ",
+        );
+        let effects = [
+            effect("Bloom.fx", &["Bloom"]),
+            effect("Vignette.fx", &["Vignette"]),
+        ];
+
+        let errors = preset.unresolved_references(&effects);
+        assert_eq!(
+            errors,
+            [
+                PresetReferenceError::TechniqueNotInEffect(
+                    "Sharpen".to_owned(),
+                    "Bloom.fx".to_owned()
+                ),
+                PresetReferenceError::UnknownTechnique("Missing".to_owned()),
+                PresetReferenceError::UnknownTechniqueEffect(
+                    "Grain.fx".to_owned(),
+                    "Grain".to_owned()
+                ),
+                PresetReferenceError::UnknownSection("Unknown.fx".to_owned()),
+            ]
+        );
+        assert_eq!(
+            errors
+                .iter()
+                .map(|error| error.reference())
+                .collect::<Vec<_>>(),
+            [
+                "Sharpen@Bloom.fx",
+                "Missing",
+                "Grain@Grain.fx",
+                "[Unknown.fx]"
+            ]
+        );
+        assert_eq!(
+            errors[2].to_string(),
+            "effect file 'Grain.fx' referenced by technique 'Grain' does not exist"
+        );
+        assert!(
+            Preset::parse("Techniques=Bloom,Vignette@Vignette.fx\n[bloom.fx]\n")
+                .unresolved_references(&effects)
+                .is_empty()
+        );
+    }
+}
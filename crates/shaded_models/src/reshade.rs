@@ -0,0 +1,435 @@
+// References:
+//  - https://github.com/crosire/reshade/tags
+
+use schemars::{
+    gen::SchemaGenerator,
+    schema::{InstanceType, Metadata, Schema, SchemaObject, SubschemaValidation},
+    JsonSchema,
+};
+use semver::Version;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt, str::FromStr};
+use thiserror::Error;
+
+/// The ReShade versions a shaderpack or collection works with.
+///
+/// Requirements are written as one or more alternatives separated by `||`, each made up of comparators
+/// separated by `,` that must all match, e.g. `5.x || >=6.0`. A comparator is a partial version with an
+/// optional `=`, `>`, `>=`, `<` or `<=` operator, or a trailing `+` as a shorthand for `>=`. A partial
+/// version without an operator matches every release it covers, so `6` and `6.x` match all of ReShade 6
+/// while `6.1.1` only matches that exact release. `*` matches every version.
+///
+/// A plain integer is read as the whole major version, which is how older manifests declared it.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+pub struct ReShadeVersionRequirement {
+    /// The matching versions as sorted, non-overlapping ranges. Never empty.
+    ranges: Vec<ReShadeVersionRange>,
+}
+
+/// A range of ReShade versions from `min` up to, but not including, `max`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone)]
+struct ReShadeVersionRange {
+    min: Version,
+    max: Option<Version>,
+}
+
+#[derive(Error, Debug, PartialEq, Eq)]
+pub enum ReShadeVersionRequirementErrorKind {
+    #[error("the requirement is empty")]
+    Empty,
+
+    #[error("'{0}' is not a valid version")]
+    InvalidVersion(String),
+
+    #[error("'{0}' can never match a version")]
+    Unsatisfiable(String),
+}
+
+impl Default for ReShadeVersionRequirement {
+    fn default() -> Self {
+        Self::any()
+    }
+}
+
+impl ReShadeVersionRequirement {
+    /// A requirement matching every ReShade version.
+    pub fn any() -> Self {
+        Self {
+            ranges: vec![ReShadeVersionRange {
+                min: Version::new(0, 0, 0),
+                max: None,
+            }],
+        }
+    }
+
+    /// A requirement matching every release of a major ReShade version, or `None` if the major version is
+    /// too large to have an upper bound.
+    pub fn major(major: u64) -> Option<Self> {
+        Some(Self {
+            ranges: vec![ReShadeVersionRange {
+                min: Version::new(major, 0, 0),
+                max: Some(Version::new(major.checked_add(1)?, 0, 0)),
+            }],
+        })
+    }
+
+    /// Whether the given ReShade version satisfies this requirement.
+    pub fn matches(&self, version: &Version) -> bool {
+        self.ranges.iter().any(|range| range.contains(version))
+    }
+
+    /// Get the versions that satisfy both this requirement and another, if there are any.
+    pub fn intersection(&self, other: &Self) -> Option<Self> {
+        Self::from_ranges(
+            self.ranges
+                .iter()
+                .flat_map(|range| {
+                    other
+                        .ranges
+                        .iter()
+                        .filter_map(|other| range.intersection(other))
+                })
+                .collect(),
+        )
+    }
+
+    /// Whether at least one ReShade version satisfies both this requirement and another.
+    pub fn overlaps(&self, other: &Self) -> bool {
+        self.intersection(other).is_some()
+    }
+
+    /// If this requirement matches exactly one whole major version, get that major version.
+    pub fn as_major(&self) -> Option<u64> {
+        match self.ranges.as_slice() {
+            [range] => range.as_major(),
+            _ => None,
+        }
+    }
+
+    /// Sort and merge ranges so that equal requirements have an equal representation.
+    fn from_ranges(mut ranges: Vec<ReShadeVersionRange>) -> Option<Self> {
+        ranges.sort();
+        let mut merged: Vec<ReShadeVersionRange> = Vec::with_capacity(ranges.len());
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if last.max.as_ref().is_none_or(|max| range.min <= *max) => {
+                    last.max = match (last.max.take(), range.max) {
+                        (Some(a), Some(b)) => Some(a.max(b)),
+                        _ => None,
+                    };
+                }
+                _ => merged.push(range),
+            }
+        }
+
+        if merged.is_empty() {
+            None
+        } else {
+            Some(Self { ranges: merged })
+        }
+    }
+}
+
+impl ReShadeVersionRange {
+    fn contains(&self, version: &Version) -> bool {
+        *version >= self.min && self.max.as_ref().is_none_or(|max| version < max)
+    }
+
+    fn intersection(&self, other: &Self) -> Option<Self> {
+        let min = (&self.min).max(&other.min).clone();
+        let max = match (&self.max, &other.max) {
+            (Some(a), Some(b)) => Some(a.min(b).clone()),
+            (max, None) | (None, max) => max.clone(),
+        };
+        match &max {
+            Some(max) if *max <= min => None,
+            _ => Some(Self { min, max }),
+        }
+    }
+
+    fn as_major(&self) -> Option<u64> {
+        let max = self.max.as_ref()?;
+        (self.min.minor == 0
+            && self.min.patch == 0
+            && next(self.min.major).is_some_and(|major| *max == Version::new(major, 0, 0)))
+        .then_some(self.min.major)
+    }
+}
+
+impl fmt::Display for ReShadeVersionRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let min = &self.min;
+        let Some(max) = &self.max else {
+            return match *min == Version::new(0, 0, 0) {
+                true => f.write_str("*"),
+                false => write!(f, ">={}", min),
+            };
+        };
+
+        if let Some(major) = self.as_major() {
+            write!(f, "{}.x", major)
+        } else if min.patch == 0
+            && next(min.minor).is_some_and(|minor| *max == Version::new(min.major, minor, 0))
+        {
+            write!(f, "{}.{}.x", min.major, min.minor)
+        } else if next(min.patch)
+            .is_some_and(|patch| *max == Version::new(min.major, min.minor, patch))
+        {
+            write!(f, "{}", min)
+        } else if *min == Version::new(0, 0, 0) {
+            write!(f, "<{}", max)
+        } else {
+            write!(f, ">={}, <{}", min, max)
+        }
+    }
+}
+
+impl fmt::Display for ReShadeVersionRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, range) in self.ranges.iter().enumerate() {
+            if index > 0 {
+                f.write_str(" || ")?;
+            }
+            write!(f, "{}", range)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for ReShadeVersionRequirement {
+    type Err = ReShadeVersionRequirementErrorKind;
+
+    fn from_str(requirement: &str) -> Result<Self, Self::Err> {
+        if requirement.trim().is_empty() {
+            return Err(ReShadeVersionRequirementErrorKind::Empty);
+        }
+
+        let mut ranges = vec![];
+        for alternative in requirement.split("||") {
+            let mut range = Some(ReShadeVersionRange {
+                min: Version::new(0, 0, 0),
+                max: None,
+            });
+            for comparator in alternative.split(',') {
+                let comparator = parse_comparator(comparator.trim())?;
+                range = range.and_then(|range| range.intersection(&comparator));
+            }
+            ranges.extend(range);
+        }
+
+        Self::from_ranges(ranges).ok_or_else(|| {
+            ReShadeVersionRequirementErrorKind::Unsatisfiable(requirement.trim().to_owned())
+        })
+    }
+}
+
+/// Parse a single comparator, e.g. `>=6.0`, into the range of versions it matches.
+fn parse_comparator(
+    comparator: &str,
+) -> Result<ReShadeVersionRange, ReShadeVersionRequirementErrorKind> {
+    let (operator, version) = [">=", "<=", ">", "<", "="]
+        .into_iter()
+        .find_map(|operator| Some((operator, comparator.strip_prefix(operator)?)))
+        .or_else(|| Some((">=", comparator.strip_suffix('+')?)))
+        .unwrap_or(("", comparator));
+
+    let (lower, upper) = parse_partial_version(version.trim())
+        .ok_or_else(|| ReShadeVersionRequirementErrorKind::InvalidVersion(comparator.to_owned()))?;
+    let unsatisfiable = || ReShadeVersionRequirementErrorKind::Unsatisfiable(comparator.to_owned());
+    let zero = Version::new(0, 0, 0);
+
+    Ok(match operator {
+        ">=" => ReShadeVersionRange {
+            min: lower,
+            max: None,
+        },
+        ">" => ReShadeVersionRange {
+            min: upper.ok_or_else(unsatisfiable)?,
+            max: None,
+        },
+        "<" if lower == zero => return Err(unsatisfiable()),
+        "<" => ReShadeVersionRange {
+            min: zero,
+            max: Some(lower),
+        },
+        "<=" => ReShadeVersionRange {
+            min: zero,
+            max: upper,
+        },
+        _ => ReShadeVersionRange {
+            min: lower,
+            max: upper,
+        },
+    })
+}
+
+/// Parse a version where trailing components may be left out or written as `x` or `*`.
+///
+/// Returns the lowest version it covers and the first version after it, or `None` if it covers every
+/// version after the lowest. Versions whose last component is too large to have a next version are
+/// rejected.
+fn parse_partial_version(version: &str) -> Option<(Version, Option<Version>)> {
+    let mut components = vec![];
+    let mut wildcard = false;
+    for component in version.split('.') {
+        match component {
+            "x" | "X" | "*" => wildcard = true,
+            _ if wildcard => return None,
+            component => components.push(component.parse::<u64>().ok()?),
+        }
+    }
+
+    Some(match components.as_slice() {
+        [] => (Version::new(0, 0, 0), None),
+        [major] => (
+            Version::new(*major, 0, 0),
+            Some(Version::new(next(*major)?, 0, 0)),
+        ),
+        [major, minor] => (
+            Version::new(*major, *minor, 0),
+            Some(Version::new(*major, next(*minor)?, 0)),
+        ),
+        [major, minor, patch] => (
+            Version::new(*major, *minor, *patch),
+            Some(Version::new(*major, *minor, next(*patch)?)),
+        ),
+        _ => return None,
+    })
+}
+
+/// Get the version component after the given one, or `None` if it is the largest.
+fn next(component: u64) -> Option<u64> {
+    component.checked_add(1)
+}
+
+impl Serialize for ReShadeVersionRequirement {
+    /// Whole major versions are written as an integer so that older readers can still load them.
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self.as_major().and_then(|major| u8::try_from(major).ok()) {
+            Some(major) => serializer.serialize_u8(major),
+            None => serializer.collect_str(self),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ReShadeVersionRequirement {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RequirementVisitor;
+
+        impl de::Visitor<'_> for RequirementVisitor {
+            type Value = ReShadeVersionRequirement;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a major ReShade version or a version requirement string")
+            }
+
+            fn visit_u64<E: de::Error>(self, major: u64) -> Result<Self::Value, E> {
+                ReShadeVersionRequirement::major(major).ok_or_else(|| {
+                    E::custom(ReShadeVersionRequirementErrorKind::InvalidVersion(
+                        major.to_string(),
+                    ))
+                })
+            }
+
+            fn visit_i64<E: de::Error>(self, major: i64) -> Result<Self::Value, E> {
+                let major = u64::try_from(major)
+                    .map_err(|_| E::invalid_value(de::Unexpected::Signed(major), &self))?;
+                self.visit_u64(major)
+            }
+
+            fn visit_str<E: de::Error>(self, requirement: &str) -> Result<Self::Value, E> {
+                requirement.parse().map_err(E::custom)
+            }
+        }
+
+        deserializer.deserialize_any(RequirementVisitor)
+    }
+}
+
+impl JsonSchema for ReShadeVersionRequirement {
+    fn schema_name() -> String {
+        "ReShadeVersionRequirement".to_owned()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+        SchemaObject {
+            metadata: Some(Box::new(Metadata {
+                description: Some(
+                    "A major ReShade version, or a version requirement such as `5.x || >=6.0`."
+                        .to_owned(),
+                ),
+                ..Default::default()
+            })),
+            subschemas: Some(Box::new(SubschemaValidation {
+                any_of: Some(vec![
+                    gen.subschema_for::<u8>(),
+                    SchemaObject {
+                        instance_type: Some(InstanceType::String.into()),
+                        ..Default::default()
+                    }
+                    .into(),
+                ]),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+        .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requirement(requirement: &str) -> ReShadeVersionRequirement {
+        requirement.parse().unwrap()
+    }
+
+    #[test]
+    fn requirements_are_parsed_and_displayed() {
+        assert_eq!(requirement("6").to_string(), "6.x");
+        assert_eq!(requirement("5.x || >=6.1").to_string(), "5.x || >=6.1.0");
+        assert_eq!(requirement(">=6.1, <6.2").to_string(), "6.1.x");
+        assert_eq!(requirement("=6.1.1").to_string(), "6.1.1");
+        assert_eq!(requirement("6.1+").to_string(), ">=6.1.0");
+        assert_eq!(requirement("*").to_string(), "*");
+        assert!(requirement("5.x || 6.x").matches(&Version::new(6, 3, 0)));
+        assert!(!requirement("<6").matches(&Version::new(6, 0, 0)));
+    }
+
+    #[test]
+    fn largest_version_components_are_rejected() {
+        let max = u64::MAX;
+        for version in [
+            format!("{}", max),
+            format!("6.{}", max),
+            format!("6.1.{}", max),
+            format!(">={}", max),
+        ] {
+            assert!(
+                matches!(
+                    version.parse::<ReShadeVersionRequirement>(),
+                    Err(ReShadeVersionRequirementErrorKind::InvalidVersion(_))
+                ),
+                "{}",
+                version
+            );
+        }
+        assert_eq!(
+            requirement(&format!("{}.0", max)).to_string(),
+            format!("{}.0.x", max)
+        );
+        assert!(ReShadeVersionRequirement::major(max).is_none());
+    }
+
+    #[test]
+    fn integer_major_versions_are_deserialized() {
+        let parse = serde_json::from_str::<ReShadeVersionRequirement>;
+        assert_eq!(parse("6").unwrap(), requirement("6.x"));
+        assert!(parse("-1").is_err());
+        assert!(parse("18446744073709551615")
+            .unwrap_err()
+            .to_string()
+            .contains("'18446744073709551615' is not a valid version"));
+    }
+}
@@ -0,0 +1,51 @@
+// References:
+//  - https://json-schema.org/draft-07/schema
+//  - https://github.com/Pictomancers/shaded-schemas
+
+use crate::{
+    collection::CollectionManifest, collection_configuration::CollectionConfiguration,
+    shaderpack::ShaderPackManifest,
+};
+use schemars::{
+    schema::{InstanceType, Metadata, RootSchema, SchemaObject},
+    schema_for,
+};
+
+/// The documents read or written by Shaded that a JSON Schema can be generated for.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SchemaKind {
+    ShaderPackManifest,
+    CollectionManifest,
+    CollectionConfiguration,
+}
+
+impl SchemaKind {
+    /// Generate the JSON Schema of this document from its model, so the schema always matches what is read.
+    pub fn schema(&self) -> RootSchema {
+        match self {
+            SchemaKind::ShaderPackManifest => schema_for!(ShaderPackManifest),
+            SchemaKind::CollectionManifest => schema_for!(CollectionManifest),
+            SchemaKind::CollectionConfiguration => {
+                let mut schema = schema_for!(CollectionConfiguration);
+
+                // Profiles are merged into the configuration before it is read, so they are not part of the model.
+                schema.schema.object().properties.insert(
+                    "profiles".to_owned(),
+                    SchemaObject {
+                        metadata: Some(Box::new(Metadata {
+                            description: Some(
+                                "Named partial configurations that can be merged over this configuration when building."
+                                    .to_owned(),
+                            ),
+                            ..Default::default()
+                        })),
+                        instance_type: Some(InstanceType::Object.into()),
+                        ..Default::default()
+                    }
+                    .into(),
+                );
+                schema
+            }
+        }
+    }
+}
@@ -1,37 +1,87 @@
 // References:
 //  - https://github.com/Pictomancers/shaded-schemas/tree/main/shaderpack
 
+use crate::{
+    effect::{EffectFile, EFFECT_FILE_EXTENSIONS},
+    preset::Preset,
+    reshade::ReShadeVersionRequirement,
+};
+use schemars::JsonSchema;
+use semver::Version;
 use serde::{Deserialize, Serialize};
 use std::{
-    fs,
+    fs, io,
     path::{Path, PathBuf},
 };
 use thiserror::Error;
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default, Clone)]
-#[serde(rename_all(serialize = "PascalCase", deserialize = "PascalCase"))]
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(
+    rename_all(serialize = "PascalCase", deserialize = "PascalCase"),
+    deny_unknown_fields
+)]
 pub struct ShaderPackManifest {
     pub manifest_version: u8,
+    /// The ReShade versions this shaderpack works with.
     #[serde(rename(serialize = "ReShadeVersion", deserialize = "ReShadeVersion"))]
-    pub reshade_version: u8,
+    pub reshade_version: ReShadeVersionRequirement,
     pub name: String,
+    /// The semver version of this shaderpack, e.g. `1.2.0`.
+    pub version: Option<Version>,
     pub authors: Vec<String>,
     pub description: String,
+    /// An SPDX license expression describing the license of this shaderpack, e.g. `MIT OR CC-BY-4.0`.
+    pub license: Option<String>,
     pub license_file: Option<PathBuf>,
     pub images: Option<Vec<String>>,
     pub shaders: Option<Vec<FileDeclaration>>,
     pub textures: Option<Vec<FileDeclaration>>,
     pub presets: Option<Vec<FileDeclaration>>,
     pub addons: Option<Vec<FileDeclaration>>,
+    /// Other shaderpacks that must be installed alongside this shaderpack.
+    pub dependencies: Option<Vec<ShaderPackDependency>>,
+}
+
+/// A shaderpack required by another shaderpack, referenced by its manifest name.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(
+    rename_all(serialize = "PascalCase", deserialize = "PascalCase"),
+    deny_unknown_fields
+)]
+pub struct ShaderPackDependency {
+    pub name: String,
+    /// A semver requirement the version of the dependency must match, e.g. `^1.2`.
+    pub version: Option<String>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Default, Clone)]
-#[serde(rename_all(serialize = "PascalCase", deserialize = "PascalCase"))]
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Default, Clone,
+)]
+#[serde(
+    rename_all(serialize = "PascalCase", deserialize = "PascalCase"),
+    deny_unknown_fields
+)]
 pub struct FileDeclaration {
     pub source: PathBuf,
     pub output: PathBuf,
 }
 
+/// The categories of files a shaderpack can declare.
+#[derive(
+    Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema, Clone, Copy,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum FileCategory {
+    Shaders,
+    Textures,
+    Presets,
+    Addons,
+}
+
 #[derive(Error, Debug)]
 pub enum FileDeclarationCopyErrorKind {
     #[error("unable to obtain base path from file output declaration")]
@@ -45,9 +95,124 @@ pub enum FileDeclarationCopyErrorKind {
 
     #[error("failed to create the copy source file to output path")]
     OutputFileCopyFailure(std::io::Error),
+
+    #[error("failed to write the source file contents to the output path")]
+    OutputFileWriteFailure(std::io::Error),
+}
+
+impl ShaderPackManifest {
+    /// Parse every declared shader that is a ReShade FX effect or header file.
+    ///
+    /// Shaders that cannot be read are skipped, as file existence is checked separately.
+    ///
+    /// # Arguments
+    /// * `read_file`: Reads the contents of a [`FileDeclaration::source`] path.
+    pub fn load_effects(
+        &self,
+        read_file: impl Fn(&Path) -> io::Result<Vec<u8>>,
+    ) -> Vec<(&FileDeclaration, EffectFile)> {
+        self.shaders
+            .iter()
+            .flatten()
+            .filter(|shader| shader.has_extension(&EFFECT_FILE_EXTENSIONS))
+            .filter_map(|shader| {
+                read_file(&shader.source).ok().map(|contents| {
+                    (
+                        shader,
+                        EffectFile::parse(&String::from_utf8_lossy(&contents)),
+                    )
+                })
+            })
+            .collect()
+    }
+
+    /// Parse every declared preset.
+    ///
+    /// Presets that cannot be read are skipped, as file existence is checked separately.
+    ///
+    /// # Arguments
+    /// * `read_file`: Reads the contents of a [`FileDeclaration::source`] path.
+    pub fn load_presets(
+        &self,
+        read_file: impl Fn(&Path) -> io::Result<Vec<u8>>,
+    ) -> Vec<(&FileDeclaration, Preset)> {
+        self.presets
+            .iter()
+            .flatten()
+            .filter_map(|preset| {
+                read_file(&preset.source)
+                    .ok()
+                    .map(|contents| (preset, Preset::parse(&String::from_utf8_lossy(&contents))))
+            })
+            .collect()
+    }
+}
+
+impl ShaderPackManifest {
+    /// Get the declared files of the given category.
+    pub fn files(&self, category: FileCategory) -> &[FileDeclaration] {
+        match category {
+            FileCategory::Shaders => self.shaders.as_deref(),
+            FileCategory::Textures => self.textures.as_deref(),
+            FileCategory::Presets => self.presets.as_deref(),
+            FileCategory::Addons => self.addons.as_deref(),
+        }
+        .unwrap_or_default()
+    }
+
+    /// Get the declared files of the given category for modification.
+    pub fn files_mut(&mut self, category: FileCategory) -> &mut Option<Vec<FileDeclaration>> {
+        match category {
+            FileCategory::Shaders => &mut self.shaders,
+            FileCategory::Textures => &mut self.textures,
+            FileCategory::Presets => &mut self.presets,
+            FileCategory::Addons => &mut self.addons,
+        }
+    }
+}
+
+impl FileCategory {
+    pub const ALL: [FileCategory; 4] = [
+        FileCategory::Shaders,
+        FileCategory::Textures,
+        FileCategory::Presets,
+        FileCategory::Addons,
+    ];
 }
 
 impl FileDeclaration {
+    /// Whether the [`FileDeclaration::source`] path has one of the given extensions, ignoring case.
+    pub fn has_extension(&self, extensions: &[&str]) -> bool {
+        self.source
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .is_some_and(|extension| {
+                extensions
+                    .iter()
+                    .any(|candidate| candidate.eq_ignore_ascii_case(extension))
+            })
+    }
+
+    /// The file name of the [`FileDeclaration::output`] path, which is how ReShade identifies effects.
+    pub fn output_file_name(&self) -> String {
+        self.output
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    /// The path of this file declaration inside of a collection archive, using `/` as the separator.
+    ///
+    /// # Arguments
+    /// * `directory_name`: The collection directory the file is placed in, e.g. [`crate::collection::constants::SHADER_DIRECTORY_NAME`].
+    pub fn archive_path(&self, directory_name: &str) -> String {
+        format!(
+            "{}/{}",
+            directory_name,
+            self.output.to_string_lossy().replace('\\', "/")
+        )
+    }
+
     /// Copy this file declaration to the given output directory and create all missing sub-directories in [`FileDeclaration::output`] while doing so.
     ///
     /// # Arguments
@@ -61,7 +226,7 @@ impl FileDeclaration {
         let original_path = input_base_path
             .join(&self.source)
             .canonicalize()
-            .map_err(|err| FileDeclarationCopyErrorKind::SourceFileCanonicalizationFailure(err))?;
+            .map_err(FileDeclarationCopyErrorKind::SourceFileCanonicalizationFailure)?;
         let output_path = output_base_path.join(&self.output);
 
         let Some(output_path_parent) = output_path.parent() else {
@@ -69,9 +234,34 @@ impl FileDeclaration {
         };
 
         fs::create_dir_all(output_path_parent)
-            .map_err(|err| FileDeclarationCopyErrorKind::OutputDirectoryCreateFailure(err))?;
+            .map_err(FileDeclarationCopyErrorKind::OutputDirectoryCreateFailure)?;
         fs::copy(original_path, output_path)
-            .map_err(|err| FileDeclarationCopyErrorKind::OutputFileCopyFailure(err))?;
+            .map_err(FileDeclarationCopyErrorKind::OutputFileCopyFailure)?;
+
+        Ok(())
+    }
+
+    /// Write the given contents of this file declaration to the given output directory, for sources that
+    /// are not plain files on disk, and create all missing sub-directories in [`FileDeclaration::output`] while doing so.
+    ///
+    /// # Arguments
+    /// * `contents`: The contents of the [`FileDeclaration::source`] file.
+    /// * `output_base_path`: The base output directory that the [`FileDeclaration::output`] path will be appended to.
+    pub fn write_to_output_path(
+        &self,
+        contents: &[u8],
+        output_base_path: &Path,
+    ) -> Result<(), FileDeclarationCopyErrorKind> {
+        let output_path = output_base_path.join(&self.output);
+
+        let Some(output_path_parent) = output_path.parent() else {
+            return Err(FileDeclarationCopyErrorKind::BasePathNotFound);
+        };
+
+        fs::create_dir_all(output_path_parent)
+            .map_err(FileDeclarationCopyErrorKind::OutputDirectoryCreateFailure)?;
+        fs::write(output_path, contents)
+            .map_err(FileDeclarationCopyErrorKind::OutputFileWriteFailure)?;
 
         Ok(())
     }